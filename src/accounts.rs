@@ -0,0 +1,310 @@
+use solana_program::{
+    account_info::{next_account_info, AccountInfo},
+    msg,
+    program_error::ProgramError,
+    program_pack::IsInitialized,
+    pubkey::Pubkey,
+    system_program,
+};
+
+use crate::state::RentShareAccount;
+
+use std::ops::Deref;
+
+/// Rent agreement account owned by this program
+pub struct AgreementAccount<'a, 'b>(&'a AccountInfo<'b>);
+
+impl<'a, 'b> AgreementAccount<'a, 'b> {
+    pub fn new(account: &'a AccountInfo<'b>, program_id: &Pubkey) -> Result<Self, ProgramError> {
+        if account.owner != program_id {
+            msg!("[RentShare] Rent agreement account is not owned by this program");
+            return Err(ProgramError::IncorrectProgramId);
+        }
+
+        Ok(Self(account))
+    }
+
+    pub fn info(&self) -> &'a AccountInfo<'b> {
+        self.0
+    }
+}
+
+/// Payer (Renter) account that signed the transaction, or a PDA signed for by a calling program
+pub struct PayerAccount<'a, 'b>(&'a AccountInfo<'b>);
+
+impl<'a, 'b> PayerAccount<'a, 'b> {
+    pub fn new(account: &'a AccountInfo<'b>) -> Result<Self, ProgramError> {
+        // PDA signatures from `invoke_signed` arrive as `is_signer` like any keypair signature
+        if !account.is_signer {
+            msg!(
+                "[RentShare] Payer {} must sign, or be signed for with invoke_signed when paying through CPI",
+                account.key
+            );
+            return Err(ProgramError::MissingRequiredSignature);
+        }
+
+        Ok(Self(account))
+    }
+
+    pub fn info(&self) -> &'a AccountInfo<'b> {
+        self.0
+    }
+}
+
+/// Payee (Owner) wallet able to spend the lamports sent to it. It must be writable because the
+/// system program credits it directly.
+pub struct PayeeAccount<'a, 'b>(&'a AccountInfo<'b>);
+
+impl<'a, 'b> PayeeAccount<'a, 'b> {
+    pub fn new(account: &'a AccountInfo<'b>) -> Result<Self, ProgramError> {
+        // Lamports sent to a program owned account cannot be spent by the payee
+        if *account.owner != system_program::id() {
+            msg!(
+                "[RentShare] Payee account must be a system owned wallet. Owner: {}",
+                account.owner
+            );
+            return Err(ProgramError::InvalidAccountData);
+        }
+
+        Ok(Self(account))
+    }
+
+    pub fn info(&self) -> &'a AccountInfo<'b> {
+        self.0
+    }
+}
+
+/// The system program, invoked for lamport transfers from wallets
+pub struct SystemProgramAccount<'a, 'b>(&'a AccountInfo<'b>);
+
+impl<'a, 'b> SystemProgramAccount<'a, 'b> {
+    pub fn new(account: &'a AccountInfo<'b>) -> Result<Self, ProgramError> {
+        if *account.key != system_program::id() {
+            msg!(
+                "[RentShare] Expected the system program but received {}",
+                account.key
+            );
+            return Err(ProgramError::IncorrectProgramId);
+        }
+
+        Ok(Self(account))
+    }
+
+    pub fn info(&self) -> &'a AccountInfo<'b> {
+        self.0
+    }
+}
+
+macro_rules! deref_account_info {
+    ($($wrapper:ident),*) => {
+        $(
+            impl<'a, 'b> Deref for $wrapper<'a, 'b> {
+                type Target = AccountInfo<'b>;
+
+                fn deref(&self) -> &Self::Target {
+                    self.0
+                }
+            }
+        )*
+    };
+}
+
+deref_account_info!(
+    AgreementAccount,
+    PayerAccount,
+    PayeeAccount,
+    SystemProgramAccount
+);
+
+/// Accounts for `PayRent`, `PrepayWholeLease` and `PayLateFees`
+pub struct PayRentAccounts<'a, 'b> {
+    pub agreement: AgreementAccount<'a, 'b>,
+    pub payee: PayeeAccount<'a, 'b>,
+    pub payer: PayerAccount<'a, 'b>,
+    pub system_program: SystemProgramAccount<'a, 'b>,
+}
+
+impl<'a, 'b> PayRentAccounts<'a, 'b> {
+    pub fn parse(
+        accounts: &'a [AccountInfo<'b>],
+        program_id: &Pubkey,
+    ) -> Result<Self, ProgramError> {
+        let accounts_iter = &mut accounts.iter();
+
+        let agreement = AgreementAccount::new(next_account_info(accounts_iter)?, program_id)?;
+        let payee_account = next_account_info(accounts_iter)?;
+        let payer = PayerAccount::new(next_account_info(accounts_iter)?)?;
+        let system_program = SystemProgramAccount::new(next_account_info(accounts_iter)?)?;
+
+        // Aliasing the agreement with either party would corrupt its state during the transfer
+        if agreement.key == payer.key || agreement.key == payee_account.key {
+            msg!("[RentShare] Rent agreement account must differ from the payer and payee");
+            return Err(ProgramError::InvalidArgument);
+        }
+
+        // Only the payer recorded on the agreement may pay against it. Unreadable and blank
+        // agreements are left to the handler to reject.
+        let rent_data = RentShareAccount::load(&agreement.data.borrow());
+        if let Ok(rent_data) = rent_data {
+            if rent_data.is_initialized() && rent_data.payer_pubkey != *payer.key {
+                msg!("[RentShare] Payer must match payer key used during agreement initialization");
+                return Err(ProgramError::InvalidAccountData);
+            }
+        }
+
+        Ok(Self {
+            agreement,
+            payee: PayeeAccount::new(payee_account)?,
+            payer,
+            system_program,
+        })
+    }
+}
+
+/// Accounts for `TerminateEarly`
+pub struct TerminateAccounts<'a, 'b> {
+    pub agreement: AgreementAccount<'a, 'b>,
+    pub payer: PayerAccount<'a, 'b>,
+    pub payee: PayeeAccount<'a, 'b>,
+}
+
+impl<'a, 'b> TerminateAccounts<'a, 'b> {
+    pub fn parse(
+        accounts: &'a [AccountInfo<'b>],
+        program_id: &Pubkey,
+    ) -> Result<Self, ProgramError> {
+        let accounts_iter = &mut accounts.iter();
+
+        Ok(Self {
+            agreement: AgreementAccount::new(next_account_info(accounts_iter)?, program_id)?,
+            payer: PayerAccount::new(next_account_info(accounts_iter)?)?,
+            payee: PayeeAccount::new(next_account_info(accounts_iter)?)?,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn account_info<'a>(
+        key: &'a Pubkey,
+        owner: &'a Pubkey,
+        is_signer: bool,
+        lamports: &'a mut u64,
+        data: &'a mut [u8],
+    ) -> AccountInfo<'a> {
+        AccountInfo::new(key, is_signer, true, lamports, data, owner, false, 0)
+    }
+
+    #[test]
+    fn wrappers_reject_accounts_breaking_their_invariant() {
+        let program_id = Pubkey::new_unique();
+        let key = Pubkey::new_unique();
+        let system_program_id = system_program::id();
+        let (mut lamports, mut data) = (0, []);
+
+        let wallet = account_info(&key, &system_program_id, false, &mut lamports, &mut data);
+        assert_eq!(
+            AgreementAccount::new(&wallet, &program_id).err(),
+            Some(ProgramError::IncorrectProgramId)
+        );
+        assert_eq!(
+            PayerAccount::new(&wallet).err(),
+            Some(ProgramError::MissingRequiredSignature)
+        );
+        assert_eq!(
+            SystemProgramAccount::new(&wallet).err(),
+            Some(ProgramError::IncorrectProgramId)
+        );
+        assert!(PayeeAccount::new(&wallet).is_ok());
+
+        let (mut lamports, mut data) = (0, []);
+        let agreement = account_info(&key, &program_id, true, &mut lamports, &mut data);
+        assert_eq!(
+            PayeeAccount::new(&agreement).err(),
+            Some(ProgramError::InvalidAccountData)
+        );
+        assert!(AgreementAccount::new(&agreement, &program_id).is_ok());
+        assert!(PayerAccount::new(&agreement).is_ok());
+
+        let (mut lamports, mut data) = (0, []);
+        let system_program = account_info(
+            &system_program_id,
+            &program_id,
+            false,
+            &mut lamports,
+            &mut data,
+        );
+        assert!(SystemProgramAccount::new(&system_program).is_ok());
+    }
+
+    #[test]
+    fn parse_rejects_accounts_passed_out_of_order() {
+        let program_id = Pubkey::new_unique();
+        let (agreement_key, payee_key, payer_key) = (
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+        );
+        let (system_program_id, loader_id) = (system_program::id(), Pubkey::new_unique());
+        let mut lamports = [0; 4];
+        let mut data = vec![0; RentShareAccount::LEN];
+        let [agreement_lamports, payee_lamports, payer_lamports, system_lamports] = &mut lamports;
+        let (mut payee_data, mut payer_data, mut system_data) = ([], [], []);
+
+        let accounts = [
+            account_info(
+                &agreement_key,
+                &program_id,
+                false,
+                agreement_lamports,
+                &mut data,
+            ),
+            account_info(
+                &payee_key,
+                &system_program_id,
+                false,
+                payee_lamports,
+                &mut payee_data,
+            ),
+            account_info(
+                &payer_key,
+                &system_program_id,
+                true,
+                payer_lamports,
+                &mut payer_data,
+            ),
+            account_info(
+                &system_program_id,
+                &loader_id,
+                false,
+                system_lamports,
+                &mut system_data,
+            ),
+        ];
+        assert!(PayRentAccounts::parse(&accounts, &program_id).is_ok());
+
+        let payer_before_payee = [
+            accounts[0].clone(),
+            accounts[2].clone(),
+            accounts[1].clone(),
+            accounts[3].clone(),
+        ];
+        assert_eq!(
+            PayRentAccounts::parse(&payer_before_payee, &program_id).err(),
+            Some(ProgramError::MissingRequiredSignature)
+        );
+
+        let agreement_last = [
+            accounts[1].clone(),
+            accounts[2].clone(),
+            accounts[3].clone(),
+            accounts[0].clone(),
+        ];
+        assert_eq!(
+            TerminateAccounts::parse(&agreement_last, &program_id).err(),
+            Some(ProgramError::IncorrectProgramId)
+        );
+    }
+}
@@ -15,6 +15,116 @@ pub enum RentShareError {
     /// Rent agreement already terminated
     #[error("Rent Agreement Terminated")]
     RentAgreementTerminated,
+
+    /// Agreement timestamp is in the future or implausibly old
+    #[error("Invalid Timestamp")]
+    InvalidTimestamp,
+
+    /// Agreement terms are outside the allowed bounds
+    #[error("Invalid Agreement Terms")]
+    InvalidAgreementTerms,
+
+    /// Rent agreement has not been accepted by the payee
+    #[error("Agreement Not Accepted")]
+    AgreementNotAccepted,
+
+    /// Termination notice period has not elapsed yet
+    #[error("Notice Period Not Elapsed")]
+    NoticePeriodNotElapsed,
+
+    /// Deposit was already refunded, forfeited or the agreement has not ended
+    #[error("Deposit Not Refundable")]
+    DepositNotRefundable,
+
+    /// Monetary calculation overflowed or underflowed
+    #[error("Arithmetic Overflow")]
+    ArithmeticOverflow,
+
+    /// Agreement account holds less than its outstanding obligations
+    #[error("Escrow Insolvent")]
+    EscrowInsolvent,
+
+    /// Agreement still has a status or deposit that must be settled
+    #[error("Agreement Not Settled")]
+    AgreementNotSettled,
+
+    /// Rent payment made before the first payment is due
+    #[error("Payment Not Due")]
+    PaymentNotDue,
+
+    /// Rent payment covers more periods than the agreement allows in one payment
+    #[error("Prepay Limit Exceeded")]
+    PrepayLimitExceeded,
+
+    /// Neither the clock syscall nor a clock sysvar account is available
+    #[error("Clock Unavailable")]
+    ClockUnavailable,
+
+    /// Agreement activated before the required deposit was collected
+    #[error("Deposit Not Fully Collected")]
+    DepositNotFullyCollected,
+
+    /// Agreement frozen by its admin
+    #[error("Agreement Frozen")]
+    AgreementFrozen,
+
+    /// Rent amount changed again before the rent change cooldown elapsed
+    #[error("Rent Change Too Soon")]
+    RentChangeTooSoon,
+}
+
+impl RentShareError {
+    /// Every error, in code order
+    pub const ALL: [RentShareError; 17] = [
+        RentShareError::RentAlreadyPaidInFull,
+        RentShareError::RentPaymentAmountMismatch,
+        RentShareError::RentAgreementTerminated,
+        RentShareError::InvalidTimestamp,
+        RentShareError::InvalidAgreementTerms,
+        RentShareError::AgreementNotAccepted,
+        RentShareError::NoticePeriodNotElapsed,
+        RentShareError::DepositNotRefundable,
+        RentShareError::ArithmeticOverflow,
+        RentShareError::EscrowInsolvent,
+        RentShareError::AgreementNotSettled,
+        RentShareError::PaymentNotDue,
+        RentShareError::PrepayLimitExceeded,
+        RentShareError::ClockUnavailable,
+        RentShareError::DepositNotFullyCollected,
+        RentShareError::AgreementFrozen,
+        RentShareError::RentChangeTooSoon,
+    ];
+
+    /// Error for a `ProgramError::Custom` code returned by the program
+    pub fn from_u32(code: u32) -> Option<RentShareError> {
+        Self::ALL
+            .iter()
+            .copied()
+            .find(|error| *error as u32 == code)
+    }
+
+    /// Message matching the error's `Display` output, for clients decoding custom error codes
+    pub fn message(&self) -> &'static str {
+        match self {
+            RentShareError::RentAlreadyPaidInFull => "Rent Already Paid In Full",
+            RentShareError::RentPaymentAmountMismatch => "Rent Payment Amount Mistmatch",
+            RentShareError::RentAgreementTerminated => "Rent Agreement Terminated",
+            RentShareError::InvalidTimestamp => "Invalid Timestamp",
+            RentShareError::InvalidAgreementTerms => "Invalid Agreement Terms",
+            RentShareError::AgreementNotAccepted => "Agreement Not Accepted",
+            RentShareError::NoticePeriodNotElapsed => "Notice Period Not Elapsed",
+            RentShareError::DepositNotRefundable => "Deposit Not Refundable",
+            RentShareError::ArithmeticOverflow => "Arithmetic Overflow",
+            RentShareError::EscrowInsolvent => "Escrow Insolvent",
+            RentShareError::AgreementNotSettled => "Agreement Not Settled",
+            RentShareError::PaymentNotDue => "Payment Not Due",
+            RentShareError::PrepayLimitExceeded => "Prepay Limit Exceeded",
+            RentShareError::ClockUnavailable => "Clock Unavailable",
+            RentShareError::DepositNotFullyCollected => "Deposit Not Fully Collected",
+            RentShareError::AgreementFrozen => "Agreement Frozen",
+            RentShareError::RentChangeTooSoon => "Rent Change Too Soon",
+        }
+    }
 }
 
 impl From<RentShareError> for ProgramError {
@@ -22,3 +132,36 @@ impl From<RentShareError> for ProgramError {
         ProgramError::Custom(e as u32)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn message_matches_display() {
+        for error in RentShareError::ALL.iter() {
+            assert_eq!(error.message(), error.to_string());
+        }
+    }
+
+    #[test]
+    fn from_u32_round_trips_every_error() {
+        for error in RentShareError::ALL.iter() {
+            let code = *error as u32;
+            assert_eq!(
+                RentShareError::from_u32(code).map(|decoded| decoded as u32),
+                Some(code)
+            );
+            assert_eq!(ProgramError::from(*error), ProgramError::Custom(code));
+        }
+    }
+
+    #[test]
+    fn all_lists_every_code_in_order() {
+        for (offset, error) in RentShareError::ALL.iter().enumerate() {
+            assert_eq!(*error as u32, 100 + offset as u32);
+        }
+        assert!(RentShareError::from_u32(99).is_none());
+        assert!(RentShareError::from_u32(100 + RentShareError::ALL.len() as u32).is_none());
+    }
+}
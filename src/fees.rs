@@ -0,0 +1,54 @@
+use solana_program::{msg, program_error::ProgramError};
+
+use crate::{error::RentShareError, state::MAX_BASIS_POINTS};
+
+/// `bps` basis points of `amount`, rounded up when `round_up` is set and down otherwise.
+/// Rates above 100% are rejected, so the result never exceeds `amount`.
+pub fn bps_of(amount: u64, bps: u16, round_up: bool) -> Result<u64, ProgramError> {
+    if bps > MAX_BASIS_POINTS {
+        msg!("[RentShare] Rate exceeds 100%: {} bps", bps);
+        return Err(RentShareError::InvalidAgreementTerms.into());
+    }
+
+    let product = amount as u128 * bps as u128;
+    let mut fee = product / MAX_BASIS_POINTS as u128;
+    if round_up && fee * (MAX_BASIS_POINTS as u128) < product {
+        fee += 1;
+    }
+    Ok(fee as u64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rounds_down_unless_asked_to_round_up() {
+        assert_eq!(bps_of(999, 100, false), Ok(9));
+        assert_eq!(bps_of(999, 100, true), Ok(10));
+        assert_eq!(bps_of(1_000, 100, true), Ok(10));
+    }
+
+    #[test]
+    fn zero_rate_or_amount_charges_nothing() {
+        assert_eq!(bps_of(1_000, 0, true), Ok(0));
+        assert_eq!(bps_of(0, MAX_BASIS_POINTS, true), Ok(0));
+    }
+
+    #[test]
+    fn full_rate_returns_the_amount_without_overflow() {
+        assert_eq!(bps_of(u64::MAX, MAX_BASIS_POINTS, true), Ok(u64::MAX));
+        assert_eq!(
+            bps_of(u64::MAX, MAX_BASIS_POINTS - 1, true),
+            Ok(u64::MAX - u64::MAX / 10_000)
+        );
+    }
+
+    #[test]
+    fn rejects_rates_above_100_percent() {
+        assert_eq!(
+            bps_of(1_000, MAX_BASIS_POINTS + 1, false),
+            Err(RentShareError::InvalidAgreementTerms.into())
+        );
+    }
+}
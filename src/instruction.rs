@@ -1,69 +1,1114 @@
-use solana_program::{program_error::ProgramError, pubkey::Pubkey};
+use solana_program::{program_error::ProgramError, pubkey::Pubkey, system_program, sysvar};
 use std::convert::TryInto;
 
-#[derive(Debug)]
+/// Requirements for a single account passed to an instruction
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct AccountSpec {
+    pub signer: bool,
+    /// The account must be writable. Read-only accounts that do not sign must not be, so a client
+    /// marking the agreement or a program writable by mistake is caught. Read-only signers may
+    /// still be writable because the fee payer always is.
+    pub writable: bool,
+    /// The account must already be owned by the rent share program
+    pub program_owned: bool,
+    /// The account must have this exact address
+    pub key: Option<Pubkey>,
+}
+
+const AGREEMENT: AccountSpec = AccountSpec {
+    signer: false,
+    writable: true,
+    program_owned: true,
+    key: None,
+};
+const AGREEMENT_READONLY: AccountSpec = AccountSpec {
+    writable: false,
+    ..AGREEMENT
+};
+// Created by the program during initialization, so not yet owned by it
+const NEW_AGREEMENT: AccountSpec = AccountSpec {
+    program_owned: false,
+    ..AGREEMENT
+};
+const SIGNER: AccountSpec = AccountSpec {
+    signer: true,
+    writable: false,
+    program_owned: false,
+    key: None,
+};
+const SIGNER_WRITABLE: AccountSpec = AccountSpec {
+    writable: true,
+    ..SIGNER
+};
+const WRITABLE: AccountSpec = AccountSpec {
+    signer: false,
+    writable: true,
+    program_owned: false,
+    key: None,
+};
+const SYSTEM_PROGRAM: AccountSpec = AccountSpec {
+    signer: false,
+    writable: false,
+    program_owned: false,
+    key: Some(system_program::ID),
+};
+const RENT_SYSVAR: AccountSpec = AccountSpec {
+    key: Some(sysvar::rent::ID),
+    ..SYSTEM_PROGRAM
+};
+// The observer program's address is recorded in the agreement, so it is checked by the handler
+const OBSERVER_PROGRAM: AccountSpec = AccountSpec {
+    key: None,
+    ..SYSTEM_PROGRAM
+};
+
+/// Template terms replaced for a single agreement by `InitializeFromTemplate`. On the wire, a
+/// bitmask byte with bit `n` set for the `n`-th field is followed by each set field in order.
+#[derive(Debug, Default, Copy, Clone, PartialEq)]
+pub struct TemplateOverrides {
+    pub duration: Option<u64>,
+    pub duration_unit: Option<u8>,
+    pub late_fee_bps: Option<u16>,
+    pub notice_period_seconds: Option<u64>,
+    pub termination_penalty_bps: Option<u16>,
+    pub termination_penalty_max: Option<u64>,
+}
+
+impl TemplateOverrides {
+    fn unpack(input: &[u8]) -> Result<Self, ProgramError> {
+        let mask = *input.first().ok_or(ProgramError::InvalidInstructionData)?;
+        if mask >> 6 != 0 {
+            return Err(ProgramError::InvalidInstructionData);
+        }
+
+        let mut offset = 1;
+        let mut overrides = Self::default();
+        if mask & 1 != 0 {
+            overrides.duration = Some(RentShareInstruction::unpack_u64(input, offset)?);
+            offset += 8;
+        }
+        if mask & 1 << 1 != 0 {
+            overrides.duration_unit = Some(
+                *input
+                    .get(offset)
+                    .ok_or(ProgramError::InvalidInstructionData)?,
+            );
+            offset += 1;
+        }
+        if mask & 1 << 2 != 0 {
+            overrides.late_fee_bps = Some(RentShareInstruction::unpack_u16(input, offset)?);
+            offset += 2;
+        }
+        if mask & 1 << 3 != 0 {
+            overrides.notice_period_seconds =
+                Some(RentShareInstruction::unpack_u64(input, offset)?);
+            offset += 8;
+        }
+        if mask & 1 << 4 != 0 {
+            overrides.termination_penalty_bps =
+                Some(RentShareInstruction::unpack_u16(input, offset)?);
+            offset += 2;
+        }
+        if mask & 1 << 5 != 0 {
+            overrides.termination_penalty_max =
+                Some(RentShareInstruction::unpack_u64(input, offset)?);
+        }
+        Ok(overrides)
+    }
+
+    fn pack_into(&self, buf: &mut Vec<u8>) {
+        let mask_index = buf.len();
+        let mut mask = 0u8;
+        buf.push(0);
+        if let Some(duration) = self.duration {
+            mask |= 1;
+            buf.extend_from_slice(&duration.to_le_bytes());
+        }
+        if let Some(duration_unit) = self.duration_unit {
+            mask |= 1 << 1;
+            buf.push(duration_unit);
+        }
+        if let Some(late_fee_bps) = self.late_fee_bps {
+            mask |= 1 << 2;
+            buf.extend_from_slice(&late_fee_bps.to_le_bytes());
+        }
+        if let Some(notice_period_seconds) = self.notice_period_seconds {
+            mask |= 1 << 3;
+            buf.extend_from_slice(&notice_period_seconds.to_le_bytes());
+        }
+        if let Some(termination_penalty_bps) = self.termination_penalty_bps {
+            mask |= 1 << 4;
+            buf.extend_from_slice(&termination_penalty_bps.to_le_bytes());
+        }
+        if let Some(termination_penalty_max) = self.termination_penalty_max {
+            mask |= 1 << 5;
+            buf.extend_from_slice(&termination_penalty_max.to_le_bytes());
+        }
+        buf[mask_index] = mask;
+    }
+}
+
+/// Terms of a new agreement, sent in full by `InitializeRentContract` and filled in from a lease
+/// template by `InitializeFromTemplate`. Zeroed terms select the defaults documented on
+/// `InitializeRentContract`.
+#[derive(Debug, Default, Copy, Clone, PartialEq)]
+pub struct AgreementTerms {
+    pub payee_pubkey: Pubkey,
+    pub payer_pubkey: Pubkey,
+    pub deposit: u64,
+    pub rent_amount: u64,
+    pub duration: u64,
+    pub duration_unit: u8,
+    pub deposit_forfeit_on_early_termination: bool,
+    pub late_fee_bps: u16,
+    pub agreement_index: u32,
+    pub notice_period_seconds: u64,
+    pub replenish_deposit_first: bool,
+    pub max_missed_payments: u8,
+    pub auto_terminate_on_default: bool,
+    pub deposit_interest_bps: u16,
+    pub currency_decimals: u8,
+    pub currency_symbol: [u8; 8],
+    pub deposit_refund_destination: Pubkey,
+    pub defer_first_payment: bool,
+    pub proration_min_unit: u64,
+    pub free_periods: u64,
+    pub reject_suspicious_amounts: bool,
+    pub suspicious_amount_threshold: u64,
+    pub max_prepay_periods: u64,
+    pub required_deposit: u64,
+    pub reminder_lead_seconds: u64,
+    pub final_settlement_grace_seconds: u64,
+    pub termination_penalty_bps: u16,
+    pub termination_penalty_max: u64,
+    pub prepay_first_period: bool,
+    pub overpay_to_deposit: bool,
+    pub platform_pubkey: Pubkey,
+    pub read_fee_lamports: u64,
+    pub observer_program: Pubkey,
+    pub observer_required: bool,
+    pub admin_pubkey: Pubkey,
+    pub late_fee_escalation_bps: u16,
+    pub late_fee_max: u64,
+    pub rent_change_cooldown_seconds: u64,
+    pub non_refundable_deposit: u64,
+    pub termination_platform_fee: u64,
+}
+
+impl AgreementTerms {
+    fn unpack(input: &[u8]) -> Result<Self, ProgramError> {
+        let payee_pubkey: Pubkey = RentShareInstruction::unpack_pubkey(input, 0)?;
+        let payer_pubkey: Pubkey = RentShareInstruction::unpack_pubkey(input, 32)?;
+        let deposit: u64 = RentShareInstruction::unpack_u64(input, 64)?;
+        let rent_amount: u64 = RentShareInstruction::unpack_u64(input, 72)?;
+        let duration: u64 = RentShareInstruction::unpack_u64(input, 80)?;
+        let duration_unit: u8 = RentShareInstruction::unpack_u8(input, 88)?;
+        let deposit_forfeit_on_early_termination: bool =
+            RentShareInstruction::unpack_bool(input, 89)?;
+        let late_fee_bps: u16 = RentShareInstruction::unpack_u16(input, 90)?;
+        let agreement_index: u32 = RentShareInstruction::unpack_u32(input, 92)?;
+        let notice_period_seconds: u64 = RentShareInstruction::unpack_u64(input, 96)?;
+        let replenish_deposit_first: bool = RentShareInstruction::unpack_bool(input, 104)?;
+        let max_missed_payments: u8 = RentShareInstruction::unpack_u8(input, 105)?;
+        let auto_terminate_on_default: bool = RentShareInstruction::unpack_bool(input, 106)?;
+        let deposit_interest_bps: u16 = RentShareInstruction::unpack_u16(input, 107)?;
+        let currency_decimals: u8 = RentShareInstruction::unpack_u8(input, 109)?;
+        let currency_symbol: [u8; 8] = input
+            .get(110..118)
+            .and_then(|slice| slice.try_into().ok())
+            .ok_or(ProgramError::InvalidInstructionData)?;
+        let deposit_refund_destination: Pubkey = RentShareInstruction::unpack_pubkey(input, 118)?;
+        let defer_first_payment: bool = RentShareInstruction::unpack_bool(input, 150)?;
+        let proration_min_unit: u64 = RentShareInstruction::unpack_u64(input, 151)?;
+        let free_periods: u64 = RentShareInstruction::unpack_u64(input, 159)?;
+        let reject_suspicious_amounts: bool = RentShareInstruction::unpack_bool(input, 167)?;
+        let suspicious_amount_threshold: u64 = RentShareInstruction::unpack_u64(input, 168)?;
+        let max_prepay_periods: u64 = RentShareInstruction::unpack_u64(input, 176)?;
+        let required_deposit: u64 = RentShareInstruction::unpack_u64(input, 184)?;
+        let reminder_lead_seconds: u64 = RentShareInstruction::unpack_u64(input, 192)?;
+        let final_settlement_grace_seconds: u64 = RentShareInstruction::unpack_u64(input, 200)?;
+        let termination_penalty_bps: u16 = RentShareInstruction::unpack_u16(input, 208)?;
+        let termination_penalty_max: u64 = RentShareInstruction::unpack_u64(input, 210)?;
+        let prepay_first_period: bool = RentShareInstruction::unpack_bool(input, 218)?;
+        let overpay_to_deposit: bool = RentShareInstruction::unpack_bool(input, 219)?;
+        let platform_pubkey: Pubkey = RentShareInstruction::unpack_pubkey(input, 220)?;
+        let read_fee_lamports: u64 = RentShareInstruction::unpack_u64(input, 252)?;
+        let observer_program: Pubkey = RentShareInstruction::unpack_pubkey(input, 260)?;
+        let observer_required: bool = RentShareInstruction::unpack_bool(input, 292)?;
+        let admin_pubkey: Pubkey = RentShareInstruction::unpack_pubkey(input, 293)?;
+        let late_fee_escalation_bps: u16 = RentShareInstruction::unpack_u16(input, 325)?;
+        let late_fee_max: u64 = RentShareInstruction::unpack_u64(input, 327)?;
+        let rent_change_cooldown_seconds: u64 = RentShareInstruction::unpack_u64(input, 335)?;
+        let non_refundable_deposit: u64 = RentShareInstruction::unpack_u64(input, 343)?;
+        let termination_platform_fee: u64 = RentShareInstruction::unpack_u64(input, 351)?;
+
+        Ok(Self {
+            payee_pubkey,
+            payer_pubkey,
+            deposit,
+            rent_amount,
+            duration,
+            duration_unit,
+            deposit_forfeit_on_early_termination,
+            late_fee_bps,
+            agreement_index,
+            notice_period_seconds,
+            replenish_deposit_first,
+            max_missed_payments,
+            auto_terminate_on_default,
+            deposit_interest_bps,
+            currency_decimals,
+            currency_symbol,
+            deposit_refund_destination,
+            defer_first_payment,
+            proration_min_unit,
+            free_periods,
+            reject_suspicious_amounts,
+            suspicious_amount_threshold,
+            max_prepay_periods,
+            required_deposit,
+            reminder_lead_seconds,
+            final_settlement_grace_seconds,
+            termination_penalty_bps,
+            termination_penalty_max,
+            prepay_first_period,
+            overpay_to_deposit,
+            platform_pubkey,
+            read_fee_lamports,
+            observer_program,
+            observer_required,
+            admin_pubkey,
+            late_fee_escalation_bps,
+            late_fee_max,
+            rent_change_cooldown_seconds,
+            non_refundable_deposit,
+            termination_platform_fee,
+        })
+    }
+
+    fn pack_into(&self, buf: &mut Vec<u8>) {
+        buf.extend_from_slice(self.payee_pubkey.as_ref());
+        buf.extend_from_slice(self.payer_pubkey.as_ref());
+        buf.extend_from_slice(&self.deposit.to_le_bytes());
+        buf.extend_from_slice(&self.rent_amount.to_le_bytes());
+        buf.extend_from_slice(&self.duration.to_le_bytes());
+        buf.push(self.duration_unit);
+        buf.push(self.deposit_forfeit_on_early_termination as u8);
+        buf.extend_from_slice(&self.late_fee_bps.to_le_bytes());
+        buf.extend_from_slice(&self.agreement_index.to_le_bytes());
+        buf.extend_from_slice(&self.notice_period_seconds.to_le_bytes());
+        buf.push(self.replenish_deposit_first as u8);
+        buf.push(self.max_missed_payments);
+        buf.push(self.auto_terminate_on_default as u8);
+        buf.extend_from_slice(&self.deposit_interest_bps.to_le_bytes());
+        buf.push(self.currency_decimals);
+        buf.extend_from_slice(&self.currency_symbol);
+        buf.extend_from_slice(self.deposit_refund_destination.as_ref());
+        buf.push(self.defer_first_payment as u8);
+        buf.extend_from_slice(&self.proration_min_unit.to_le_bytes());
+        buf.extend_from_slice(&self.free_periods.to_le_bytes());
+        buf.push(self.reject_suspicious_amounts as u8);
+        buf.extend_from_slice(&self.suspicious_amount_threshold.to_le_bytes());
+        buf.extend_from_slice(&self.max_prepay_periods.to_le_bytes());
+        buf.extend_from_slice(&self.required_deposit.to_le_bytes());
+        buf.extend_from_slice(&self.reminder_lead_seconds.to_le_bytes());
+        buf.extend_from_slice(&self.final_settlement_grace_seconds.to_le_bytes());
+        buf.extend_from_slice(&self.termination_penalty_bps.to_le_bytes());
+        buf.extend_from_slice(&self.termination_penalty_max.to_le_bytes());
+        buf.push(self.prepay_first_period as u8);
+        buf.push(self.overpay_to_deposit as u8);
+        buf.extend_from_slice(self.platform_pubkey.as_ref());
+        buf.extend_from_slice(&self.read_fee_lamports.to_le_bytes());
+        buf.extend_from_slice(self.observer_program.as_ref());
+        buf.push(self.observer_required as u8);
+        buf.extend_from_slice(self.admin_pubkey.as_ref());
+        buf.extend_from_slice(&self.late_fee_escalation_bps.to_le_bytes());
+        buf.extend_from_slice(&self.late_fee_max.to_le_bytes());
+        buf.extend_from_slice(&self.rent_change_cooldown_seconds.to_le_bytes());
+        buf.extend_from_slice(&self.non_refundable_deposit.to_le_bytes());
+        buf.extend_from_slice(&self.termination_platform_fee.to_le_bytes());
+    }
+}
+
+/// Prefix of the instruction data `PayRent` sends to an agreement's observer program
+pub const PAYMENT_NOTIFICATION_DISCRIMINATOR: [u8; 8] = *b"rspaid\0\0";
+
+/// Payment details sent to an agreement's observer program through CPI after each `PayRent`.
+/// The observer receives the agreement account and the payer as read-only accounts.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct PaymentNotification {
+    /// Lamports transferred to the payee, including late fees
+    pub rent_paid: u64,
+    pub late_fees: u64,
+    pub periods: u64,
+    pub remaining_payments: u64,
+    pub paid_at: i64,
+}
+
+impl PaymentNotification {
+    /// Size of the packed notification, including the discriminator
+    pub const LEN: usize = 8 + 8 + 8 + 8 + 8 + 8;
+
+    pub fn unpack(input: &[u8]) -> Result<Self, ProgramError> {
+        if input.len() != Self::LEN || input[..8] != PAYMENT_NOTIFICATION_DISCRIMINATOR {
+            return Err(ProgramError::InvalidInstructionData);
+        }
+
+        Ok(Self {
+            rent_paid: RentShareInstruction::unpack_u64(input, 8)?,
+            late_fees: RentShareInstruction::unpack_u64(input, 16)?,
+            periods: RentShareInstruction::unpack_u64(input, 24)?,
+            remaining_payments: RentShareInstruction::unpack_u64(input, 32)?,
+            paid_at: RentShareInstruction::unpack_i64(input, 40)?,
+        })
+    }
+
+    pub fn pack(&self) -> Vec<u8> {
+        let mut buf = Vec::with_capacity(Self::LEN);
+        buf.extend_from_slice(&PAYMENT_NOTIFICATION_DISCRIMINATOR);
+        buf.extend_from_slice(&self.rent_paid.to_le_bytes());
+        buf.extend_from_slice(&self.late_fees.to_le_bytes());
+        buf.extend_from_slice(&self.periods.to_le_bytes());
+        buf.extend_from_slice(&self.remaining_payments.to_le_bytes());
+        buf.extend_from_slice(&self.paid_at.to_le_bytes());
+        buf
+    }
+}
+
+// Instructions are unpacked once per transaction, so the size of the initialization terms is not
+// worth boxing them for
+#[allow(clippy::large_enum_variant)]
+#[derive(Debug, PartialEq)]
 pub enum RentShareInstruction {
-    /// Initialize the rent contract with the agreed on terms and persist initial state in the agreement account
+    /// Initialize the rent contract with the agreed on terms and persist initial state in the agreement account.
+    /// The agreement stays pending until the payee accepts it. A `max_missed_payments` of 0 uses
+    /// `DEFAULT_MAX_MISSED_PAYMENTS`. The currency decimals and zero padded symbol are display hints
+    /// only; leaving both zeroed displays amounts as SOL. A default `deposit_refund_destination` refunds
+    /// the deposit to the payer. The first payment is due at signing, or when `defer_first_payment` is
+    /// set, only accepted once the first period has elapsed. Prorated refunds are rounded to the nearest
+    /// `proration_min_unit` lamports, where 0 rounds to the nearest lamport. The first `free_periods`
+    /// periods are paid without a transfer. Non-zero rent or deposit amounts below
+    /// `suspicious_amount_threshold` lamports, or `DEFAULT_SUSPICIOUS_AMOUNT_THRESHOLD` when 0, are logged
+    /// as likely entry errors and rejected when `reject_suspicious_amounts` is set. A single payment can
+    /// cover at most `max_prepay_periods` periods, where 0 allows the full duration. A `required_deposit`
+    /// above `deposit` stages the deposit: the rest is collected with `IncreaseDeposit` and the payee can
+    /// only accept once it is fully collected. 0 requires exactly `deposit`. `CheckReminders` reminds
+    /// the payer of a payment `reminder_lead_seconds` before it is due. A non-zero
+    /// `final_settlement_grace_seconds` keeps an agreement holding a deposit `AwaitingSettlement` after the
+    /// final payment until the deposit is refunded or the grace elapses. Terminating early withholds a
+    /// penalty of `termination_penalty_bps` of the remaining rent, capped at `termination_penalty_max`
+    /// lamports when non-zero, from the lamports returned to the payer. When `prepay_first_period` is set,
+    /// the first period's rent is collected with the deposit and released to the payee on acceptance.
+    /// When `overpay_to_deposit` is set, rent payments above the amount due add the surplus to the deposit
+    /// instead of being rejected. A non-zero `read_fee_lamports`, at most `MAX_READ_FEE_LAMPORTS`, is paid
+    /// to `platform_pubkey` by whoever sends `ViewAgreement`, `GetHealthScore` or `CheckReminders`.
+    /// A non-default `observer_program` is sent a `PaymentNotification` through CPI after every rent
+    /// payment. A payment without the observer passed is rejected when `observer_required` is set and
+    /// otherwise skips the notification; a failing observer always fails the payment. A non-default
+    /// `admin_pubkey` can `Freeze` the agreement in an emergency. Each period a payment is overdue beyond
+    /// the first adds `late_fee_escalation_bps` of the late fee to it, capped at `late_fee_max` lamports
+    /// per period when non-zero. `UpdateRentAmount` is rejected until `rent_change_cooldown_seconds` have
+    /// elapsed since the previous rent change. `non_refundable_deposit` lamports of the deposit, such as a
+    /// cleaning fee, are paid to the payee instead of returned to the payer. Terminating early pays a
+    /// `termination_platform_fee` to `platform_pubkey` from the refundable part of the held deposit, and
+    /// from the payer for any part it cannot cover.
+    /// The agreement account must be the address returned by `find_agreement_address` for the payee, payer
+    /// and `agreement_index`, and is created by the program when it has not been allocated yet, even
+    /// when lamports were already sent to the address.
+    ///
+    /// Accounts expected:
+    /// 0. `[writable]` The Rent Agreement account derived from the payee, payer and agreement index; owned by program id.
+    /// 1. `[]` Sysvar Rent Account to validate rent exemption (SYSVAR_RENT_PUBKEY)
+    /// 2. `[signer, writable]` Payer (Renter) account funding the agreement account and the deposit it holds
+    /// 3. `[]` System program account
+    InitializeRentContract { terms: AgreementTerms },
+
+    /// Pay rent from payer to payee. Several periods can be prepaid with a single transfer by
+    /// passing the number of `periods`, in which case `rent_amount` is the total for all periods.
+    /// Periods paid after they have fully elapsed include the late fee. When the agreement replenishes
+    /// the deposit first, any shortfall from the required deposit is included and held by the agreement account.
+    /// Agreements that auto terminate on default are terminated instead of paid once the payer has missed
+    /// `max_missed_payments`, forfeiting the deposit and its accrued interest to the payee recorded at
+    /// initialization even while rent is assigned.
+    ///
+    /// Another program can pay through CPI for a payer that is one of its program derived addresses by
+    /// signing for the payer with `invoke_signed`. The payer must be system owned with no data so the
+    /// system program can transfer from it, and the signature carries through to that transfer.
+    ///
+    /// Accounts expected:
+    /// 0. `[writable]` The Rent Agreement account created to manage state across 2 parties; owned by program id.
+    /// 1. `[writable]` Payee (Owner) account (public key)
+    /// 2. `[signer, writable]` Payer (Renter) account (keypair, or a PDA signed by the calling program)
+    /// 3. `[]` System program account
+    /// 4. `[writable]` Payee recorded at initialization, only while rent is assigned or when the observer follows
+    /// 5. `[]` Observer program, only when the agreement has one
+    PayRent { rent_amount: u64, periods: u64 },
+
+    /// Terminate agreement early, violating the terms. The held deposit is transferred to the payee
+    /// when the agreement forfeits it on early termination, otherwise it is refunded to the deposit
+    /// refund destination.
+    /// Agreements with a notice period can only be terminated once the notice period has elapsed.
+    /// Agreements with a termination platform fee pay it to the platform before the deposit is split.
+    /// Accrued deposit interest is forfeited with the deposit, or paid to the payer from the part of
+    /// the deposit the payee receives.
+    ///
+    /// Accounts expected:
+    /// 0. `[writable]` The Rent Agreement account created to manage state across 2 parties; owned by program id.
+    /// 1. `[signer, writable]` Payer (Renter) account (keypair)
+    /// 2. `[writable]` Payee (Owner) account (public key)
+    /// 3. `[writable]` Platform account, only when the agreement charges a termination platform fee
+    /// 4. `[]` System program account, only when the refundable deposit cannot cover the termination platform fee
+    /// 5. `[writable]` Deposit refund destination, only when it differs from the payer
+    TerminateEarly {},
+
+    /// Log the current agreement state without modifying it
+    ///
+    /// Accounts expected:
+    /// 0. `[]` The Rent Agreement account created to manage state across 2 parties; owned by program id.
+    /// 1. `[signer, writable]` Caller paying the read fee, only when the agreement charges one
+    /// 2. `[writable]` Platform account receiving the read fee
+    /// 3. `[]` System program account
+    ViewAgreement {},
+
+    /// Add to the deposit held by the agreement account while the agreement is pending or active
     ///
     /// Accounts expected:
     /// 0. `[writable]` The Rent Agreement account created to manage state across 2 parties; owned by program id.
+    /// 1. `[signer, writable]` Payer (Renter) account (keypair)
+    /// 2. `[]` System program account
+    IncreaseDeposit { amount: u64 },
+
+    /// Transfer lamports from any funder so the agreement account holds the rent exempt minimum
+    /// in addition to the deposit
+    ///
+    /// Accounts expected:
+    /// 0. `[writable]` The Rent Agreement account created to manage state across 2 parties; owned by program id.
+    /// 1. `[signer, writable]` Funder account (keypair)
+    /// 2. `[]` System program account
+    /// 3. `[]` Sysvar Rent Account to calculate rent exemption (SYSVAR_RENT_PUBKEY)
+    TopUpRentExemption {},
+
+    /// Accept the agreement terms as the payee, activating the agreement and starting the payment schedule.
+    /// A prepaid first period is released to the payee, which must then be writable.
+    ///
+    /// Accounts expected:
+    /// 0. `[writable]` The Rent Agreement account created to manage state across 2 parties; owned by program id.
+    /// 1. `[signer]` Payee (Owner) account (keypair), `[signer, writable]` with a prepaid first period
+    AcceptAgreement {},
+
+    /// Give notice of early termination as the payer, starting the notice period
+    ///
+    /// Accounts expected:
+    /// 0. `[writable]` The Rent Agreement account created to manage state across 2 parties; owned by program id.
+    /// 1. `[signer]` Payer (Renter) account (keypair)
+    GiveNotice {},
+
+    /// Refund the held deposit to the refund destination once the agreement has ended, approved by the payee.
+    /// Interest accrued on the deposit is paid to the same destination by the payee with the refund.
+    ///
+    /// Accounts expected:
+    /// 0. `[writable]` The Rent Agreement account created to manage state across 2 parties; owned by program id.
+    /// 1. `[signer, writable]` Payee (Owner) account (keypair)
+    /// 2. `[writable]` Deposit refund destination recorded at initialization, the payer by default (public key)
+    /// 3. `[]` System program account
+    RefundDeposit {},
+
+    /// Verify the agreement account holds at least the rent exempt minimum plus the held deposit,
+    /// without modifying it
+    ///
+    /// Accounts expected:
+    /// 0. `[]` The Rent Agreement account created to manage state across 2 parties; owned by program id.
+    /// 1. `[]` Sysvar Rent Account to calculate rent exemption (SYSVAR_RENT_PUBKEY)
+    VerifyEscrow {},
+
+    /// Accrue interest on the held deposit for the periods elapsed since the last accrual.
+    /// Interest is owed by the payee and paid out with the deposit refund.
+    ///
+    /// Accounts expected:
+    /// 0. `[writable]` The Rent Agreement account created to manage state across 2 parties; owned by program id.
+    AccrueDepositInterest {},
+
+    /// Close the agreement account once it has ended and the deposit has been refunded or forfeited,
+    /// returning the remaining lamports to the payer who funded the account
+    ///
+    /// Accounts expected:
+    /// 0. `[writable]` The Rent Agreement account created to manage state across 2 parties; owned by program id.
+    /// 1. `[signer, writable]` Payer (Renter) account (keypair)
+    CloseAgreement {},
+
+    /// Replace the payee recorded at initialization while the agreement is still pending acceptance.
+    /// The agreement address stays derived from the original payee.
+    ///
+    /// Accounts expected:
+    /// 0. `[writable]` The Rent Agreement account created to manage state across 2 parties; owned by program id.
+    /// 1. `[signer]` Payer (Renter) account (keypair)
+    CorrectPayee { new_payee: Pubkey },
+
+    /// Pay every remaining period in a single transfer, completing the agreement. The amount is computed
+    /// by the program the same way as `PayRent`, including late fees and any deposit shortfall, but
+    /// is not capped by the prepay limit. Periods after a rent assignment ends are paid to the payee
+    /// recorded at initialization. The deposit stays held until the payee refunds it.
+    ///
+    /// Accounts expected:
+    /// 0. `[writable]` The Rent Agreement account created to manage state across 2 parties; owned by program id.
+    /// 1. `[writable]` Payee (Owner) account currently receiving rent (public key)
+    /// 2. `[signer, writable]` Payer (Renter) account (keypair)
+    /// 3. `[]` System program account
+    /// 4. `[writable]` Payee recorded at initialization, only while rent is assigned or when the observer follows
+    /// 5. `[]` Observer program, only when the agreement has one
+    PrepayWholeLease {},
+
+    /// Change the rent charged for the remaining periods, signed by both parties, once the agreement's
+    /// rent change cooldown has elapsed since the previous change
+    ///
+    /// Accounts expected:
+    /// 0. `[writable]` The Rent Agreement account created to manage state across 2 parties; owned by program id.
+    /// 1. `[signer]` Payee (Owner) account (keypair)
+    /// 2. `[signer]` Payer (Renter) account (keypair)
+    UpdateRentAmount { rent_amount: u64 },
+
+    /// Extend the agreement by `periods` additional payment periods, signed by both parties
+    ///
+    /// Accounts expected:
+    /// 0. `[writable]` The Rent Agreement account created to manage state across 2 parties; owned by program id.
+    /// 1. `[signer]` Payee (Owner) account (keypair)
+    /// 2. `[signer]` Payer (Renter) account (keypair)
+    ExtendAgreement { periods: u64 },
+
+    /// Assign the lease to a new payer, signed by the current payer and approved by the payee.
+    /// Later payments must come from the new payer, and a deposit refunding to the current payer
+    /// refunds to the new payer instead. The agreement address stays derived from the original payer.
+    ///
+    /// Accounts expected:
+    /// 0. `[writable]` The Rent Agreement account created to manage state across 2 parties; owned by program id.
+    /// 1. `[signer]` Payee (Owner) account (keypair)
+    /// 2. `[signer]` Current Payer (Renter) account (keypair)
+    TransferTenant { new_payer: Pubkey },
+
+    /// Log the amount that settles the lease if it is terminated now, without modifying it
+    ///
+    /// Accounts expected:
+    /// 0. `[]` The Rent Agreement account created to manage state across 2 parties; owned by program id.
+    GetPayoffAmount {},
+
+    /// Record the hash of the signed off-chain lease document, signed by both parties.
+    /// The hash can only change while the agreement is pending acceptance.
+    ///
+    /// Accounts expected:
+    /// 0. `[writable]` The Rent Agreement account created to manage state across 2 parties; owned by program id.
+    /// 1. `[signer]` Payee (Owner) account (keypair)
+    /// 2. `[signer]` Payer (Renter) account (keypair)
+    SetDocumentHash { hash: [u8; 32] },
+
+    /// Reset the agreement account to uninitialized so it can be reused, returning any held deposit
+    /// to the payer who funded it. Only available with the `dev-reset` feature.
+    ///
+    /// Accounts expected:
+    /// 0. `[writable]` The Rent Agreement account created to manage state across 2 parties; owned by program id.
+    /// 1. `[signer, writable]` Payer (Renter) account that funded the agreement (keypair)
+    #[cfg(feature = "dev-reset")]
+    ResetAgreement {},
+
+    /// Log the final accounting of an ended agreement in a stable `key=value` format, without modifying it
+    ///
+    /// Accounts expected:
+    /// 0. `[]` The Rent Agreement account created to manage state across 2 parties; owned by program id.
+    GenerateSettlementReport {},
+
+    /// Log a 0 to 100 health score from the on time payment streak, overdue periods and remaining
+    /// payments, without modifying the agreement
+    ///
+    /// Accounts expected:
+    /// 0. `[]` The Rent Agreement account created to manage state across 2 parties; owned by program id.
+    /// 1. `[signer, writable]` Caller paying the read fee, only when the agreement charges one
+    /// 2. `[writable]` Platform account receiving the read fee
+    /// 3. `[]` System program account
+    GetHealthScore {},
+
+    /// Log `EVENT:REMINDER` when the next payment is due within the reminder lead time, or
+    /// `EVENT:OVERDUE` once its due date has passed, without modifying the agreement
+    ///
+    /// Accounts expected:
+    /// 0. `[]` The Rent Agreement account created to manage state across 2 parties; owned by program id.
+    /// 1. `[signer, writable]` Caller paying the read fee, only when the agreement charges one
+    /// 2. `[writable]` Platform account receiving the read fee
+    /// 3. `[]` System program account
+    CheckReminders {},
+
+    /// Complete an agreement awaiting settlement once the final settlement grace has elapsed without the
+    /// deposit being refunded. Anyone may send this instruction.
+    ///
+    /// Accounts expected:
+    /// 0. `[writable]` The Rent Agreement account created to manage state across 2 parties; owned by program id.
+    CompleteSettlement {},
+
+    /// Push the lease end out by `additional_seconds` without adding payment periods, such as for a
+    /// rent free holdover, signed by both parties
+    ///
+    /// Accounts expected:
+    /// 0. `[writable]` The Rent Agreement account created to manage state across 2 parties; owned by program id.
+    /// 1. `[signer]` Payee (Owner) account (keypair)
+    /// 2. `[signer]` Payer (Renter) account (keypair)
+    ExtendEndDate { additional_seconds: i64 },
+
+    /// Log every field of the agreement as `DUMP:key=value`, without modifying it. Only available
+    /// with the `debug-instructions` feature.
+    ///
+    /// Accounts expected:
+    /// 0. `[]` The Rent Agreement account created to manage state across 2 parties; owned by program id.
+    #[cfg(feature = "debug-instructions")]
+    DumpState {},
+
+    /// Log the serialized agreement with `sol_log_data`, which the runtime prints base64 encoded as
+    /// `Program data:`, without modifying it. Only available with the `debug-instructions` feature.
+    ///
+    /// Accounts expected:
+    /// 0. `[]` The Rent Agreement account created to manage state across 2 parties; owned by program id.
+    #[cfg(feature = "debug-instructions")]
+    EmitAccountBlob {},
+
+    /// Write standard lease terms to a template account that agreements can be initialized from.
+    /// The first write sets the signer as the template authority; later writes must be signed by it.
+    ///
+    /// Accounts expected:
+    /// 0. `[writable]` Lease template account allocated with `LeaseTemplate::LEN` bytes; owned by program id.
+    /// 1. `[signer]` Template authority (keypair)
+    WriteLeaseTemplate {
+        duration: u64,
+        duration_unit: u8,
+        late_fee_bps: u16,
+        notice_period_seconds: u64,
+        termination_penalty_bps: u16,
+        termination_penalty_max: u64,
+    },
+
+    /// Initialize a rent agreement like `InitializeRentContract`, taking the duration, late fee,
+    /// notice period and termination penalty from a lease template unless overridden. Every other
+    /// term uses its default.
+    ///
+    /// Accounts expected:
+    /// 0. `[writable]` The Rent Agreement account derived from the payee, payer and agreement index; owned by program id.
     /// 1. `[]` Sysvar Rent Account to validate rent exemption (SYSVAR_RENT_PUBKEY)
-    InitializeRentContract {
+    /// 2. `[signer, writable]` Payer (Renter) account (keypair)
+    /// 3. `[]` System program account
+    /// 4. `[]` Lease template account; owned by program id.
+    InitializeFromTemplate {
         payee_pubkey: Pubkey,
         payer_pubkey: Pubkey,
         deposit: u64,
         rent_amount: u64,
-        duration: u64,
-        duration_unit: u8,
+        agreement_index: u32,
+        overrides: TemplateOverrides,
     },
 
-    /// Pay rent from payee to payer
+    /// Return `return_amount` of the held deposit to the refund destination once the agreement has
+    /// ended, approved by the payee, forfeiting the rest to the payee for the itemized deductions
+    /// identified by `deduction_codes`. Deposits with accrued interest are refunded with `RefundDeposit`.
     ///
     /// Accounts expected:
     /// 0. `[writable]` The Rent Agreement account created to manage state across 2 parties; owned by program id.
-    /// 1. `[signer]` Payer (Renter) account (keypair)
-    /// 2. `[]` Payee (Owner) account (public key)
-    /// 3. `[]` System program account
-    PayRent { rent_amount: u64 },
+    /// 1. `[signer, writable]` Payee (Owner) account (keypair)
+    /// 2. `[writable]` Deposit refund destination recorded at initialization, the payer by default (public key)
+    ReturnDepositItemized {
+        return_amount: u64,
+        deduction_codes: [u8; 4],
+    },
 
-    /// Terminate agreement early, violating the terms
+    /// Block every instruction that modifies the agreement until it is unfrozen, approved by the
+    /// admin recorded at initialization. Read-only instructions keep working.
     ///
     /// Accounts expected:
     /// 0. `[writable]` The Rent Agreement account created to manage state across 2 parties; owned by program id.
-    TerminateEarly {},
+    /// 1. `[signer]` Admin account (keypair)
+    Freeze {},
+
+    /// Lift a freeze, approved by the admin recorded at initialization
+    ///
+    /// Accounts expected:
+    /// 0. `[writable]` The Rent Agreement account created to manage state across 2 parties; owned by program id.
+    /// 1. `[signer]` Admin account (keypair)
+    Unfreeze {},
+
+    /// Redirect rent to `assigned_payee`, such as a lender the rent is assigned to, for every period
+    /// before the zero based `until_period`, approved by the payee. An `until_period` at or below the
+    /// payments made ends the assignment. `PayRent` must then pass the assigned payee, and payments
+    /// spanning the end of the assignment are rejected so each side of it is paid separately.
+    ///
+    /// Accounts expected:
+    /// 0. `[writable]` The Rent Agreement account created to manage state across 2 parties; owned by program id.
+    /// 1. `[signer]` Payee (Owner) account (keypair)
+    AssignRent {
+        assigned_payee: Pubkey,
+        until_period: u64,
+    },
+
+    /// Pay `amount` of the late fees accrued on overdue periods without paying their rent. The
+    /// payment is credited against the late fees charged when those periods are paid, and cannot
+    /// exceed the late fees accrued.
+    ///
+    /// Accounts expected:
+    /// 0. `[writable]` The Rent Agreement account created to manage state across 2 parties; owned by program id.
+    /// 1. `[writable]` Payee (Owner) account currently receiving rent (public key)
+    /// 2. `[signer, writable]` Payer (Renter) account (keypair)
+    /// 3. `[]` System program account
+    PayLateFees { amount: u64 },
 }
 
 impl RentShareInstruction {
+    /// Whether the instruction modifies an existing agreement, and so is blocked while it is frozen
+    pub fn modifies_agreement(&self) -> bool {
+        match self {
+            Self::WriteLeaseTemplate { .. } | Self::Freeze {} | Self::Unfreeze {} => false,
+            _ => self.account_specs().first() == Some(&AGREEMENT),
+        }
+    }
+
+    /// Accounts that may follow `account_specs`, in order, when the agreement's terms need them
+    pub fn optional_account_specs(&self) -> &'static [AccountSpec] {
+        match self {
+            Self::ViewAgreement {} | Self::GetHealthScore {} | Self::CheckReminders {} => {
+                &[SIGNER_WRITABLE, WRITABLE, SYSTEM_PROGRAM]
+            }
+            Self::PayRent { .. } | Self::PrepayWholeLease {} => &[WRITABLE, OBSERVER_PROGRAM],
+            Self::TerminateEarly {} => &[WRITABLE, SYSTEM_PROGRAM, WRITABLE],
+            _ => &[],
+        }
+    }
+
+    /// Accounts the instruction expects, in order, as documented on each variant
+    pub fn account_specs(&self) -> &'static [AccountSpec] {
+        match self {
+            Self::InitializeRentContract { .. } => {
+                &[NEW_AGREEMENT, RENT_SYSVAR, SIGNER_WRITABLE, SYSTEM_PROGRAM]
+            }
+            Self::InitializeFromTemplate { .. } => &[
+                NEW_AGREEMENT,
+                RENT_SYSVAR,
+                SIGNER_WRITABLE,
+                SYSTEM_PROGRAM,
+                AGREEMENT_READONLY,
+            ],
+            Self::WriteLeaseTemplate { .. } => &[AGREEMENT, SIGNER],
+            Self::ReturnDepositItemized { .. } => &[AGREEMENT, SIGNER_WRITABLE, WRITABLE],
+            Self::PayRent { .. } | Self::PrepayWholeLease {} | Self::PayLateFees { .. } => {
+                &[AGREEMENT, WRITABLE, SIGNER_WRITABLE, SYSTEM_PROGRAM]
+            }
+            Self::TerminateEarly {} => &[AGREEMENT, SIGNER_WRITABLE, WRITABLE],
+            Self::ViewAgreement {}
+            | Self::GetPayoffAmount {}
+            | Self::GenerateSettlementReport {}
+            | Self::GetHealthScore {}
+            | Self::CheckReminders {} => &[AGREEMENT_READONLY],
+            #[cfg(feature = "debug-instructions")]
+            Self::DumpState {} | Self::EmitAccountBlob {} => &[AGREEMENT_READONLY],
+            Self::IncreaseDeposit { .. } => &[AGREEMENT, SIGNER_WRITABLE, SYSTEM_PROGRAM],
+            Self::TopUpRentExemption {} => {
+                &[AGREEMENT, SIGNER_WRITABLE, SYSTEM_PROGRAM, RENT_SYSVAR]
+            }
+            Self::AcceptAgreement {}
+            | Self::GiveNotice {}
+            | Self::CorrectPayee { .. }
+            | Self::Freeze {}
+            | Self::Unfreeze {}
+            | Self::AssignRent { .. } => &[AGREEMENT, SIGNER],
+            Self::RefundDeposit {} => &[AGREEMENT, SIGNER_WRITABLE, WRITABLE, SYSTEM_PROGRAM],
+            Self::VerifyEscrow {} => &[AGREEMENT_READONLY, RENT_SYSVAR],
+            Self::AccrueDepositInterest {} | Self::CompleteSettlement {} => &[AGREEMENT],
+            Self::CloseAgreement {} => &[AGREEMENT, SIGNER_WRITABLE],
+            #[cfg(feature = "dev-reset")]
+            Self::ResetAgreement {} => &[AGREEMENT, SIGNER_WRITABLE],
+            Self::UpdateRentAmount { .. }
+            | Self::ExtendAgreement { .. }
+            | Self::TransferTenant { .. }
+            | Self::SetDocumentHash { .. }
+            | Self::ExtendEndDate { .. } => &[AGREEMENT, SIGNER, SIGNER],
+        }
+    }
+
+    /// Parse instruction data: a one byte tag followed by the fields at fixed offsets. Integers are
+    /// little-endian, matching the Borsh encoded account state, and are part of the client wire
+    /// format; `PayRent { rent_amount: 1_000_000_000, periods: 1 }` is `[1, 0, 202, 154, 59, 0, 0, 0, 0,
+    /// 1, 0, 0, 0, 0, 0, 0, 0]`.
     pub fn unpack(input: &[u8]) -> Result<Self, ProgramError> {
         let (tag, rest) = input
             .split_first()
             .ok_or(ProgramError::InvalidInstructionData)?;
 
-        Ok(match tag {
-            0 => {
-                let payee_pubkey: Pubkey = Pubkey::new(&rest[..32]);
-                let payer_pubkey: Pubkey = Pubkey::new(&rest[32..64]);
-                let deposit: u64 = Self::unpack_u64(rest, 64)?;
-                let rent_amount: u64 = Self::unpack_u64(rest, 72)?;
-                let duration: u64 = Self::unpack_u64(rest, 80)?;
-                let duration_unit: u8 = rest[88];
-
-                Self::InitializeRentContract {
-                    payee_pubkey,
-                    payer_pubkey,
-                    deposit,
+        let instruction = match tag {
+            0 => Self::InitializeRentContract {
+                terms: AgreementTerms::unpack(rest)?,
+            },
+            1 => {
+                let rent_amount: u64 = Self::unpack_u64(rest, 0)?;
+                // Clients paying a single period may omit the number of periods
+                let periods: u64 = if rest.len() > 8 {
+                    Self::unpack_u64(rest, 8)?
+                } else {
+                    1
+                };
+                Self::PayRent {
                     rent_amount,
-                    duration,
-                    duration_unit,
+                    periods,
                 }
             }
-            1 => {
+            2 => Self::TerminateEarly {},
+            3 => Self::ViewAgreement {},
+            4 => {
+                let amount: u64 = Self::unpack_u64(rest, 0)?;
+                Self::IncreaseDeposit { amount }
+            }
+            5 => Self::TopUpRentExemption {},
+            6 => Self::AcceptAgreement {},
+            7 => Self::GiveNotice {},
+            8 => Self::RefundDeposit {},
+            9 => Self::VerifyEscrow {},
+            10 => Self::AccrueDepositInterest {},
+            11 => Self::CloseAgreement {},
+            12 => {
+                let new_payee: Pubkey = Self::unpack_pubkey(rest, 0)?;
+                Self::CorrectPayee { new_payee }
+            }
+            13 => Self::PrepayWholeLease {},
+            14 => {
                 let rent_amount: u64 = Self::unpack_u64(rest, 0)?;
-                Self::PayRent { rent_amount }
+                Self::UpdateRentAmount { rent_amount }
             }
-            2 => Self::TerminateEarly {},
+            15 => {
+                let periods: u64 = Self::unpack_u64(rest, 0)?;
+                Self::ExtendAgreement { periods }
+            }
+            16 => {
+                let new_payer: Pubkey = Self::unpack_pubkey(rest, 0)?;
+                Self::TransferTenant { new_payer }
+            }
+            17 => Self::GetPayoffAmount {},
+            18 => {
+                let hash: [u8; 32] = rest
+                    .get(..32)
+                    .and_then(|slice| slice.try_into().ok())
+                    .ok_or(ProgramError::InvalidInstructionData)?;
+                Self::SetDocumentHash { hash }
+            }
+            #[cfg(feature = "dev-reset")]
+            19 => Self::ResetAgreement {},
+            20 => Self::GenerateSettlementReport {},
+            21 => Self::GetHealthScore {},
+            22 => Self::CheckReminders {},
+            23 => Self::CompleteSettlement {},
+            24 => {
+                let additional_seconds: i64 = Self::unpack_i64(rest, 0)?;
+                Self::ExtendEndDate { additional_seconds }
+            }
+            #[cfg(feature = "debug-instructions")]
+            25 => Self::DumpState {},
+            26 => Self::WriteLeaseTemplate {
+                duration: Self::unpack_u64(rest, 0)?,
+                duration_unit: Self::unpack_u8(rest, 8)?,
+                late_fee_bps: Self::unpack_u16(rest, 9)?,
+                notice_period_seconds: Self::unpack_u64(rest, 11)?,
+                termination_penalty_bps: Self::unpack_u16(rest, 19)?,
+                termination_penalty_max: Self::unpack_u64(rest, 21)?,
+            },
+            27 => {
+                let payee_pubkey: Pubkey = Self::unpack_pubkey(rest, 0)?;
+                let payer_pubkey: Pubkey = Self::unpack_pubkey(rest, 32)?;
+                Self::InitializeFromTemplate {
+                    payee_pubkey,
+                    payer_pubkey,
+                    deposit: Self::unpack_u64(rest, 64)?,
+                    rent_amount: Self::unpack_u64(rest, 72)?,
+                    agreement_index: Self::unpack_u32(rest, 80)?,
+                    overrides: TemplateOverrides::unpack(
+                        rest.get(84..).ok_or(ProgramError::InvalidInstructionData)?,
+                    )?,
+                }
+            }
+            28 => {
+                let return_amount: u64 = Self::unpack_u64(rest, 0)?;
+                let deduction_codes: [u8; 4] = rest
+                    .get(8..12)
+                    .and_then(|slice| slice.try_into().ok())
+                    .ok_or(ProgramError::InvalidInstructionData)?;
+                Self::ReturnDepositItemized {
+                    return_amount,
+                    deduction_codes,
+                }
+            }
+            29 => Self::Freeze {},
+            30 => Self::Unfreeze {},
+            31 => {
+                let assigned_payee: Pubkey = Self::unpack_pubkey(rest, 0)?;
+                let until_period: u64 = Self::unpack_u64(rest, 32)?;
+                Self::AssignRent {
+                    assigned_payee,
+                    until_period,
+                }
+            }
+            32 => {
+                let amount: u64 = Self::unpack_u64(rest, 0)?;
+                Self::PayLateFees { amount }
+            }
+            #[cfg(feature = "debug-instructions")]
+            33 => Self::EmitAccountBlob {},
             _ => return Err(ProgramError::InvalidInstructionData),
-        })
+        };
+
+        #[cfg(feature = "strict-instruction-parsing")]
+        instruction.reject_trailing_bytes(input.len())?;
+
+        Ok(instruction)
+    }
+
+    /// Reject instruction data longer than the variant reads, so clients cannot append bytes
+    /// that are silently ignored
+    #[cfg(feature = "strict-instruction-parsing")]
+    fn reject_trailing_bytes(&self, input_len: usize) -> Result<(), ProgramError> {
+        let expected_len = self.pack().len();
+        // Clients paying a single period may omit the number of periods
+        let single_period_pay_rent =
+            matches!(self, Self::PayRent { periods: 1, .. }) && input_len == 1 + 8;
+
+        if input_len != expected_len && !single_period_pay_rent {
+            solana_program::msg!(
+                "[RentShare] Instruction data length {} does not match the expected {}",
+                input_len,
+                expected_len
+            );
+            return Err(ProgramError::InvalidInstructionData);
+        }
+
+        Ok(())
+    }
+
+    /// Serialize the instruction into the little-endian layout `unpack` parses
+    pub fn pack(&self) -> Vec<u8> {
+        let mut buf = Vec::new();
+        match self {
+            Self::InitializeRentContract { terms } => {
+                buf.push(0);
+                terms.pack_into(&mut buf);
+            }
+            Self::PayRent {
+                rent_amount,
+                periods,
+            } => {
+                buf.push(1);
+                buf.extend_from_slice(&rent_amount.to_le_bytes());
+                buf.extend_from_slice(&periods.to_le_bytes());
+            }
+            Self::TerminateEarly {} => buf.push(2),
+            Self::ViewAgreement {} => buf.push(3),
+            Self::IncreaseDeposit { amount } => {
+                buf.push(4);
+                buf.extend_from_slice(&amount.to_le_bytes());
+            }
+            Self::TopUpRentExemption {} => buf.push(5),
+            Self::AcceptAgreement {} => buf.push(6),
+            Self::GiveNotice {} => buf.push(7),
+            Self::RefundDeposit {} => buf.push(8),
+            Self::VerifyEscrow {} => buf.push(9),
+            Self::AccrueDepositInterest {} => buf.push(10),
+            Self::CloseAgreement {} => buf.push(11),
+            Self::CorrectPayee { new_payee } => {
+                buf.push(12);
+                buf.extend_from_slice(new_payee.as_ref());
+            }
+            Self::PrepayWholeLease {} => buf.push(13),
+            Self::UpdateRentAmount { rent_amount } => {
+                buf.push(14);
+                buf.extend_from_slice(&rent_amount.to_le_bytes());
+            }
+            Self::ExtendAgreement { periods } => {
+                buf.push(15);
+                buf.extend_from_slice(&periods.to_le_bytes());
+            }
+            Self::TransferTenant { new_payer } => {
+                buf.push(16);
+                buf.extend_from_slice(new_payer.as_ref());
+            }
+            Self::GetPayoffAmount {} => buf.push(17),
+            Self::SetDocumentHash { hash } => {
+                buf.push(18);
+                buf.extend_from_slice(hash);
+            }
+            #[cfg(feature = "dev-reset")]
+            Self::ResetAgreement {} => buf.push(19),
+            Self::GenerateSettlementReport {} => buf.push(20),
+            Self::GetHealthScore {} => buf.push(21),
+            Self::CheckReminders {} => buf.push(22),
+            Self::CompleteSettlement {} => buf.push(23),
+            Self::ExtendEndDate { additional_seconds } => {
+                buf.push(24);
+                buf.extend_from_slice(&additional_seconds.to_le_bytes());
+            }
+            #[cfg(feature = "debug-instructions")]
+            Self::DumpState {} => buf.push(25),
+            Self::WriteLeaseTemplate {
+                duration,
+                duration_unit,
+                late_fee_bps,
+                notice_period_seconds,
+                termination_penalty_bps,
+                termination_penalty_max,
+            } => {
+                buf.push(26);
+                buf.extend_from_slice(&duration.to_le_bytes());
+                buf.push(*duration_unit);
+                buf.extend_from_slice(&late_fee_bps.to_le_bytes());
+                buf.extend_from_slice(&notice_period_seconds.to_le_bytes());
+                buf.extend_from_slice(&termination_penalty_bps.to_le_bytes());
+                buf.extend_from_slice(&termination_penalty_max.to_le_bytes());
+            }
+            Self::InitializeFromTemplate {
+                payee_pubkey,
+                payer_pubkey,
+                deposit,
+                rent_amount,
+                agreement_index,
+                overrides,
+            } => {
+                buf.push(27);
+                buf.extend_from_slice(payee_pubkey.as_ref());
+                buf.extend_from_slice(payer_pubkey.as_ref());
+                buf.extend_from_slice(&deposit.to_le_bytes());
+                buf.extend_from_slice(&rent_amount.to_le_bytes());
+                buf.extend_from_slice(&agreement_index.to_le_bytes());
+                overrides.pack_into(&mut buf);
+            }
+            Self::ReturnDepositItemized {
+                return_amount,
+                deduction_codes,
+            } => {
+                buf.push(28);
+                buf.extend_from_slice(&return_amount.to_le_bytes());
+                buf.extend_from_slice(deduction_codes);
+            }
+            Self::Freeze {} => buf.push(29),
+            Self::Unfreeze {} => buf.push(30),
+            Self::AssignRent {
+                assigned_payee,
+                until_period,
+            } => {
+                buf.push(31);
+                buf.extend_from_slice(assigned_payee.as_ref());
+                buf.extend_from_slice(&until_period.to_le_bytes());
+            }
+            Self::PayLateFees { amount } => {
+                buf.push(32);
+                buf.extend_from_slice(&amount.to_le_bytes());
+            }
+            #[cfg(feature = "debug-instructions")]
+            Self::EmitAccountBlob {} => buf.push(33),
+        }
+        buf
+    }
+
+    fn unpack_pubkey(input: &[u8], start: usize) -> Result<Pubkey, ProgramError> {
+        let value = input
+            .get(start..32 + start)
+            .map(Pubkey::new)
+            .ok_or(ProgramError::InvalidInstructionData)?;
+        Ok(value)
     }
 
     fn unpack_u64(input: &[u8], start: usize) -> Result<u64, ProgramError> {
@@ -74,4 +1119,217 @@ impl RentShareInstruction {
             .ok_or(ProgramError::InvalidInstructionData)?;
         Ok(value)
     }
+
+    fn unpack_i64(input: &[u8], start: usize) -> Result<i64, ProgramError> {
+        let value = input
+            .get(start..8 + start)
+            .and_then(|slice| slice.try_into().ok())
+            .map(i64::from_le_bytes)
+            .ok_or(ProgramError::InvalidInstructionData)?;
+        Ok(value)
+    }
+
+    fn unpack_u32(input: &[u8], start: usize) -> Result<u32, ProgramError> {
+        let value = input
+            .get(start..4 + start)
+            .and_then(|slice| slice.try_into().ok())
+            .map(u32::from_le_bytes)
+            .ok_or(ProgramError::InvalidInstructionData)?;
+        Ok(value)
+    }
+
+    fn unpack_u16(input: &[u8], start: usize) -> Result<u16, ProgramError> {
+        let value = input
+            .get(start..2 + start)
+            .and_then(|slice| slice.try_into().ok())
+            .map(u16::from_le_bytes)
+            .ok_or(ProgramError::InvalidInstructionData)?;
+        Ok(value)
+    }
+
+    fn unpack_u8(input: &[u8], start: usize) -> Result<u8, ProgramError> {
+        let value = *input
+            .get(start)
+            .ok_or(ProgramError::InvalidInstructionData)?;
+        Ok(value)
+    }
+
+    fn unpack_bool(input: &[u8], start: usize) -> Result<bool, ProgramError> {
+        match input.get(start) {
+            Some(0) => Ok(false),
+            Some(1) => Ok(true),
+            _ => Err(ProgramError::InvalidInstructionData),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Length of `InitializeRentContract` data: the tag and the fixed size fields
+    const INITIALIZE_LEN: usize = 1 + 359;
+
+    #[test]
+    fn pay_rent_packs_little_endian_fields() {
+        let instruction = RentShareInstruction::PayRent {
+            rent_amount: 1_000_000_000,
+            periods: 1,
+        };
+        let data = [1, 0, 202, 154, 59, 0, 0, 0, 0, 1, 0, 0, 0, 0, 0, 0, 0];
+
+        assert_eq!(instruction.pack(), data);
+        assert_eq!(RentShareInstruction::unpack(&data), Ok(instruction));
+        // Clients paying a single period may omit the number of periods
+        assert_eq!(
+            RentShareInstruction::unpack(&data[..9]),
+            Ok(RentShareInstruction::PayRent {
+                rent_amount: 1_000_000_000,
+                periods: 1,
+            })
+        );
+    }
+
+    #[test]
+    fn initialize_packs_terms_at_fixed_offsets() {
+        let payee_pubkey = Pubkey::new_unique();
+        let data = RentShareInstruction::InitializeRentContract {
+            terms: AgreementTerms {
+                payee_pubkey,
+                rent_amount: 0x0102_0304,
+                agreement_index: 0x0a0b_0c0d,
+                termination_platform_fee: u64::MAX - 1,
+                ..AgreementTerms::default()
+            },
+        }
+        .pack();
+
+        assert_eq!(data[0], 0);
+        assert_eq!(&data[1..33], payee_pubkey.as_ref());
+        assert_eq!(&data[1 + 72..1 + 80], &[4, 3, 2, 1, 0, 0, 0, 0]);
+        assert_eq!(&data[1 + 92..1 + 96], &[0x0d, 0x0c, 0x0b, 0x0a]);
+        assert_eq!(&data[1 + 351..], &[254, 255, 255, 255, 255, 255, 255, 255]);
+    }
+
+    #[test]
+    fn payment_notification_packs_after_its_discriminator() {
+        let notification = PaymentNotification {
+            rent_paid: 1,
+            late_fees: 2,
+            periods: 3,
+            remaining_payments: 4,
+            paid_at: -1,
+        };
+        let data = notification.pack();
+
+        assert_eq!(data.len(), PaymentNotification::LEN);
+        assert_eq!(&data[..8], b"rspaid\0\0");
+        assert_eq!(&data[8..16], &1u64.to_le_bytes());
+        assert_eq!(&data[40..], &[255; 8]);
+        assert_eq!(PaymentNotification::unpack(&data), Ok(notification));
+    }
+
+    #[test]
+    fn initialize_round_trips_its_terms() {
+        let instruction = RentShareInstruction::InitializeRentContract {
+            terms: AgreementTerms {
+                payee_pubkey: Pubkey::new_unique(),
+                payer_pubkey: Pubkey::new_unique(),
+                deposit: 2_000_000,
+                rent_amount: 1_000_000,
+                duration: 12,
+                late_fee_bps: 500,
+                agreement_index: 7,
+                admin_pubkey: Pubkey::new_unique(),
+                termination_platform_fee: 10_000,
+                currency_decimals: 6,
+                currency_symbol: *b"USDC\0\0\0\0",
+                ..AgreementTerms::default()
+            },
+        };
+
+        let data = instruction.pack();
+        assert_eq!(data.len(), INITIALIZE_LEN);
+        assert_eq!(RentShareInstruction::unpack(&data), Ok(instruction));
+    }
+
+    #[test]
+    fn initialize_rejects_truncated_data() {
+        let mut data = vec![0; INITIALIZE_LEN];
+        assert!(RentShareInstruction::unpack(&data).is_ok());
+
+        for len in 1..INITIALIZE_LEN {
+            data.truncate(len);
+            assert_eq!(
+                RentShareInstruction::unpack(&data),
+                Err(ProgramError::InvalidInstructionData),
+                "data truncated to {} bytes",
+                len
+            );
+        }
+    }
+
+    fn initialize_from_template(overrides: TemplateOverrides) -> RentShareInstruction {
+        RentShareInstruction::InitializeFromTemplate {
+            payee_pubkey: Pubkey::new_unique(),
+            payer_pubkey: Pubkey::new_unique(),
+            deposit: 2_000_000,
+            rent_amount: 1_000_000,
+            agreement_index: 3,
+            overrides,
+        }
+    }
+
+    #[test]
+    fn template_overrides_round_trip_set_fields_only() {
+        let every_field = TemplateOverrides {
+            duration: Some(6),
+            duration_unit: Some(1),
+            late_fee_bps: Some(250),
+            notice_period_seconds: Some(86_400),
+            termination_penalty_bps: Some(1_000),
+            termination_penalty_max: Some(u64::MAX),
+        };
+        let some_fields = TemplateOverrides {
+            late_fee_bps: Some(0),
+            termination_penalty_max: Some(5),
+            ..TemplateOverrides::default()
+        };
+
+        for (overrides, mask, len) in [
+            (TemplateOverrides::default(), 0, 1),
+            (every_field, 0b11_1111, 1 + 8 + 1 + 2 + 8 + 2 + 8),
+            (some_fields, 0b10_0100, 1 + 2 + 8),
+        ] {
+            let instruction = initialize_from_template(overrides);
+            let data = instruction.pack();
+
+            assert_eq!(data[1 + 84], mask);
+            assert_eq!(data.len(), 1 + 84 + len);
+            assert_eq!(RentShareInstruction::unpack(&data), Ok(instruction));
+        }
+    }
+
+    #[test]
+    fn template_overrides_reject_unknown_bits_and_missing_fields() {
+        let mut data = initialize_from_template(TemplateOverrides::default()).pack();
+        data[1 + 84] = 1 << 6;
+        assert_eq!(
+            RentShareInstruction::unpack(&data),
+            Err(ProgramError::InvalidInstructionData)
+        );
+
+        // The mask announces a duration that is not there
+        data[1 + 84] = 1;
+        assert_eq!(
+            RentShareInstruction::unpack(&data),
+            Err(ProgramError::InvalidInstructionData)
+        );
+
+        data.truncate(1 + 84);
+        assert_eq!(
+            RentShareInstruction::unpack(&data),
+            Err(ProgramError::InvalidInstructionData)
+        );
+    }
 }
@@ -0,0 +1,127 @@
+use solana_program::{
+    instruction::{AccountMeta, Instruction},
+    program_error::ProgramError,
+    pubkey::Pubkey,
+    system_program,
+};
+
+use crate::{instruction::RentShareInstruction, math};
+
+/// Build a single `PayRent` instruction prepaying `periods` periods of `rent_amount` each
+pub fn pay_rent_many_ix(
+    program_id: &Pubkey,
+    agreement: &Pubkey,
+    payer: &Pubkey,
+    payee: &Pubkey,
+    system_program: &Pubkey,
+    rent_amount: u64,
+    periods: u64,
+) -> Result<Instruction, ProgramError> {
+    if periods == 0 {
+        return Err(ProgramError::InvalidArgument);
+    }
+
+    if *system_program != system_program::id() {
+        return Err(ProgramError::IncorrectProgramId);
+    }
+
+    if agreement == payer || agreement == payee || payer == payee {
+        return Err(ProgramError::InvalidArgument);
+    }
+
+    let total_amount = math::mul(rent_amount, periods)?;
+
+    Ok(Instruction {
+        program_id: *program_id,
+        accounts: vec![
+            AccountMeta::new(*agreement, false),
+            AccountMeta::new(*payee, false),
+            AccountMeta::new(*payer, true),
+            AccountMeta::new_readonly(*system_program, false),
+        ],
+        data: RentShareInstruction::PayRent {
+            rent_amount: total_amount,
+            periods,
+        }
+        .pack(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::RentShareError;
+
+    #[test]
+    fn pay_rent_many_ix_sums_the_periods_into_one_payment() {
+        let program_id = Pubkey::new_unique();
+        let agreement = Pubkey::new_unique();
+        let payer = Pubkey::new_unique();
+        let payee = Pubkey::new_unique();
+
+        let instruction = pay_rent_many_ix(
+            &program_id,
+            &agreement,
+            &payer,
+            &payee,
+            &system_program::id(),
+            1_000_000,
+            3,
+        )
+        .unwrap();
+
+        assert_eq!(instruction.program_id, program_id);
+        assert_eq!(
+            instruction.accounts,
+            vec![
+                AccountMeta::new(agreement, false),
+                AccountMeta::new(payee, false),
+                AccountMeta::new(payer, true),
+                AccountMeta::new_readonly(system_program::id(), false),
+            ]
+        );
+        assert_eq!(
+            RentShareInstruction::unpack(&instruction.data),
+            Ok(RentShareInstruction::PayRent {
+                rent_amount: 3_000_000,
+                periods: 3,
+            })
+        );
+    }
+
+    #[test]
+    fn pay_rent_many_ix_rejects_invalid_arguments() {
+        let program_id = Pubkey::new_unique();
+        let agreement = Pubkey::new_unique();
+        let payer = Pubkey::new_unique();
+        let payee = Pubkey::new_unique();
+        let build = |payee: &Pubkey, system_program: &Pubkey, rent_amount, periods| {
+            pay_rent_many_ix(
+                &program_id,
+                &agreement,
+                &payer,
+                payee,
+                system_program,
+                rent_amount,
+                periods,
+            )
+        };
+
+        assert_eq!(
+            build(&payee, &system_program::id(), 1_000_000, 0),
+            Err(ProgramError::InvalidArgument)
+        );
+        assert_eq!(
+            build(&payee, &system_program::id(), u64::MAX / 2 + 1, 2),
+            Err(RentShareError::ArithmeticOverflow.into())
+        );
+        assert_eq!(
+            build(&payee, &Pubkey::new_unique(), 1_000_000, 1),
+            Err(ProgramError::IncorrectProgramId)
+        );
+        assert_eq!(
+            build(&payer, &system_program::id(), 1_000_000, 1),
+            Err(ProgramError::InvalidArgument)
+        );
+    }
+}
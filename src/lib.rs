@@ -1,7 +1,13 @@
+pub mod accounts;
 pub mod error;
+pub mod fees;
 pub mod instruction;
+pub mod instruction_builder;
+pub mod math;
 pub mod processor;
 pub mod state;
 
+pub use state::AGREEMENT_ACCOUNT_SPACE;
+
 #[cfg(not(feature = "no-entrypoint"))]
 pub mod entrypoint;
@@ -0,0 +1,55 @@
+use solana_program::{msg, program_error::ProgramError};
+
+use crate::error::RentShareError;
+
+/// Add two amounts, failing on overflow
+pub fn add(a: u64, b: u64) -> Result<u64, ProgramError> {
+    a.checked_add(b).ok_or_else(|| {
+        msg!("[RentShare] Arithmetic overflow: {} + {}", a, b);
+        RentShareError::ArithmeticOverflow.into()
+    })
+}
+
+/// Subtract `b` from `a`, failing on underflow
+pub fn sub(a: u64, b: u64) -> Result<u64, ProgramError> {
+    a.checked_sub(b).ok_or_else(|| {
+        msg!("[RentShare] Arithmetic underflow: {} - {}", a, b);
+        RentShareError::ArithmeticOverflow.into()
+    })
+}
+
+/// Multiply two amounts, failing on overflow
+pub fn mul(a: u64, b: u64) -> Result<u64, ProgramError> {
+    a.checked_mul(b).ok_or_else(|| {
+        msg!("[RentShare] Arithmetic overflow: {} * {}", a, b);
+        RentShareError::ArithmeticOverflow.into()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn overflow() -> Result<u64, ProgramError> {
+        Err(RentShareError::ArithmeticOverflow.into())
+    }
+
+    #[test]
+    fn add_fails_past_u64_max() {
+        assert_eq!(add(u64::MAX - 1, 1), Ok(u64::MAX));
+        assert_eq!(add(u64::MAX, 1), overflow());
+    }
+
+    #[test]
+    fn sub_fails_below_zero() {
+        assert_eq!(sub(1, 1), Ok(0));
+        assert_eq!(sub(0, 1), overflow());
+    }
+
+    #[test]
+    fn mul_fails_past_u64_max() {
+        assert_eq!(mul(u64::MAX, 1), Ok(u64::MAX));
+        assert_eq!(mul(u64::MAX, 0), Ok(0));
+        assert_eq!(mul(u64::MAX / 2 + 1, 2), overflow());
+    }
+}
@@ -1,20 +1,32 @@
-use borsh::{BorshDeserialize, BorshSerialize};
 use solana_program::{
     account_info::{next_account_info, AccountInfo},
     entrypoint::ProgramResult,
+    instruction::{AccountMeta, Instruction},
     msg,
-    program::invoke,
+    program::{invoke, invoke_signed},
     program_error::ProgramError,
     program_pack::IsInitialized,
     pubkey::Pubkey,
-    system_instruction,
-    sysvar::{rent::Rent, Sysvar},
+    system_instruction, system_program,
+    sysvar::{self, clock::Clock, rent::Rent, Sysvar},
 };
+use std::convert::TryFrom;
+
+#[cfg(feature = "debug-instructions")]
+use {borsh::BorshSerialize, solana_program::log::sol_log_data};
 
 use crate::{
+    accounts::{PayRentAccounts, PayeeAccount, TerminateAccounts},
     error::RentShareError,
-    instruction::RentShareInstruction,
-    state::{AgreementStatus, RentShareAccount},
+    instruction::{AgreementTerms, PaymentNotification, RentShareInstruction, TemplateOverrides},
+    math,
+    state::{
+        find_agreement_address, AgreementStatus, Amendment, Duration, LeaseTemplate, Reminder,
+        RentShareAccount, AGREEMENT_ACCOUNT_SPACE, AGREEMENT_SEED,
+        DEFAULT_SUSPICIOUS_AMOUNT_THRESHOLD, MAX_AGREEMENT_ACCOUNT_DATA_LEN, MAX_BASIS_POINTS,
+        MAX_CURRENCY_DECIMALS, MAX_DEPOSIT, MAX_READ_FEE_LAMPORTS, MAX_RENT_AMOUNT, SOL_DECIMALS,
+        SOL_SYMBOL,
+    },
 };
 
 pub struct Processor;
@@ -25,52 +37,269 @@ impl Processor {
         instruction_data: &[u8],
     ) -> ProgramResult {
         let instruction = RentShareInstruction::unpack(instruction_data)?;
+        Self::validate_accounts(&instruction, accounts, program_id)?;
+        Self::require_not_frozen(&instruction, accounts)?;
 
         match instruction {
-            RentShareInstruction::InitializeRentContract {
+            RentShareInstruction::InitializeRentContract { terms } => {
+                Self::initialize_rent_contract(accounts, program_id, terms)
+            }
+            RentShareInstruction::PayRent {
+                rent_amount,
+                periods,
+            } => Self::pay_rent(accounts, program_id, rent_amount, periods, true),
+            RentShareInstruction::TerminateEarly {} => Self::terminate_early(accounts, program_id),
+            RentShareInstruction::ViewAgreement {} => Self::view_agreement(accounts),
+            RentShareInstruction::IncreaseDeposit { amount } => {
+                Self::increase_deposit(accounts, amount)
+            }
+            RentShareInstruction::TopUpRentExemption {} => Self::top_up_rent_exemption(accounts),
+            RentShareInstruction::AcceptAgreement {} => Self::accept_agreement(accounts),
+            RentShareInstruction::GiveNotice {} => Self::give_notice(accounts),
+            RentShareInstruction::RefundDeposit {} => Self::refund_deposit(accounts),
+            RentShareInstruction::VerifyEscrow {} => Self::verify_escrow(accounts),
+            RentShareInstruction::AccrueDepositInterest {} => {
+                Self::accrue_deposit_interest(accounts)
+            }
+            RentShareInstruction::CloseAgreement {} => Self::close_agreement(accounts),
+            RentShareInstruction::CorrectPayee { new_payee } => {
+                Self::correct_payee(accounts, new_payee)
+            }
+            RentShareInstruction::PrepayWholeLease {} => {
+                Self::prepay_whole_lease(accounts, program_id)
+            }
+            RentShareInstruction::UpdateRentAmount { rent_amount } => {
+                Self::update_rent_amount(accounts, rent_amount)
+            }
+            RentShareInstruction::ExtendAgreement { periods } => {
+                Self::extend_agreement(accounts, periods)
+            }
+            RentShareInstruction::TransferTenant { new_payer } => {
+                Self::transfer_tenant(accounts, new_payer)
+            }
+            RentShareInstruction::GetPayoffAmount {} => Self::get_payoff_amount(accounts),
+            RentShareInstruction::SetDocumentHash { hash } => {
+                Self::set_document_hash(accounts, hash)
+            }
+            #[cfg(feature = "dev-reset")]
+            RentShareInstruction::ResetAgreement {} => Self::reset_agreement(accounts),
+            #[cfg(feature = "debug-instructions")]
+            RentShareInstruction::DumpState {} => Self::dump_state(accounts),
+            #[cfg(feature = "debug-instructions")]
+            RentShareInstruction::EmitAccountBlob {} => Self::emit_account_blob(accounts),
+            RentShareInstruction::WriteLeaseTemplate {
+                duration,
+                duration_unit,
+                late_fee_bps,
+                notice_period_seconds,
+                termination_penalty_bps,
+                termination_penalty_max,
+            } => Self::write_lease_template(
+                accounts,
+                LeaseTemplate {
+                    is_initialized: true,
+                    authority: Pubkey::default(),
+                    duration,
+                    duration_unit,
+                    late_fee_bps,
+                    notice_period_seconds,
+                    termination_penalty_bps,
+                    termination_penalty_max,
+                },
+            ),
+            RentShareInstruction::InitializeFromTemplate {
                 payee_pubkey,
                 payer_pubkey,
                 deposit,
                 rent_amount,
-                duration,
-                duration_unit,
-            } => Self::initialize_rent_contract(
+                agreement_index,
+                overrides,
+            } => Self::initialize_from_template(
                 accounts,
                 program_id,
                 payee_pubkey,
                 payer_pubkey,
                 deposit,
                 rent_amount,
-                duration,
-                duration_unit,
+                agreement_index,
+                overrides,
             ),
-            RentShareInstruction::PayRent { rent_amount } => {
-                Self::pay_rent(accounts, program_id, rent_amount)
+            RentShareInstruction::ReturnDepositItemized {
+                return_amount,
+                deduction_codes,
+            } => Self::return_deposit_itemized(accounts, return_amount, deduction_codes),
+            RentShareInstruction::Freeze {} => Self::set_frozen(accounts, true),
+            RentShareInstruction::Unfreeze {} => Self::set_frozen(accounts, false),
+            RentShareInstruction::AssignRent {
+                assigned_payee,
+                until_period,
+            } => Self::assign_rent(accounts, assigned_payee, until_period),
+            RentShareInstruction::PayLateFees { amount } => {
+                Self::pay_late_fees(accounts, program_id, amount)
+            }
+            RentShareInstruction::GenerateSettlementReport {} => {
+                Self::generate_settlement_report(accounts)
+            }
+            RentShareInstruction::GetHealthScore {} => Self::get_health_score(accounts),
+            RentShareInstruction::CheckReminders {} => Self::check_reminders(accounts),
+            RentShareInstruction::CompleteSettlement {} => Self::complete_settlement(accounts),
+            RentShareInstruction::ExtendEndDate { additional_seconds } => {
+                Self::extend_end_date(accounts, additional_seconds)
             }
-            RentShareInstruction::TerminateEarly {} => Self::terminate_early(accounts, program_id),
         }
     }
 
-    #[allow(clippy::too_many_arguments)]
     fn initialize_rent_contract(
         accounts: &[AccountInfo],
         program_id: &Pubkey,
-        payee_pubkey: Pubkey,
-        payer_pubkey: Pubkey,
-        deposit: u64,
-        rent_amount: u64,
-        duration: u64,
-        duration_unit: u8,
+        terms: AgreementTerms,
     ) -> ProgramResult {
+        let AgreementTerms {
+            payee_pubkey,
+            payer_pubkey,
+            deposit,
+            rent_amount,
+            duration,
+            duration_unit,
+            deposit_forfeit_on_early_termination,
+            late_fee_bps,
+            agreement_index,
+            notice_period_seconds,
+            replenish_deposit_first,
+            max_missed_payments,
+            auto_terminate_on_default,
+            deposit_interest_bps,
+            currency_decimals,
+            currency_symbol,
+            deposit_refund_destination,
+            defer_first_payment,
+            proration_min_unit,
+            free_periods,
+            reject_suspicious_amounts,
+            suspicious_amount_threshold,
+            max_prepay_periods,
+            required_deposit,
+            reminder_lead_seconds,
+            final_settlement_grace_seconds,
+            termination_penalty_bps,
+            termination_penalty_max,
+            prepay_first_period,
+            overpay_to_deposit,
+            platform_pubkey,
+            read_fee_lamports,
+            observer_program,
+            observer_required,
+            admin_pubkey,
+            late_fee_escalation_bps,
+            late_fee_max,
+            rent_change_cooldown_seconds,
+            non_refundable_deposit,
+            termination_platform_fee,
+        } = terms;
+
         let accounts_iter = &mut accounts.iter();
 
         let rent_agreement_account = next_account_info(accounts_iter)?;
+        let solana_rent = &Self::load_rent_sysvar(next_account_info(accounts_iter)?)?;
+        let payer_account = next_account_info(accounts_iter)?;
+        let system_program_account = next_account_info(accounts_iter)?;
+
+        // Each agreement between the same parties lives at the address derived from its index
+        let (agreement_address, bump_seed) =
+            find_agreement_address(program_id, &payee_pubkey, &payer_pubkey, agreement_index);
+        if agreement_address != *rent_agreement_account.key {
+            msg!(
+                "[RentShare] Rent agreement account does not match the address for index {}",
+                agreement_index
+            );
+            return Err(ProgramError::InvalidSeeds);
+        }
+
+        // Create the agreement account funded by the payer when it has not been allocated yet
+        if *rent_agreement_account.owner == system_program::id() {
+            let agreement_index_bytes = agreement_index.to_le_bytes();
+            let agreement_seeds: &[&[u8]] = &[
+                AGREEMENT_SEED,
+                payee_pubkey.as_ref(),
+                payer_pubkey.as_ref(),
+                &agreement_index_bytes,
+                &[bump_seed],
+            ];
+            let required_lamports = solana_rent.minimum_balance(AGREEMENT_ACCOUNT_SPACE);
+
+            if rent_agreement_account.lamports() == 0 {
+                let instruction = system_instruction::create_account(
+                    payer_account.key,
+                    rent_agreement_account.key,
+                    required_lamports,
+                    AGREEMENT_ACCOUNT_SPACE as u64,
+                    program_id,
+                );
+
+                invoke_signed(
+                    &instruction,
+                    &[
+                        system_program_account.clone(),
+                        payer_account.clone(),
+                        rent_agreement_account.clone(),
+                    ],
+                    &[agreement_seeds],
+                )?;
+            } else {
+                // Anyone can send lamports to the derived address, and `create_account` rejects an
+                // address that already holds some, so the balance is topped up to rent exemption and
+                // the account allocated and assigned instead
+                let top_up = required_lamports.saturating_sub(rent_agreement_account.lamports());
+                if top_up > 0 {
+                    let instruction = system_instruction::transfer(
+                        payer_account.key,
+                        rent_agreement_account.key,
+                        top_up,
+                    );
+
+                    invoke(
+                        &instruction,
+                        &[
+                            system_program_account.clone(),
+                            payer_account.clone(),
+                            rent_agreement_account.clone(),
+                        ],
+                    )?;
+                }
+
+                let instruction = system_instruction::allocate(
+                    rent_agreement_account.key,
+                    AGREEMENT_ACCOUNT_SPACE as u64,
+                );
+                invoke_signed(
+                    &instruction,
+                    &[
+                        system_program_account.clone(),
+                        rent_agreement_account.clone(),
+                    ],
+                    &[agreement_seeds],
+                )?;
+
+                let instruction =
+                    system_instruction::assign(rent_agreement_account.key, program_id);
+                invoke_signed(
+                    &instruction,
+                    &[
+                        system_program_account.clone(),
+                        rent_agreement_account.clone(),
+                    ],
+                    &[agreement_seeds],
+                )?;
+            }
+        }
+
         if rent_agreement_account.owner != program_id {
             msg!("[RentShare] Rent agreement account not owned by this program");
             return Err(ProgramError::IncorrectProgramId);
         }
 
-        let solana_rent = &Rent::from_account_info(next_account_info(accounts_iter)?)?;
+        Self::require_sane_data_len(rent_agreement_account)?;
+
         // Make sure this account is rent exemtpt
         if !solana_rent.is_exempt(
             rent_agreement_account.lamports(),
@@ -83,10 +312,174 @@ impl Processor {
             return Err(ProgramError::AccountNotRentExempt);
         }
 
+        if late_fee_bps > MAX_BASIS_POINTS {
+            msg!("[RentShare] Late fee exceeds 100%: {} bps", late_fee_bps);
+            return Err(RentShareError::InvalidAgreementTerms.into());
+        }
+
+        if late_fee_escalation_bps > MAX_BASIS_POINTS {
+            msg!(
+                "[RentShare] Late fee escalation exceeds 100%: {} bps",
+                late_fee_escalation_bps
+            );
+            return Err(RentShareError::InvalidAgreementTerms.into());
+        }
+
+        if termination_penalty_bps > MAX_BASIS_POINTS {
+            msg!(
+                "[RentShare] Termination penalty exceeds 100%: {} bps",
+                termination_penalty_bps
+            );
+            return Err(RentShareError::InvalidAgreementTerms.into());
+        }
+
+        if deposit_interest_bps > MAX_BASIS_POINTS {
+            msg!(
+                "[RentShare] Deposit interest exceeds 100%: {} bps",
+                deposit_interest_bps
+            );
+            return Err(RentShareError::InvalidAgreementTerms.into());
+        }
+
+        if currency_decimals > MAX_CURRENCY_DECIMALS {
+            msg!(
+                "[RentShare] Currency decimals {} exceed the maximum of {}",
+                currency_decimals,
+                MAX_CURRENCY_DECIMALS
+            );
+            return Err(RentShareError::InvalidAgreementTerms.into());
+        }
+
+        if rent_amount > MAX_RENT_AMOUNT {
+            msg!(
+                "[RentShare] Rent amount {} exceeds the maximum of {}",
+                rent_amount,
+                MAX_RENT_AMOUNT
+            );
+            return Err(RentShareError::InvalidAgreementTerms.into());
+        }
+
+        if proration_min_unit > rent_amount {
+            msg!(
+                "[RentShare] Proration unit {} exceeds the rent amount {}",
+                proration_min_unit,
+                rent_amount
+            );
+            return Err(RentShareError::InvalidAgreementTerms.into());
+        }
+
+        let unit = Duration::try_from(duration_unit)
+            .map_err(|_| ProgramError::from(RentShareError::InvalidAgreementTerms))?;
+        if duration < unit.min_duration() || duration > unit.max_duration() {
+            msg!(
+                "[RentShare] Duration {} {:?} outside the allowed range of {} to {}",
+                duration,
+                unit,
+                unit.min_duration(),
+                unit.max_duration()
+            );
+            return Err(RentShareError::InvalidAgreementTerms.into());
+        }
+
+        if free_periods >= duration {
+            msg!(
+                "[RentShare] Free periods {} must be fewer than the duration {}",
+                free_periods,
+                duration
+            );
+            return Err(RentShareError::InvalidAgreementTerms.into());
+        }
+
+        if deposit > MAX_DEPOSIT {
+            msg!(
+                "[RentShare] Deposit {} exceeds the maximum of {}",
+                deposit,
+                MAX_DEPOSIT
+            );
+            return Err(RentShareError::InvalidAgreementTerms.into());
+        }
+
+        // A staged deposit starts below the required deposit and is collected with `IncreaseDeposit`
+        let required_deposit = if required_deposit == 0 {
+            deposit
+        } else {
+            required_deposit
+        };
+        if required_deposit < deposit || required_deposit > MAX_DEPOSIT {
+            msg!(
+                "[RentShare] Required deposit {} must be between the initial deposit {} and the maximum of {}",
+                required_deposit,
+                deposit,
+                MAX_DEPOSIT
+            );
+            return Err(RentShareError::InvalidAgreementTerms.into());
+        }
+
+        if non_refundable_deposit > required_deposit {
+            msg!(
+                "[RentShare] Non-refundable deposit {} exceeds the deposit of {}",
+                non_refundable_deposit,
+                required_deposit
+            );
+            return Err(RentShareError::InvalidAgreementTerms.into());
+        }
+
+        if read_fee_lamports > MAX_READ_FEE_LAMPORTS {
+            msg!(
+                "[RentShare] Read fee {} exceeds the maximum of {} lamports",
+                read_fee_lamports,
+                MAX_READ_FEE_LAMPORTS
+            );
+            return Err(RentShareError::InvalidAgreementTerms.into());
+        }
+
+        if (read_fee_lamports > 0 || termination_platform_fee > 0)
+            && platform_pubkey == Pubkey::default()
+        {
+            msg!("[RentShare] Platform fees require a platform account to receive them");
+            return Err(RentShareError::InvalidAgreementTerms.into());
+        }
+
+        let threshold = if suspicious_amount_threshold == 0 {
+            DEFAULT_SUSPICIOUS_AMOUNT_THRESHOLD
+        } else {
+            suspicious_amount_threshold
+        };
+        for (name, amount) in [("Rent amount", rent_amount), ("Deposit", deposit)] {
+            if amount > 0 && amount < threshold {
+                msg!(
+                    "[RentShare] WARNING: {} of {} lamports is below {} lamports and may have been entered in SOL",
+                    name,
+                    amount,
+                    threshold
+                );
+                if reject_suspicious_amounts {
+                    return Err(RentShareError::InvalidAgreementTerms.into());
+                }
+            }
+        }
+
+        // The deposit is funded by the payer used in the agreement terms
+        if payer_pubkey != *payer_account.key {
+            msg!("[RentShare] Payer must match payer key used in the agreement terms");
+            return Err(ProgramError::InvalidAccountData);
+        }
+
+        // A prepaid first period is held with the deposit until the payee accepts, unless it is free
+        let first_period_rent = if prepay_first_period && free_periods == 0 {
+            rent_amount
+        } else {
+            0
+        };
+        let escrow_amount = math::add(deposit, first_period_rent)?;
+
+        if payer_account.lamports() < escrow_amount {
+            return Err(ProgramError::InsufficientFunds);
+        }
+
         // Initialize the Rent Agreement Account with the initial data
-        // Note: the structure of the data state must match the `space` reserved when account created
-        let rent_agreement_data =
-            RentShareAccount::try_from_slice(&rent_agreement_account.data.borrow());
+        // Note: the `space` reserved when the account was created must be at least `AGREEMENT_ACCOUNT_SPACE`
+        let rent_agreement_data = RentShareAccount::load(&rent_agreement_account.data.borrow());
 
         if rent_agreement_data.is_err() {
             msg!(
@@ -102,7 +495,13 @@ impl Processor {
             return Err(ProgramError::AccountAlreadyInitialized);
         }
 
-        rent_data.status = AgreementStatus::Active as u8;
+        if !RentShareAccount::is_blank(&rent_agreement_account.data.borrow()) {
+            msg!("[RentShare] Rent agreement account data is not empty");
+            return Err(ProgramError::AccountAlreadyInitialized);
+        }
+
+        let now = Self::current_timestamp(accounts)?;
+        rent_data.set_status(AgreementStatus::PendingAcceptance, now)?;
         rent_data.payee_pubkey = payee_pubkey;
         rent_data.payer_pubkey = payer_pubkey;
         rent_data.rent_amount = rent_amount;
@@ -110,7 +509,89 @@ impl Processor {
         rent_data.duration = duration;
         rent_data.duration_unit = duration_unit;
         rent_data.remaining_payments = duration;
-        rent_data.serialize(&mut &mut rent_agreement_account.data.borrow_mut()[..])?;
+        rent_data.deposit_forfeit_on_early_termination = deposit_forfeit_on_early_termination;
+        rent_data.created_at = now;
+        rent_data.late_fee_bps = late_fee_bps;
+        rent_data.agreement_index = agreement_index;
+        rent_data.notice_period_seconds = notice_period_seconds;
+        rent_data.required_deposit = required_deposit;
+        rent_data.replenish_deposit_first = replenish_deposit_first;
+        rent_data.max_missed_payments = max_missed_payments;
+        rent_data.auto_terminate_on_default = auto_terminate_on_default;
+        rent_data.deposit_interest_bps = deposit_interest_bps;
+        rent_data.defer_first_payment = defer_first_payment;
+        rent_data.proration_min_unit = proration_min_unit;
+        rent_data.free_periods = free_periods;
+        rent_data.prepay_first_period = prepay_first_period;
+        rent_data.overpay_to_deposit = overpay_to_deposit;
+        rent_data.platform_pubkey = platform_pubkey;
+        rent_data.read_fee_lamports = read_fee_lamports;
+        rent_data.observer_program = observer_program;
+        rent_data.observer_required = observer_required;
+        rent_data.admin_pubkey = admin_pubkey;
+        rent_data.late_fee_escalation_bps = late_fee_escalation_bps;
+        rent_data.late_fee_max = late_fee_max;
+        rent_data.rent_change_cooldown_seconds = rent_change_cooldown_seconds;
+        rent_data.non_refundable_deposit = non_refundable_deposit;
+        rent_data.termination_platform_fee = termination_platform_fee;
+        if prepay_first_period {
+            rent_data.escrowed_rent = first_period_rent;
+            rent_data.remaining_payments = math::sub(duration, 1)?;
+        }
+        rent_data.reminder_lead_seconds = reminder_lead_seconds;
+        rent_data.final_settlement_grace_seconds = final_settlement_grace_seconds;
+        rent_data.termination_penalty_bps = termination_penalty_bps;
+        rent_data.termination_penalty_max = termination_penalty_max;
+        rent_data.bump = bump_seed;
+        rent_data.max_prepay_periods = if max_prepay_periods == 0 {
+            duration
+        } else {
+            max_prepay_periods
+        };
+        rent_data.deposit_refund_destination = if deposit_refund_destination == Pubkey::default() {
+            payer_pubkey
+        } else {
+            deposit_refund_destination
+        };
+        // Lamport denominated agreements leave the display hints unset
+        if currency_decimals == 0 && currency_symbol == [0; 8] {
+            rent_data.currency_decimals = SOL_DECIMALS;
+            rent_data.currency_symbol = SOL_SYMBOL;
+        } else {
+            rent_data.currency_decimals = currency_decimals;
+            rent_data.currency_symbol = currency_symbol;
+        }
+        rent_data.save(&mut rent_agreement_account.data.borrow_mut())?;
+
+        // Hold the deposit in the agreement account until the agreement is settled
+        if escrow_amount > 0 {
+            let instruction = system_instruction::transfer(
+                payer_account.key,
+                rent_agreement_account.key,
+                escrow_amount,
+            );
+
+            invoke(
+                &instruction,
+                &[
+                    system_program_account.clone(),
+                    rent_agreement_account.clone(),
+                    payer_account.clone(),
+                ],
+            )?;
+        }
+
+        // The escrowed lamports must sit on top of the rent exempt minimum, never inside it
+        let required_balance =
+            Self::required_agreement_balance(rent_agreement_account, solana_rent, &rent_data)?;
+        if rent_agreement_account.lamports() < required_balance {
+            msg!(
+                "[RentShare] Escrow insolvent after collecting the deposit. Balance: {} Required: {}",
+                rent_agreement_account.lamports(),
+                required_balance
+            );
+            return Err(RentShareError::EscrowInsolvent.into());
+        }
 
         msg!(
             "[RentShare] Initialized rent agreement account: {:?}",
@@ -120,23 +601,102 @@ impl Processor {
         Ok(())
     }
 
-    fn pay_rent(accounts: &[AccountInfo], program_id: &Pubkey, rent_amount: u64) -> ProgramResult {
+    fn write_lease_template(
+        accounts: &[AccountInfo],
+        mut template: LeaseTemplate,
+    ) -> ProgramResult {
         let accounts_iter = &mut accounts.iter();
 
-        let rent_agreement_account = next_account_info(accounts_iter)?;
-        if rent_agreement_account.owner != program_id {
-            msg!("[RentShare] Rent agreement account is not owned by this program");
-            return Err(ProgramError::IncorrectProgramId);
+        let template_account = next_account_info(accounts_iter)?;
+        let authority_account = next_account_info(accounts_iter)?;
+        if template_account.data_len() != LeaseTemplate::LEN {
+            msg!(
+                "[RentShare] Lease template account data size incorrect: {}",
+                template_account.data_len()
+            );
+            return Err(ProgramError::InvalidAccountData);
         }
 
-        let payee_account: &AccountInfo = next_account_info(accounts_iter)?;
-        let payer_account = next_account_info(accounts_iter)?;
-        let system_program_account = next_account_info(accounts_iter)?;
+        // A blank account has no authority yet, so its first writer claims it
+        let existing = LeaseTemplate::load(&template_account.data.borrow())?;
+        if existing.is_initialized && existing.authority != *authority_account.key {
+            msg!("[RentShare] Lease template can only be updated by its authority");
+            return Err(ProgramError::InvalidAccountData);
+        }
+        if !existing.is_initialized && !RentShareAccount::is_blank(&template_account.data.borrow())
+        {
+            msg!("[RentShare] Lease template account data is not empty");
+            return Err(ProgramError::AccountAlreadyInitialized);
+        }
+
+        template.authority = *authority_account.key;
+        template.save(&mut template_account.data.borrow_mut())?;
+
+        msg!("[RentShare] Lease template written: {:?}", template);
+
+        Ok(())
+    }
 
-        if !payer_account.is_signer {
-            return Err(ProgramError::MissingRequiredSignature);
+    #[allow(clippy::too_many_arguments)]
+    fn initialize_from_template(
+        accounts: &[AccountInfo],
+        program_id: &Pubkey,
+        payee_pubkey: Pubkey,
+        payer_pubkey: Pubkey,
+        deposit: u64,
+        rent_amount: u64,
+        agreement_index: u32,
+        overrides: TemplateOverrides,
+    ) -> ProgramResult {
+        // The template follows the accounts `initialize_rent_contract` reads
+        let accounts_iter = &mut accounts.iter().skip(4);
+        let template_account = next_account_info(accounts_iter)?;
+        let template = LeaseTemplate::load(&template_account.data.borrow())?;
+        if !template.is_initialized {
+            msg!("[RentShare] Lease template account not initialized");
+            return Err(ProgramError::UninitializedAccount);
         }
 
+        // Terms are validated by `initialize_rent_contract` exactly as if they were sent in full
+        Self::initialize_rent_contract(
+            accounts,
+            program_id,
+            AgreementTerms {
+                payee_pubkey,
+                payer_pubkey,
+                deposit,
+                rent_amount,
+                agreement_index,
+                duration: overrides.duration.unwrap_or(template.duration),
+                duration_unit: overrides.duration_unit.unwrap_or(template.duration_unit),
+                late_fee_bps: overrides.late_fee_bps.unwrap_or(template.late_fee_bps),
+                notice_period_seconds: overrides
+                    .notice_period_seconds
+                    .unwrap_or(template.notice_period_seconds),
+                termination_penalty_bps: overrides
+                    .termination_penalty_bps
+                    .unwrap_or(template.termination_penalty_bps),
+                termination_penalty_max: overrides
+                    .termination_penalty_max
+                    .unwrap_or(template.termination_penalty_max),
+                ..AgreementTerms::default()
+            },
+        )
+    }
+
+    fn pay_rent(
+        accounts: &[AccountInfo],
+        program_id: &Pubkey,
+        rent_amount: u64,
+        periods: u64,
+        enforce_prepay_limit: bool,
+    ) -> ProgramResult {
+        let pay_rent_accounts = PayRentAccounts::parse(accounts, program_id)?;
+        let rent_agreement_account = pay_rent_accounts.agreement.info();
+        let payee_account = pay_rent_accounts.payee.info();
+        let payer_account = pay_rent_accounts.payer.info();
+        let system_program_account = pay_rent_accounts.system_program.info();
+
         if payer_account.lamports() < rent_amount {
             return Err(ProgramError::InsufficientFunds);
         }
@@ -147,9 +707,8 @@ impl Processor {
         }
 
         // Initialize the Rent Agreement Account with the initial data
-        // Note: the structure of the data state must match the `space` the client used to create the account
-        let rent_agreement_data =
-            RentShareAccount::try_from_slice(&rent_agreement_account.data.borrow());
+        // Note: the `space` the client used to create the account must be at least `AGREEMENT_ACCOUNT_SPACE`
+        let rent_agreement_data = RentShareAccount::load(&rent_agreement_account.data.borrow());
 
         if rent_agreement_data.is_err() {
             msg!(
@@ -159,15 +718,29 @@ impl Processor {
             return Err(ProgramError::InvalidAccountData);
         }
 
+        // Transactions writing the agreement account are executed one at a time, so a termination
+        // landing in the same block as this payment has either already been saved or will see the
+        // payment. Reading the status from the account data here means a terminated agreement
+        // always rejects the payment with `RentAgreementTerminated`.
         let mut rent_data = rent_agreement_data.unwrap();
-        if !rent_data.is_initialized() {
-            msg!("[RentShare] Rent agreement account not initialized");
-            return Err(ProgramError::UninitializedAccount);
+        rent_data.require_active()?;
+
+        // An active agreement with nothing left to pay is inconsistent state; treat it as complete
+        // rather than letting the payment underflow. A rejected transaction cannot persist the
+        // status, so this is checked on every payment.
+        if rent_data.remaining_payments == 0 {
+            msg!("[RentShare] Active agreement has no remaining payments. Treating it as complete");
+            return Err(RentShareError::RentAlreadyPaidInFull.into());
         }
 
-        // Make sure we pay the same account used during the agreement initialization
-        if rent_data.payee_pubkey != *payee_account.key {
-            msg!("[RentShare] Payee must match payee key used during agreement initialization");
+        // Make sure we pay the account used during the agreement initialization, or the payee the
+        // rent is currently assigned to
+        let active_payee = rent_data.active_payee();
+        if active_payee != *payee_account.key {
+            msg!(
+                "[RentShare] Payee must match the payee currently receiving rent: {}",
+                active_payee
+            );
             return Err(ProgramError::InvalidAccountData);
         }
 
@@ -177,64 +750,283 @@ impl Processor {
             payer_account.lamports()
         );
 
-        if rent_data.is_complete() {
-            msg!("[RentShare] Rent already paid in full");
-            return Err(RentShareError::RentAlreadyPaidInFull.into());
+        if periods == 0 || periods > rent_data.remaining_payments {
+            msg!(
+                "[RentShare] Invalid number of periods: {}. Remaining payments: {}",
+                periods,
+                rent_data.remaining_payments
+            );
+            return Err(ProgramError::InvalidArgument);
+        }
+
+        if enforce_prepay_limit && periods > rent_data.max_prepay_periods {
+            msg!(
+                "[RentShare] Payment for {} periods exceeds the limit of {} periods",
+                periods,
+                rent_data.max_prepay_periods
+            );
+            return Err(RentShareError::PrepayLimitExceeded.into());
+        }
+
+        if rent_data.spans_assignment_end(periods) {
+            msg!(
+                "[RentShare] Payment for {} periods spans the end of the rent assignment at period {}. Pay each side separately",
+                periods,
+                rent_data.assigned_until_period
+            );
+            return Err(ProgramError::InvalidArgument);
+        }
+
+        let now = Self::current_timestamp(accounts)?;
+
+        // A payer in default loses the agreement and the deposit rather than catching up
+        if rent_data.should_auto_terminate(now)? {
+            msg!(
+                "[RentShare] Payer missed {} payments. Agreement terminated on default",
+                rent_data.overdue_periods(now)?
+            );
+            msg!(
+                "[RentShare] Deposit of {} lamports forfeited to payee",
+                rent_data.deposit
+            );
+            // The deposit secures the lease for its owner, not the payee rent is assigned to
+            let recorded_payee_account = Self::recorded_payee_account(&rent_data, accounts)?;
+            Self::transfer_from_agreement(
+                rent_agreement_account,
+                recorded_payee_account,
+                rent_data.deposit,
+            )?;
+
+            if rent_data.accrued_interest > 0 {
+                msg!(
+                    "[RentShare] Deposit interest of {} lamports forfeited with the deposit",
+                    rent_data.accrued_interest
+                );
+                rent_data.accrued_interest = 0;
+            }
+
+            rent_data.deposit = 0;
+            rent_data.remaining_payments = 0;
+            rent_data.set_status(AgreementStatus::Terminated, now)?;
+            rent_data.save(&mut rent_agreement_account.data.borrow_mut())?;
+
+            return Ok(());
+        }
+
+        if rent_data.is_before_first_payment(now)? {
+            msg!(
+                "[RentShare] First rent payment is not due until: {}",
+                rent_data.next_due_timestamp(now)?
+            );
+            return Err(RentShareError::PaymentNotDue.into());
         }
 
-        if rent_data.is_terminated() {
-            msg!("[RentShare] Rent agreement already terminated");
-            return Err(RentShareError::RentAgreementTerminated.into());
+        // Prepaying several periods is a single transfer of the summed rent plus late fees
+        let late_periods = rent_data.late_charged_periods(periods, now)?;
+        let late_fees = rent_data.late_fees_due(periods, now)?;
+        let late_fee_credit_applied = rent_data.late_fee_credit_applied(periods, now)?;
+        let overdue_paid = rent_data.overdue_periods(now)?.min(periods);
+        // A depleted deposit is restored before the remainder is applied to rent
+        let deposit_shortfall = rent_data.deposit_shortfall();
+        let expected_amount = match periods {
+            1 => rent_data.expected_payment_for_current_period(now)?,
+            _ => rent_data.amount_due(periods, now)?,
+        };
+
+        if late_fees > 0 {
+            msg!(
+                "[RentShare] Late fee of {} lamports applied to {} overdue periods",
+                late_fees,
+                late_periods
+            );
         }
 
-        if rent_data.rent_amount != rent_amount {
+        // Agreements may route a surplus into the deposit instead of rejecting the payment
+        let overpayment = if rent_amount > expected_amount && rent_data.overpay_to_deposit {
+            math::sub(rent_amount, expected_amount)?
+        } else {
+            0
+        };
+
+        if expected_amount != math::sub(rent_amount, overpayment)? {
             msg!(
                 "[RentShare] Rent amount does not match agreement amount: {} vs {}",
-                rent_data.rent_amount,
+                expected_amount,
                 rent_amount
             );
             return Err(RentShareError::RentPaymentAmountMismatch.into());
         }
 
-        let instruction =
-            system_instruction::transfer(payer_account.key, payee_account.key, rent_amount);
+        if overpayment > 0 {
+            msg!(
+                "[RentShare] Overpayment of {} lamports added to the deposit",
+                overpayment
+            );
+        }
 
-        // Invoke the system program to transfer funds
-        invoke(
-            &instruction,
-            &[
-                system_program_account.clone(),
-                payee_account.clone(),
-                payer_account.clone(),
-            ],
-        )?;
+        let deposit_transfer = math::add(deposit_shortfall, overpayment)?;
+        if deposit_transfer > 0 {
+            let instruction = system_instruction::transfer(
+                payer_account.key,
+                rent_agreement_account.key,
+                deposit_transfer,
+            );
+
+            invoke(
+                &instruction,
+                &[
+                    system_program_account.clone(),
+                    rent_agreement_account.clone(),
+                    payer_account.clone(),
+                ],
+            )?;
+
+            rent_data.deposit = math::add(rent_data.deposit, deposit_transfer)?;
+            msg!(
+                "[RentShare] Deposit increased by {} lamports to {}",
+                deposit_transfer,
+                rent_data.deposit
+            );
+        }
+
+        // Free periods are recorded as paid without a transfer
+        let rent_transfer = math::sub(rent_amount, deposit_transfer)?;
+        if rent_transfer > 0 {
+            let instruction =
+                system_instruction::transfer(payer_account.key, payee_account.key, rent_transfer);
+
+            // Invoke the system program to transfer funds
+            invoke(
+                &instruction,
+                &[
+                    system_program_account.clone(),
+                    payee_account.clone(),
+                    payer_account.clone(),
+                ],
+            )?;
+        }
 
         msg!(
             "[RentShare] Transfer completed. New payer balance: {}",
             payer_account.lamports()
         );
 
-        // Decrement the number of payment
-        rent_data.remaining_payments -= 1;
+        rent_data.total_rent_paid = math::add(
+            rent_data.total_rent_paid,
+            math::sub(rent_transfer, late_fees)?,
+        )?;
+        rent_data.total_late_fees_paid = math::add(rent_data.total_late_fees_paid, late_fees)?;
+        rent_data.late_fee_credit = math::sub(rent_data.late_fee_credit, late_fee_credit_applied)?;
+
+        // Overdue periods are paid first, so only the periods after them extend a fresh streak
+        rent_data.on_time_streak = if overdue_paid > 0 {
+            periods - overdue_paid
+        } else {
+            math::add(rent_data.on_time_streak, periods)?
+        };
+
+        // Decrement the number of payments
+        rent_data.remaining_payments = math::sub(rent_data.remaining_payments, periods)?;
         if rent_data.remaining_payments == 0 {
-            rent_data.status = AgreementStatus::Completed as u8;
+            let status = rent_data.final_payment_status();
+            if status == AgreementStatus::AwaitingSettlement {
+                rent_data.settlement_started_at = now;
+            }
+            rent_data.set_status(status, now)?;
+        } else {
+            msg!(
+                "[RentShare] Next rent payment due at: {}",
+                rent_data.next_due_timestamp(now)?
+            );
         }
-        rent_data.serialize(&mut &mut rent_agreement_account.data.borrow_mut()[..])?;
+        rent_data.save(&mut rent_agreement_account.data.borrow_mut())?;
 
-        Ok(())
+        // Saved first so the observer reads the agreement as of this payment
+        Self::notify_observer(
+            &rent_data,
+            accounts,
+            PaymentNotification {
+                rent_paid: rent_transfer,
+                late_fees,
+                periods,
+                remaining_payments: rent_data.remaining_payments,
+                paid_at: now,
+            },
+        )
     }
 
-    fn terminate_early(accounts: &[AccountInfo], program_id: &Pubkey) -> ProgramResult {
-        let accounts_iter = &mut accounts.iter();
+    fn prepay_whole_lease(accounts: &[AccountInfo], program_id: &Pubkey) -> ProgramResult {
+        let pay_accounts = PayRentAccounts::parse(accounts, program_id)?;
+        let rent_agreement_account = pay_accounts.agreement.info();
+        let payer_account = pay_accounts.payer.info();
 
-        let rent_agreement_account = next_account_info(accounts_iter)?;
-        if rent_agreement_account.owner != program_id {
-            msg!("[RentShare] Rent agreement account is not owned by this program");
-            return Err(ProgramError::IncorrectProgramId);
+        let rent_data =
+            RentShareAccount::load(&rent_agreement_account.data.borrow()).map_err(|_| {
+                msg!(
+                    "[RentShare] Rent agreement account data size incorrect: {}",
+                    rent_agreement_account.data_len()
+                );
+                ProgramError::InvalidAccountData
+            })?;
+        rent_data.require_active()?;
+
+        let now = Self::current_timestamp(accounts)?;
+        let periods = rent_data.remaining_payments;
+        let amount = rent_data.amount_due(periods, now)?;
+
+        if payer_account.lamports() < amount {
+            msg!(
+                "[RentShare] Payer balance {} cannot cover the remaining lease of {} lamports",
+                payer_account.lamports(),
+                amount
+            );
+            return Err(ProgramError::InsufficientFunds);
+        }
+
+        msg!(
+            "[RentShare] Prepaying {} remaining periods for {} lamports",
+            periods,
+            amount
+        );
+
+        // The prepay limit caps routine payments, not paying off the whole lease. Paying every
+        // remaining period completes the agreement, or terminates it when the payer is in default.
+        // The deposit is not refunded here: the payee releases it with `RefundDeposit` or
+        // `ReturnDepositItemized` once any deductions are settled.
+        if !rent_data.spans_assignment_end(periods) || rent_data.should_auto_terminate(now)? {
+            return Self::pay_rent(accounts, program_id, amount, periods, false);
         }
 
-        let rent_agreement_data =
-            RentShareAccount::try_from_slice(&rent_agreement_account.data.borrow());
+        // Rent assigned for part of the remaining lease is paid to the assigned payee, and the
+        // periods after the assignment ends to the recorded payee passed after the system program
+        let assigned_periods = rent_data.assigned_until_period - rent_data.payments_made();
+        let assigned_amount = rent_data.amount_due(assigned_periods, now)?;
+        Self::pay_rent(
+            accounts,
+            program_id,
+            assigned_amount,
+            assigned_periods,
+            false,
+        )?;
+
+        let rent_data = RentShareAccount::load(&rent_agreement_account.data.borrow())?;
+        let recorded_payee_account = Self::recorded_payee_account(&rent_data, accounts)?;
+        let mut recorded_payee_accounts = accounts.to_vec();
+        recorded_payee_accounts[1] = recorded_payee_account.clone();
+
+        let periods = rent_data.remaining_payments;
+        let amount = rent_data.amount_due(periods, now)?;
+        Self::pay_rent(&recorded_payee_accounts, program_id, amount, periods, false)
+    }
+
+    fn pay_late_fees(accounts: &[AccountInfo], program_id: &Pubkey, amount: u64) -> ProgramResult {
+        let pay_accounts = PayRentAccounts::parse(accounts, program_id)?;
+        let rent_agreement_account = pay_accounts.agreement.info();
+        let payee_account = pay_accounts.payee.info();
+        let payer_account = pay_accounts.payer.info();
+        let system_program_account = pay_accounts.system_program.info();
+
+        let rent_agreement_data = RentShareAccount::load(&rent_agreement_account.data.borrow());
 
         if rent_agreement_data.is_err() {
             msg!(
@@ -245,25 +1037,5599 @@ impl Processor {
         }
 
         let mut rent_data = rent_agreement_data.unwrap();
-        if !rent_data.is_initialized() {
+        rent_data.require_active()?;
+
+        let active_payee = rent_data.active_payee();
+        if active_payee != *payee_account.key {
+            msg!(
+                "[RentShare] Payee must match the payee currently receiving rent: {}",
+                active_payee
+            );
+            return Err(ProgramError::InvalidAccountData);
+        }
+
+        let now = Self::current_timestamp(accounts)?;
+        let accrued = rent_data.accrued_late_fees(now)?;
+        if amount == 0 || amount > accrued {
+            msg!(
+                "[RentShare] Late fee payment of {} lamports must be between 1 and the {} lamports accrued",
+                amount,
+                accrued
+            );
+            return Err(ProgramError::InvalidArgument);
+        }
+
+        let instruction =
+            system_instruction::transfer(payer_account.key, payee_account.key, amount);
+
+        invoke(
+            &instruction,
+            &[
+                system_program_account.clone(),
+                payee_account.clone(),
+                payer_account.clone(),
+            ],
+        )?;
+
+        rent_data.late_fee_credit = math::add(rent_data.late_fee_credit, amount)?;
+        rent_data.total_late_fees_paid = math::add(rent_data.total_late_fees_paid, amount)?;
+        rent_data.save(&mut rent_agreement_account.data.borrow_mut())?;
+
+        msg!(
+            "[RentShare] Paid {} lamports of late fees. {} lamports remain accrued",
+            amount,
+            math::sub(accrued, amount)?
+        );
+        Ok(())
+    }
+
+    fn terminate_early(accounts: &[AccountInfo], program_id: &Pubkey) -> ProgramResult {
+        let terminate_accounts = TerminateAccounts::parse(accounts, program_id)?;
+        let rent_agreement_account = terminate_accounts.agreement.info();
+        let payer_account = terminate_accounts.payer.info();
+        let payee_account = terminate_accounts.payee.info();
+
+        let rent_agreement_data = RentShareAccount::load(&rent_agreement_account.data.borrow());
+
+        if rent_agreement_data.is_err() {
+            msg!(
+                "[RentShare] Rent agreement account data size incorrect: {}",
+                rent_agreement_account.try_data_len()?
+            );
+            return Err(ProgramError::InvalidAccountData);
+        }
+
+        let mut rent_data = rent_agreement_data.unwrap();
+        rent_data.require_active()?;
+
+        // Only the parties used during the agreement initialization can settle the deposit
+        if rent_data.payer_pubkey != *payer_account.key {
+            msg!("[RentShare] Payer must match payer key used during agreement initialization");
+            return Err(ProgramError::InvalidAccountData);
+        }
+
+        if rent_data.payee_pubkey != *payee_account.key {
+            msg!("[RentShare] Payee must match payee key used during agreement initialization");
+            return Err(ProgramError::InvalidAccountData);
+        }
+
+        let now = Self::current_timestamp(accounts)?;
+        if !rent_data.notice_period_elapsed(now)? {
+            msg!(
+                "[RentShare] Notice period of {} seconds has not elapsed. Notice given at: {}",
+                rent_data.notice_period_seconds,
+                rent_data.notice_given_at
+            );
+            return Err(RentShareError::NoticePeriodNotElapsed.into());
+        }
+
+        Self::charge_termination_platform_fee(&mut rent_data, accounts)?;
+
+        // Rent already paid for time after termination is settled from a forfeited deposit
+        let prorated_refund = rent_data.prorated_refund(now)?;
+        // The penalty is withheld from whatever the agreement would otherwise return to the payer
+        let penalty = rent_data.termination_penalty()?;
+
+        if rent_data.deposit_forfeit_on_early_termination {
+            let refund_owed = prorated_refund.min(rent_data.refundable_deposit());
+            let penalty_paid = penalty.min(refund_owed);
+            let payer_refund = math::sub(refund_owed, penalty_paid)?;
+            rent_data.termination_penalty_paid = penalty_paid;
+            let forfeited = math::sub(rent_data.deposit, payer_refund)?;
+
+            msg!(
+                "[RentShare] Deposit of {} lamports forfeited to payee",
+                forfeited
+            );
+            Self::transfer_from_agreement(rent_agreement_account, payee_account, forfeited)?;
+
+            if payer_refund > 0 {
+                msg!(
+                    "[RentShare] Prorated rent of {} lamports refunded to payer",
+                    payer_refund
+                );
+                Self::transfer_from_agreement(rent_agreement_account, payer_account, payer_refund)?;
+            }
+
+            // Interest accrues on the deposit, so it is forfeited along with it
+            if rent_data.accrued_interest > 0 {
+                msg!(
+                    "[RentShare] Deposit interest of {} lamports forfeited with the deposit",
+                    rent_data.accrued_interest
+                );
+                rent_data.accrued_interest = 0;
+            }
+        } else {
+            let refund_account = Self::deposit_refund_account(&rent_data, accounts)?;
+            if prorated_refund > 0 {
+                msg!(
+                    "[RentShare] Prorated rent of {} lamports owed to payer by payee",
+                    prorated_refund
+                );
+            }
+
+            let refundable = rent_data.refundable_deposit();
+            let non_refundable = math::sub(rent_data.deposit, refundable)?;
+            if non_refundable > 0 {
+                msg!(
+                    "[RentShare] Non-refundable deposit of {} lamports paid to payee",
+                    non_refundable
+                );
+            }
+
+            let penalty_paid = penalty.min(refundable);
+            if penalty_paid > 0 {
+                msg!(
+                    "[RentShare] Termination penalty of {} lamports paid to payee from the deposit",
+                    penalty_paid
+                );
+            }
+            rent_data.termination_penalty_paid = penalty_paid;
+
+            // Interest on the deposit is owed by the payee, so it is withheld from the part of the
+            // deposit the payee would otherwise receive
+            let payee_share = math::add(non_refundable, penalty_paid)?;
+            let interest_paid = rent_data.accrued_interest.min(payee_share);
+            let payee_transfer = math::sub(payee_share, interest_paid)?;
+            if payee_transfer > 0 {
+                Self::transfer_from_agreement(
+                    rent_agreement_account,
+                    payee_account,
+                    payee_transfer,
+                )?;
+            }
+
+            let payer_refund = math::sub(refundable, penalty_paid)?;
+            msg!(
+                "[RentShare] Deposit of {} lamports refunded to {}",
+                payer_refund,
+                refund_account.key
+            );
+            Self::transfer_from_agreement(
+                rent_agreement_account,
+                refund_account,
+                math::add(payer_refund, interest_paid)?,
+            )?;
+            rent_data.deposit_refunded = true;
+
+            if interest_paid > 0 {
+                msg!(
+                    "[RentShare] Deposit interest of {} lamports paid to {} from the payee's share of the deposit",
+                    interest_paid,
+                    refund_account.key
+                );
+                rent_data.deposit_interest_paid =
+                    math::add(rent_data.deposit_interest_paid, interest_paid)?;
+                rent_data.accrued_interest = math::sub(rent_data.accrued_interest, interest_paid)?;
+            }
+
+            if rent_data.accrued_interest > 0 {
+                msg!(
+                    "[RentShare] Deposit interest of {} lamports owed to payer by payee",
+                    rent_data.accrued_interest
+                );
+            }
+        }
+
+        if rent_data.termination_penalty_paid < penalty {
+            msg!(
+                "[RentShare] Termination penalty of {} lamports owed to payee by payer",
+                math::sub(penalty, rent_data.termination_penalty_paid)?
+            );
+        }
+
+        rent_data.deposit = 0;
+        rent_data.remaining_payments = 0;
+        rent_data.set_status(AgreementStatus::Terminated, now)?;
+        rent_data.save(&mut rent_agreement_account.data.borrow_mut())?;
+
+        Ok(())
+    }
+
+    fn view_agreement(accounts: &[AccountInfo]) -> ProgramResult {
+        let accounts_iter = &mut accounts.iter();
+
+        let rent_agreement_account = next_account_info(accounts_iter)?;
+        let rent_agreement_data = RentShareAccount::load(&rent_agreement_account.data.borrow());
+
+        if rent_agreement_data.is_err() {
+            msg!(
+                "[RentShare] Rent agreement account data size incorrect: {}",
+                rent_agreement_account.try_data_len()?
+            );
+            return Err(ProgramError::InvalidAccountData);
+        }
+
+        let rent_data = rent_agreement_data.unwrap();
+        if !rent_data.is_initialized() {
             msg!("[RentShare] Rent agreement account not initialized");
             return Err(ProgramError::UninitializedAccount);
         }
 
-        if rent_data.is_complete() {
-            msg!("[RentShare] Rent already paid in full");
-            return Err(RentShareError::RentAlreadyPaidInFull.into());
+        Self::charge_read_fee(&rent_data, accounts)?;
+
+        msg!("[RentShare] Rent agreement account: {:?}", rent_data);
+        msg!(
+            "[RentShare] Payments made: {} of {}",
+            rent_data.payments_made(),
+            rent_data.duration
+        );
+        msg!(
+            "[RentShare] Payee net received: {} lamports",
+            rent_data.landlord_net_received()?
+        );
+
+        let now = Self::current_timestamp(accounts)?;
+        msg!("[RentShare] Next action: {:?}", rent_data.next_action(now)?);
+
+        if rent_data.is_active() {
+            msg!(
+                "[RentShare] Seconds until due: {}",
+                rent_data.seconds_until_due(now)?
+            );
         }
 
-        if rent_data.is_terminated() {
-            msg!("[RentShare] Rent agreement already terminated");
-            return Err(RentShareError::RentAgreementTerminated.into());
+        if rent_data.is_active() && rent_data.is_in_default(now)? {
+            msg!(
+                "[RentShare] Payer in default with {} missed payments. Auto terminate on next payment: {}",
+                rent_data.overdue_periods(now)?,
+                rent_data.auto_terminate_on_default
+            );
         }
 
-        rent_data.remaining_payments = 0;
-        rent_data.status = AgreementStatus::Terminated as u8;
-        rent_data.serialize(&mut &mut rent_agreement_account.data.borrow_mut()[..])?;
+        Ok(())
+    }
+
+    fn increase_deposit(accounts: &[AccountInfo], amount: u64) -> ProgramResult {
+        let accounts_iter = &mut accounts.iter();
+
+        let rent_agreement_account = next_account_info(accounts_iter)?;
+        let payer_account = next_account_info(accounts_iter)?;
+        let system_program_account = next_account_info(accounts_iter)?;
+
+        if payer_account.lamports() < amount {
+            return Err(ProgramError::InsufficientFunds);
+        }
+
+        let rent_agreement_data = RentShareAccount::load(&rent_agreement_account.data.borrow());
+
+        if rent_agreement_data.is_err() {
+            msg!(
+                "[RentShare] Rent agreement account data size incorrect: {}",
+                rent_agreement_account.try_data_len()?
+            );
+            return Err(ProgramError::InvalidAccountData);
+        }
+
+        let mut rent_data = rent_agreement_data.unwrap();
+        // A staged deposit is collected while the agreement waits for the payee to accept it
+        rent_data.require_status(&[AgreementStatus::Active, AgreementStatus::PendingAcceptance])?;
+
+        if rent_data.payer_pubkey != *payer_account.key {
+            msg!("[RentShare] Payer must match payer key used during agreement initialization");
+            return Err(ProgramError::InvalidAccountData);
+        }
+
+        rent_data.deposit = math::add(rent_data.deposit, amount)?;
+
+        let instruction =
+            system_instruction::transfer(payer_account.key, rent_agreement_account.key, amount);
+
+        invoke(
+            &instruction,
+            &[
+                system_program_account.clone(),
+                rent_agreement_account.clone(),
+                payer_account.clone(),
+            ],
+        )?;
+
+        msg!(
+            "[RentShare] Deposit increased by {} lamports to {}",
+            amount,
+            rent_data.deposit
+        );
+
+        rent_data.save(&mut rent_agreement_account.data.borrow_mut())?;
+
+        Ok(())
+    }
+
+    fn accept_agreement(accounts: &[AccountInfo]) -> ProgramResult {
+        let accounts_iter = &mut accounts.iter();
+
+        let rent_agreement_account = next_account_info(accounts_iter)?;
+        let payee_account = next_account_info(accounts_iter)?;
+
+        let rent_agreement_data = RentShareAccount::load(&rent_agreement_account.data.borrow());
+
+        if rent_agreement_data.is_err() {
+            msg!(
+                "[RentShare] Rent agreement account data size incorrect: {}",
+                rent_agreement_account.try_data_len()?
+            );
+            return Err(ProgramError::InvalidAccountData);
+        }
+
+        let mut rent_data = rent_agreement_data.unwrap();
+        rent_data.require_status(&[AgreementStatus::PendingAcceptance])?;
+
+        if rent_data.payee_pubkey != *payee_account.key {
+            msg!("[RentShare] Payee must match payee key used during agreement initialization");
+            return Err(ProgramError::InvalidAccountData);
+        }
+
+        if !rent_data.is_deposit_fully_collected() {
+            msg!(
+                "[RentShare] Deposit of {} lamports is below the required deposit of {}",
+                rent_data.deposit,
+                rent_data.required_deposit
+            );
+            return Err(RentShareError::DepositNotFullyCollected.into());
+        }
+
+        // Rent prepaid for the first period is released to the payee once the terms are accepted
+        if rent_data.escrowed_rent > 0 {
+            if !payee_account.is_writable {
+                msg!("[RentShare] Payee account must be writable to receive the prepaid first period");
+                return Err(ProgramError::InvalidArgument);
+            }
+
+            Self::transfer_from_agreement(
+                rent_agreement_account,
+                payee_account,
+                rent_data.escrowed_rent,
+            )?;
+            msg!(
+                "[RentShare] Prepaid first period of {} lamports released to payee",
+                rent_data.escrowed_rent
+            );
+            rent_data.total_rent_paid =
+                math::add(rent_data.total_rent_paid, rent_data.escrowed_rent)?;
+            rent_data.escrowed_rent = 0;
+        }
+        if rent_data.prepay_first_period {
+            rent_data.on_time_streak = 1;
+        }
+
+        let now = Self::current_timestamp(accounts)?;
+        rent_data.accepted_at = now;
+        // A single period lease prepaid at signing has nothing left to pay once accepted
+        if rent_data.remaining_payments == 0 {
+            let status = rent_data.final_payment_status();
+            if status == AgreementStatus::AwaitingSettlement {
+                rent_data.settlement_started_at = now;
+            }
+            rent_data.set_status(status, now)?;
+        } else {
+            rent_data.set_status(AgreementStatus::Active, now)?;
+        }
+        rent_data.save(&mut rent_agreement_account.data.borrow_mut())?;
+
+        msg!(
+            "[RentShare] Rent agreement accepted by payee at: {}",
+            rent_data.accepted_at
+        );
+
+        Ok(())
+    }
+
+    fn set_frozen(accounts: &[AccountInfo], frozen: bool) -> ProgramResult {
+        let accounts_iter = &mut accounts.iter();
+
+        let rent_agreement_account = next_account_info(accounts_iter)?;
+        let admin_account = next_account_info(accounts_iter)?;
+
+        let rent_agreement_data = RentShareAccount::load(&rent_agreement_account.data.borrow());
+
+        if rent_agreement_data.is_err() {
+            msg!(
+                "[RentShare] Rent agreement account data size incorrect: {}",
+                rent_agreement_account.try_data_len()?
+            );
+            return Err(ProgramError::InvalidAccountData);
+        }
+
+        let mut rent_data = rent_agreement_data.unwrap();
+        if !rent_data.is_initialized() {
+            msg!("[RentShare] Rent agreement account not initialized");
+            return Err(ProgramError::UninitializedAccount);
+        }
+
+        // Agreements initialized without an admin can never be frozen
+        if rent_data.admin_pubkey == Pubkey::default()
+            || rent_data.admin_pubkey != *admin_account.key
+        {
+            msg!("[RentShare] Only the agreement admin can freeze or unfreeze it");
+            return Err(ProgramError::InvalidAccountData);
+        }
+
+        if rent_data.frozen == frozen {
+            msg!("[RentShare] Agreement frozen is already {}", frozen);
+            return Ok(());
+        }
+
+        rent_data.frozen = frozen;
+        rent_data.save(&mut rent_agreement_account.data.borrow_mut())?;
+
+        msg!(
+            "[RentShare] Agreement {} by admin {}",
+            if frozen { "frozen" } else { "unfrozen" },
+            admin_account.key
+        );
+        Ok(())
+    }
+
+    fn give_notice(accounts: &[AccountInfo]) -> ProgramResult {
+        let accounts_iter = &mut accounts.iter();
+
+        let rent_agreement_account = next_account_info(accounts_iter)?;
+        let payer_account = next_account_info(accounts_iter)?;
+
+        let rent_agreement_data = RentShareAccount::load(&rent_agreement_account.data.borrow());
+
+        if rent_agreement_data.is_err() {
+            msg!(
+                "[RentShare] Rent agreement account data size incorrect: {}",
+                rent_agreement_account.try_data_len()?
+            );
+            return Err(ProgramError::InvalidAccountData);
+        }
+
+        let mut rent_data = rent_agreement_data.unwrap();
+        rent_data.require_active()?;
+
+        if rent_data.payer_pubkey != *payer_account.key {
+            msg!("[RentShare] Payer must match payer key used during agreement initialization");
+            return Err(ProgramError::InvalidAccountData);
+        }
+
+        if rent_data.notice_given_at != 0 {
+            msg!(
+                "[RentShare] Notice already given at: {}",
+                rent_data.notice_given_at
+            );
+            return Err(ProgramError::InvalidAccountData);
+        }
+
+        rent_data.notice_given_at = Self::current_timestamp(accounts)?;
+        rent_data.save(&mut rent_agreement_account.data.borrow_mut())?;
+
+        msg!(
+            "[RentShare] Termination notice given at: {}",
+            rent_data.notice_given_at
+        );
 
         Ok(())
     }
+
+    fn refund_deposit(accounts: &[AccountInfo]) -> ProgramResult {
+        let accounts_iter = &mut accounts.iter();
+
+        let rent_agreement_account = next_account_info(accounts_iter)?;
+        let payee_account = next_account_info(accounts_iter)?;
+        let destination_account = next_account_info(accounts_iter)?;
+        let system_program_account = next_account_info(accounts_iter)?;
+
+        let rent_agreement_data = RentShareAccount::load(&rent_agreement_account.data.borrow());
+
+        if rent_agreement_data.is_err() {
+            msg!(
+                "[RentShare] Rent agreement account data size incorrect: {}",
+                rent_agreement_account.try_data_len()?
+            );
+            return Err(ProgramError::InvalidAccountData);
+        }
+
+        let mut rent_data = rent_agreement_data.unwrap();
+        match rent_data.agreement_status()? {
+            AgreementStatus::Completed
+            | AgreementStatus::Terminated
+            | AgreementStatus::AwaitingSettlement => {}
+            status => {
+                msg!(
+                    "[RentShare] Deposit can only be refunded once the agreement has ended. Status: {:?}",
+                    status
+                );
+                return Err(RentShareError::DepositNotRefundable.into());
+            }
+        }
+
+        if rent_data.payee_pubkey != *payee_account.key {
+            msg!("[RentShare] Payee must match payee key used during agreement initialization");
+            return Err(ProgramError::InvalidAccountData);
+        }
+
+        if rent_data.deposit_refund_destination != *destination_account.key {
+            msg!("[RentShare] Refund destination must match the destination used during agreement initialization");
+            return Err(ProgramError::InvalidAccountData);
+        }
+
+        // A program owned destination, including the agreement itself, could not spend the refund
+        if destination_account.key == rent_agreement_account.key
+            || *destination_account.owner != system_program::id()
+        {
+            msg!(
+                "[RentShare] Refund destination must be a system owned wallet. Owner: {}",
+                destination_account.owner
+            );
+            return Err(ProgramError::InvalidAccountData);
+        }
+
+        if rent_data.is_deposit_refunded() {
+            msg!("[RentShare] Deposit already refunded");
+            return Err(RentShareError::DepositNotRefundable.into());
+        }
+
+        if rent_data.deposit == 0 {
+            msg!("[RentShare] No deposit held to refund");
+            return Err(RentShareError::DepositNotRefundable.into());
+        }
+
+        let refundable = rent_data.refundable_deposit();
+        let non_refundable = math::sub(rent_data.deposit, refundable)?;
+        msg!(
+            "[RentShare] Deposit of {} lamports refunded to {}",
+            refundable,
+            destination_account.key
+        );
+        Self::transfer_from_agreement(rent_agreement_account, destination_account, refundable)?;
+
+        if non_refundable > 0 {
+            msg!(
+                "[RentShare] Non-refundable deposit of {} lamports paid to payee",
+                non_refundable
+            );
+            Self::transfer_from_agreement(rent_agreement_account, payee_account, non_refundable)?;
+        }
+
+        // Interest on the deposit is owed by the payee, who held the benefit of the deposit
+        if rent_data.accrued_interest > 0 {
+            let instruction = system_instruction::transfer(
+                payee_account.key,
+                destination_account.key,
+                rent_data.accrued_interest,
+            );
+
+            invoke(
+                &instruction,
+                &[
+                    system_program_account.clone(),
+                    destination_account.clone(),
+                    payee_account.clone(),
+                ],
+            )?;
+
+            msg!(
+                "[RentShare] Deposit interest of {} lamports paid to {}",
+                rent_data.accrued_interest,
+                destination_account.key
+            );
+            rent_data.deposit_interest_paid =
+                math::add(rent_data.deposit_interest_paid, rent_data.accrued_interest)?;
+            rent_data.accrued_interest = 0;
+        }
+
+        rent_data.deposit = 0;
+        rent_data.deposit_refunded = true;
+        // The refund is the settlement the agreement was waiting for
+        if rent_data.is_awaiting_settlement() {
+            rent_data.set_status(
+                AgreementStatus::Completed,
+                Self::current_timestamp(accounts)?,
+            )?;
+        }
+        rent_data.save(&mut rent_agreement_account.data.borrow_mut())?;
+
+        Ok(())
+    }
+
+    fn return_deposit_itemized(
+        accounts: &[AccountInfo],
+        return_amount: u64,
+        deduction_codes: [u8; 4],
+    ) -> ProgramResult {
+        let accounts_iter = &mut accounts.iter();
+
+        let rent_agreement_account = next_account_info(accounts_iter)?;
+        let payee_account = next_account_info(accounts_iter)?;
+        let destination_account = next_account_info(accounts_iter)?;
+
+        let rent_agreement_data = RentShareAccount::load(&rent_agreement_account.data.borrow());
+
+        if rent_agreement_data.is_err() {
+            msg!(
+                "[RentShare] Rent agreement account data size incorrect: {}",
+                rent_agreement_account.try_data_len()?
+            );
+            return Err(ProgramError::InvalidAccountData);
+        }
+
+        let mut rent_data = rent_agreement_data.unwrap();
+        match rent_data.agreement_status()? {
+            AgreementStatus::Completed
+            | AgreementStatus::Terminated
+            | AgreementStatus::AwaitingSettlement => {}
+            status => {
+                msg!(
+                    "[RentShare] Deposit can only be returned once the agreement has ended. Status: {:?}",
+                    status
+                );
+                return Err(RentShareError::DepositNotRefundable.into());
+            }
+        }
+
+        if rent_data.payee_pubkey != *payee_account.key {
+            msg!("[RentShare] Payee must match payee key used during agreement initialization");
+            return Err(ProgramError::InvalidAccountData);
+        }
+
+        if rent_data.deposit_refund_destination != *destination_account.key {
+            msg!("[RentShare] Refund destination must match the destination used during agreement initialization");
+            return Err(ProgramError::InvalidAccountData);
+        }
+
+        if destination_account.key == rent_agreement_account.key
+            || *destination_account.owner != system_program::id()
+        {
+            msg!(
+                "[RentShare] Refund destination must be a system owned wallet. Owner: {}",
+                destination_account.owner
+            );
+            return Err(ProgramError::InvalidAccountData);
+        }
+
+        if rent_data.is_deposit_refunded() || rent_data.deposit == 0 {
+            msg!("[RentShare] No deposit held to return");
+            return Err(RentShareError::DepositNotRefundable.into());
+        }
+
+        if rent_data.accrued_interest > 0 {
+            msg!(
+                "[RentShare] Deposit has {} lamports of accrued interest and must be refunded with RefundDeposit",
+                rent_data.accrued_interest
+            );
+            return Err(RentShareError::DepositNotRefundable.into());
+        }
+
+        if return_amount > rent_data.refundable_deposit() {
+            msg!(
+                "[RentShare] Return of {} lamports exceeds the refundable deposit of {}",
+                return_amount,
+                rent_data.refundable_deposit()
+            );
+            return Err(ProgramError::InvalidArgument);
+        }
+
+        let deducted = math::sub(rent_data.deposit, return_amount)?;
+        msg!(
+            "[RentShare] Deposit of {} lamports returned to {}, {} lamports deducted for codes {:?}",
+            return_amount,
+            destination_account.key,
+            deducted,
+            deduction_codes
+        );
+        if return_amount > 0 {
+            Self::transfer_from_agreement(
+                rent_agreement_account,
+                destination_account,
+                return_amount,
+            )?;
+        }
+        if deducted > 0 {
+            Self::transfer_from_agreement(rent_agreement_account, payee_account, deducted)?;
+        }
+
+        rent_data.deposit = 0;
+        rent_data.deposit_refunded = return_amount > 0;
+        if rent_data.is_awaiting_settlement() {
+            rent_data.set_status(
+                AgreementStatus::Completed,
+                Self::current_timestamp(accounts)?,
+            )?;
+        }
+        rent_data.save(&mut rent_agreement_account.data.borrow_mut())?;
+
+        Ok(())
+    }
+
+    fn top_up_rent_exemption(accounts: &[AccountInfo]) -> ProgramResult {
+        let accounts_iter = &mut accounts.iter();
+
+        let rent_agreement_account = next_account_info(accounts_iter)?;
+        let funder_account = next_account_info(accounts_iter)?;
+        let system_program_account = next_account_info(accounts_iter)?;
+        let solana_rent = &Self::load_rent_sysvar(next_account_info(accounts_iter)?)?;
+
+        let rent_agreement_data = RentShareAccount::load(&rent_agreement_account.data.borrow());
+
+        if rent_agreement_data.is_err() {
+            msg!(
+                "[RentShare] Rent agreement account data size incorrect: {}",
+                rent_agreement_account.try_data_len()?
+            );
+            return Err(ProgramError::InvalidAccountData);
+        }
+
+        let rent_data = rent_agreement_data.unwrap();
+        if !rent_data.is_initialized() {
+            msg!("[RentShare] Rent agreement account not initialized");
+            return Err(ProgramError::UninitializedAccount);
+        }
+
+        let required_balance =
+            Self::required_agreement_balance(rent_agreement_account, solana_rent, &rent_data)?;
+
+        let shortfall = required_balance.saturating_sub(rent_agreement_account.lamports());
+        if shortfall == 0 {
+            msg!(
+                "[RentShare] Rent agreement account already rent exempt. Balance: {}",
+                rent_agreement_account.lamports()
+            );
+            return Ok(());
+        }
+
+        if funder_account.lamports() < shortfall {
+            return Err(ProgramError::InsufficientFunds);
+        }
+
+        let instruction =
+            system_instruction::transfer(funder_account.key, rent_agreement_account.key, shortfall);
+
+        invoke(
+            &instruction,
+            &[
+                system_program_account.clone(),
+                rent_agreement_account.clone(),
+                funder_account.clone(),
+            ],
+        )?;
+
+        msg!(
+            "[RentShare] Rent agreement account topped up by {} lamports. Balance: {}",
+            shortfall,
+            rent_agreement_account.lamports()
+        );
+
+        Ok(())
+    }
+
+    fn verify_escrow(accounts: &[AccountInfo]) -> ProgramResult {
+        let accounts_iter = &mut accounts.iter();
+
+        let rent_agreement_account = next_account_info(accounts_iter)?;
+        let solana_rent = &Self::load_rent_sysvar(next_account_info(accounts_iter)?)?;
+
+        let rent_agreement_data = RentShareAccount::load(&rent_agreement_account.data.borrow());
+
+        if rent_agreement_data.is_err() {
+            msg!(
+                "[RentShare] Rent agreement account data size incorrect: {}",
+                rent_agreement_account.try_data_len()?
+            );
+            return Err(ProgramError::InvalidAccountData);
+        }
+
+        let rent_data = rent_agreement_data.unwrap();
+        if !rent_data.is_initialized() {
+            msg!("[RentShare] Rent agreement account not initialized");
+            return Err(ProgramError::UninitializedAccount);
+        }
+
+        let required_balance =
+            Self::required_agreement_balance(rent_agreement_account, solana_rent, &rent_data)?;
+
+        if rent_agreement_account.lamports() < required_balance {
+            msg!(
+                "[RentShare] Escrow insolvent. Balance: {} Required: {}",
+                rent_agreement_account.lamports(),
+                required_balance
+            );
+            return Err(RentShareError::EscrowInsolvent.into());
+        }
+
+        msg!(
+            "[RentShare] Escrow solvent. Balance: {} Required: {}",
+            rent_agreement_account.lamports(),
+            required_balance
+        );
+
+        Ok(())
+    }
+
+    fn accrue_deposit_interest(accounts: &[AccountInfo]) -> ProgramResult {
+        let accounts_iter = &mut accounts.iter();
+
+        let rent_agreement_account = next_account_info(accounts_iter)?;
+        let rent_agreement_data = RentShareAccount::load(&rent_agreement_account.data.borrow());
+
+        if rent_agreement_data.is_err() {
+            msg!(
+                "[RentShare] Rent agreement account data size incorrect: {}",
+                rent_agreement_account.try_data_len()?
+            );
+            return Err(ProgramError::InvalidAccountData);
+        }
+
+        // Interest keeps accruing for the full lease until the deposit is settled
+        let mut rent_data = rent_agreement_data.unwrap();
+        rent_data.require_status(&[
+            AgreementStatus::Active,
+            AgreementStatus::AwaitingSettlement,
+            AgreementStatus::Completed,
+        ])?;
+
+        if rent_data.is_deposit_refunded() {
+            msg!("[RentShare] Deposit already refunded");
+            return Err(RentShareError::DepositNotRefundable.into());
+        }
+
+        let now = Self::current_timestamp(accounts)?;
+        let interest = rent_data.accrue_deposit_interest(now)?;
+
+        msg!(
+            "[RentShare] Deposit interest of {} lamports accrued. Total: {}",
+            interest,
+            rent_data.accrued_interest
+        );
+
+        rent_data.save(&mut rent_agreement_account.data.borrow_mut())?;
+
+        Ok(())
+    }
+
+    fn close_agreement(accounts: &[AccountInfo]) -> ProgramResult {
+        let accounts_iter = &mut accounts.iter();
+
+        let rent_agreement_account = next_account_info(accounts_iter)?;
+        let payer_account = next_account_info(accounts_iter)?;
+
+        let rent_agreement_data = RentShareAccount::load(&rent_agreement_account.data.borrow());
+
+        if rent_agreement_data.is_err() {
+            msg!(
+                "[RentShare] Rent agreement account data size incorrect: {}",
+                rent_agreement_account.try_data_len()?
+            );
+            return Err(ProgramError::InvalidAccountData);
+        }
+
+        let rent_data = rent_agreement_data.unwrap();
+        match rent_data.agreement_status()? {
+            AgreementStatus::Completed | AgreementStatus::Terminated => {}
+            status => {
+                msg!(
+                    "[RentShare] Agreement can only be closed once it has ended. Status: {:?}",
+                    status
+                );
+                return Err(RentShareError::AgreementNotSettled.into());
+            }
+        }
+
+        if rent_data.payer_pubkey != *payer_account.key {
+            msg!("[RentShare] Payer must match payer key used during agreement initialization");
+            return Err(ProgramError::InvalidAccountData);
+        }
+
+        if rent_data.deposit > 0 {
+            msg!(
+                "[RentShare] Deposit of {} lamports must be refunded before closing",
+                rent_data.deposit
+            );
+            return Err(RentShareError::AgreementNotSettled.into());
+        }
+
+        // The payer funded the account at initialization, so the rent exempt balance returns to them
+        let balance = rent_agreement_account.lamports();
+        Self::transfer_from_agreement(rent_agreement_account, payer_account, balance)?;
+        rent_agreement_account.data.borrow_mut().fill(0);
+
+        msg!(
+            "[RentShare] Rent agreement account closed. {} lamports returned to payer",
+            balance
+        );
+
+        Ok(())
+    }
+
+    fn correct_payee(accounts: &[AccountInfo], new_payee: Pubkey) -> ProgramResult {
+        let accounts_iter = &mut accounts.iter();
+
+        let rent_agreement_account = next_account_info(accounts_iter)?;
+        let payer_account = next_account_info(accounts_iter)?;
+
+        let rent_agreement_data = RentShareAccount::load(&rent_agreement_account.data.borrow());
+
+        if rent_agreement_data.is_err() {
+            msg!(
+                "[RentShare] Rent agreement account data size incorrect: {}",
+                rent_agreement_account.try_data_len()?
+            );
+            return Err(ProgramError::InvalidAccountData);
+        }
+
+        // Once accepted the payee has agreed to the terms and can no longer be replaced
+        let mut rent_data = rent_agreement_data.unwrap();
+        rent_data.require_status(&[AgreementStatus::PendingAcceptance])?;
+
+        if rent_data.payer_pubkey != *payer_account.key {
+            msg!("[RentShare] Payer must match payer key used during agreement initialization");
+            return Err(ProgramError::InvalidAccountData);
+        }
+
+        if new_payee == rent_data.payer_pubkey || new_payee == *rent_agreement_account.key {
+            msg!("[RentShare] Payee must differ from the payer and the agreement account");
+            return Err(ProgramError::InvalidArgument);
+        }
+
+        msg!(
+            "[RentShare] Payee corrected from {} to {}",
+            rent_data.payee_pubkey,
+            new_payee
+        );
+
+        rent_data.payee_pubkey = new_payee;
+        rent_data.save(&mut rent_agreement_account.data.borrow_mut())?;
+
+        Ok(())
+    }
+
+    fn assign_rent(
+        accounts: &[AccountInfo],
+        assigned_payee: Pubkey,
+        until_period: u64,
+    ) -> ProgramResult {
+        let accounts_iter = &mut accounts.iter();
+
+        let rent_agreement_account = next_account_info(accounts_iter)?;
+        let payee_account = next_account_info(accounts_iter)?;
+
+        let rent_agreement_data = RentShareAccount::load(&rent_agreement_account.data.borrow());
+
+        if rent_agreement_data.is_err() {
+            msg!(
+                "[RentShare] Rent agreement account data size incorrect: {}",
+                rent_agreement_account.try_data_len()?
+            );
+            return Err(ProgramError::InvalidAccountData);
+        }
+
+        let mut rent_data = rent_agreement_data.unwrap();
+        rent_data.require_active()?;
+
+        if rent_data.payee_pubkey != *payee_account.key {
+            msg!("[RentShare] Payee must match payee key used during agreement initialization");
+            return Err(ProgramError::InvalidAccountData);
+        }
+
+        if until_period > rent_data.duration {
+            msg!(
+                "[RentShare] Rent assignment until period {} exceeds the duration of {} periods",
+                until_period,
+                rent_data.duration
+            );
+            return Err(RentShareError::InvalidAgreementTerms.into());
+        }
+
+        if until_period <= rent_data.payments_made() {
+            msg!(
+                "[RentShare] Rent assignment to {} ended",
+                rent_data.assigned_payee
+            );
+            rent_data.assigned_payee = Pubkey::default();
+            rent_data.assigned_until_period = 0;
+        } else {
+            if assigned_payee == Pubkey::default()
+                || assigned_payee == rent_data.payer_pubkey
+                || assigned_payee == *rent_agreement_account.key
+            {
+                msg!("[RentShare] Assigned payee must differ from the payer and the agreement account");
+                return Err(ProgramError::InvalidArgument);
+            }
+
+            msg!(
+                "[RentShare] Rent assigned to {} until period {}",
+                assigned_payee,
+                until_period
+            );
+            rent_data.assigned_payee = assigned_payee;
+            rent_data.assigned_until_period = until_period;
+        }
+
+        rent_data.save(&mut rent_agreement_account.data.borrow_mut())?;
+
+        Ok(())
+    }
+
+    fn update_rent_amount(accounts: &[AccountInfo], rent_amount: u64) -> ProgramResult {
+        let (rent_agreement_account, mut rent_data) =
+            Self::load_amendment(accounts, &[AgreementStatus::Active])?;
+
+        if rent_amount > MAX_RENT_AMOUNT {
+            msg!(
+                "[RentShare] Rent amount {} exceeds the maximum of {}",
+                rent_amount,
+                MAX_RENT_AMOUNT
+            );
+            return Err(RentShareError::InvalidAgreementTerms.into());
+        }
+
+        let now = Self::current_timestamp(accounts)?;
+        if rent_data.last_rent_change_at != 0 {
+            let next_change_at = rent_data.last_rent_change_at.saturating_add(
+                i64::try_from(rent_data.rent_change_cooldown_seconds).unwrap_or(i64::MAX),
+            );
+            if now < next_change_at {
+                msg!(
+                    "[RentShare] Rent was last changed at {}. The next change is allowed at {}",
+                    rent_data.last_rent_change_at,
+                    next_change_at
+                );
+                return Err(RentShareError::RentChangeTooSoon.into());
+            }
+        }
+
+        msg!(
+            "[RentShare] Rent amount updated from {} to {}",
+            rent_data.rent_amount,
+            rent_amount
+        );
+
+        rent_data.rent_amount = rent_amount;
+        rent_data.last_rent_change_at = now;
+        rent_data.record_amendment(Amendment::RentAmountUpdate)?;
+        rent_data.save(&mut rent_agreement_account.data.borrow_mut())?;
+
+        Ok(())
+    }
+
+    fn extend_agreement(accounts: &[AccountInfo], periods: u64) -> ProgramResult {
+        let (rent_agreement_account, mut rent_data) =
+            Self::load_amendment(accounts, &[AgreementStatus::Active])?;
+
+        let unit = Duration::try_from(rent_data.duration_unit)?;
+        let duration = math::add(rent_data.duration, periods)?;
+        if periods == 0 || duration > unit.max_duration() {
+            msg!(
+                "[RentShare] Extension by {} periods outside the allowed range. Maximum duration: {}",
+                periods,
+                unit.max_duration()
+            );
+            return Err(RentShareError::InvalidAgreementTerms.into());
+        }
+
+        rent_data.duration = duration;
+        rent_data.remaining_payments = math::add(rent_data.remaining_payments, periods)?;
+
+        msg!(
+            "[RentShare] Agreement extended by {} periods to {}",
+            periods,
+            rent_data.duration
+        );
+
+        rent_data.record_amendment(Amendment::Extension)?;
+        rent_data.save(&mut rent_agreement_account.data.borrow_mut())?;
+
+        Ok(())
+    }
+
+    fn transfer_tenant(accounts: &[AccountInfo], new_payer: Pubkey) -> ProgramResult {
+        let (rent_agreement_account, mut rent_data) =
+            Self::load_amendment(accounts, &[AgreementStatus::Active])?;
+
+        if new_payer == rent_data.payee_pubkey || new_payer == *rent_agreement_account.key {
+            msg!("[RentShare] New payer must differ from the payee and the agreement account");
+            return Err(ProgramError::InvalidArgument);
+        }
+
+        msg!(
+            "[RentShare] Tenant transferred from {} to {}",
+            rent_data.payer_pubkey,
+            new_payer
+        );
+
+        // The deposit follows the lease unless it was routed elsewhere at initialization
+        if rent_data.deposit_refund_destination == rent_data.payer_pubkey {
+            rent_data.deposit_refund_destination = new_payer;
+        }
+        rent_data.payer_pubkey = new_payer;
+        rent_data.record_amendment(Amendment::TenantTransfer)?;
+        rent_data.save(&mut rent_agreement_account.data.borrow_mut())?;
+
+        Ok(())
+    }
+
+    fn get_payoff_amount(accounts: &[AccountInfo]) -> ProgramResult {
+        let accounts_iter = &mut accounts.iter();
+
+        let rent_agreement_account = next_account_info(accounts_iter)?;
+        let rent_agreement_data = RentShareAccount::load(&rent_agreement_account.data.borrow());
+
+        if rent_agreement_data.is_err() {
+            msg!(
+                "[RentShare] Rent agreement account data size incorrect: {}",
+                rent_agreement_account.try_data_len()?
+            );
+            return Err(ProgramError::InvalidAccountData);
+        }
+
+        let rent_data = rent_agreement_data.unwrap();
+        rent_data.require_active()?;
+
+        let now = Self::current_timestamp(accounts)?;
+        let payoff = rent_data.payoff(now)?;
+
+        msg!(
+            "[RentShare] Payoff at {}: overdue rent {} lamports, prorated credit {} lamports, termination penalty {} lamports",
+            now,
+            payoff.overdue_rent,
+            payoff.prorated_credit,
+            payoff.termination_penalty
+        );
+
+        Ok(())
+    }
+
+    fn set_document_hash(accounts: &[AccountInfo], hash: [u8; 32]) -> ProgramResult {
+        // The document is frozen once the payee accepts the agreement it describes
+        let (rent_agreement_account, mut rent_data) =
+            Self::load_amendment(accounts, &[AgreementStatus::PendingAcceptance])?;
+
+        rent_data.document_hash = hash;
+        msg!("[RentShare] Document hash set to {:?}", hash);
+
+        rent_data.save(&mut rent_agreement_account.data.borrow_mut())?;
+
+        Ok(())
+    }
+
+    #[cfg(feature = "dev-reset")]
+    fn reset_agreement(accounts: &[AccountInfo]) -> ProgramResult {
+        let accounts_iter = &mut accounts.iter();
+
+        let rent_agreement_account = next_account_info(accounts_iter)?;
+        let payer_account = next_account_info(accounts_iter)?;
+
+        let rent_agreement_data = RentShareAccount::load(&rent_agreement_account.data.borrow());
+
+        if rent_agreement_data.is_err() {
+            msg!(
+                "[RentShare] Rent agreement account data size incorrect: {}",
+                rent_agreement_account.try_data_len()?
+            );
+            return Err(ProgramError::InvalidAccountData);
+        }
+
+        let rent_data = rent_agreement_data.unwrap();
+        if rent_data.payer_pubkey != *payer_account.key {
+            msg!("[RentShare] Payer must match payer key used during agreement initialization");
+            return Err(ProgramError::InvalidAccountData);
+        }
+
+        let held = math::add(rent_data.deposit, rent_data.escrowed_rent)?;
+        if held > 0 {
+            Self::transfer_from_agreement(rent_agreement_account, payer_account, held)?;
+        }
+        rent_agreement_account.data.borrow_mut().fill(0);
+
+        msg!(
+            "[RentShare] Rent agreement account reset. Deposit and prepaid rent of {} lamports returned to payer",
+            held
+        );
+
+        Ok(())
+    }
+
+    #[cfg(feature = "debug-instructions")]
+    fn emit_account_blob(accounts: &[AccountInfo]) -> ProgramResult {
+        let accounts_iter = &mut accounts.iter();
+
+        let rent_agreement_account = next_account_info(accounts_iter)?;
+        let rent_agreement_data = RentShareAccount::load(&rent_agreement_account.data.borrow());
+
+        if rent_agreement_data.is_err() {
+            msg!(
+                "[RentShare] Rent agreement account data size incorrect: {}",
+                rent_agreement_account.try_data_len()?
+            );
+            return Err(ProgramError::InvalidAccountData);
+        }
+
+        // Re-serialized rather than copied so trailing padding in larger accounts is left out
+        let blob = rent_agreement_data.unwrap().try_to_vec()?;
+        msg!("[RentShare] Agreement account blob of {} bytes", blob.len());
+        sol_log_data(&[&blob]);
+
+        Ok(())
+    }
+
+    #[cfg(feature = "debug-instructions")]
+    fn dump_state(accounts: &[AccountInfo]) -> ProgramResult {
+        let accounts_iter = &mut accounts.iter();
+
+        let rent_agreement_account = next_account_info(accounts_iter)?;
+        let rent_agreement_data = RentShareAccount::load(&rent_agreement_account.data.borrow());
+
+        if rent_agreement_data.is_err() {
+            msg!(
+                "[RentShare] Rent agreement account data size incorrect: {}",
+                rent_agreement_account.try_data_len()?
+            );
+            return Err(ProgramError::InvalidAccountData);
+        }
+
+        // One line per field, in declaration order, so the output is stable across releases
+        let rent_data = rent_agreement_data.unwrap();
+        msg!("[RentShare] DUMP:status={}", rent_data.status);
+        msg!("[RentShare] DUMP:payee_pubkey={}", rent_data.payee_pubkey);
+        msg!("[RentShare] DUMP:payer_pubkey={}", rent_data.payer_pubkey);
+        msg!("[RentShare] DUMP:deposit={}", rent_data.deposit);
+        msg!("[RentShare] DUMP:rent_amount={}", rent_data.rent_amount);
+        msg!("[RentShare] DUMP:duration={}", rent_data.duration);
+        msg!("[RentShare] DUMP:duration_unit={}", rent_data.duration_unit);
+        msg!(
+            "[RentShare] DUMP:remaining_payments={}",
+            rent_data.remaining_payments
+        );
+        msg!(
+            "[RentShare] DUMP:deposit_forfeit_on_early_termination={}",
+            rent_data.deposit_forfeit_on_early_termination
+        );
+        msg!("[RentShare] DUMP:created_at={}", rent_data.created_at);
+        msg!("[RentShare] DUMP:late_fee_bps={}", rent_data.late_fee_bps);
+        msg!(
+            "[RentShare] DUMP:agreement_index={}",
+            rent_data.agreement_index
+        );
+        msg!("[RentShare] DUMP:accepted_at={}", rent_data.accepted_at);
+        msg!(
+            "[RentShare] DUMP:notice_period_seconds={}",
+            rent_data.notice_period_seconds
+        );
+        msg!(
+            "[RentShare] DUMP:notice_given_at={}",
+            rent_data.notice_given_at
+        );
+        msg!(
+            "[RentShare] DUMP:required_deposit={}",
+            rent_data.required_deposit
+        );
+        msg!(
+            "[RentShare] DUMP:replenish_deposit_first={}",
+            rent_data.replenish_deposit_first
+        );
+        msg!(
+            "[RentShare] DUMP:deposit_refunded={}",
+            rent_data.deposit_refunded
+        );
+        msg!(
+            "[RentShare] DUMP:max_missed_payments={}",
+            rent_data.max_missed_payments
+        );
+        msg!(
+            "[RentShare] DUMP:auto_terminate_on_default={}",
+            rent_data.auto_terminate_on_default
+        );
+        msg!(
+            "[RentShare] DUMP:deposit_interest_bps={}",
+            rent_data.deposit_interest_bps
+        );
+        msg!(
+            "[RentShare] DUMP:accrued_interest={}",
+            rent_data.accrued_interest
+        );
+        msg!(
+            "[RentShare] DUMP:interest_periods_accrued={}",
+            rent_data.interest_periods_accrued
+        );
+        msg!(
+            "[RentShare] DUMP:currency_decimals={}",
+            rent_data.currency_decimals
+        );
+        msg!(
+            "[RentShare] DUMP:currency_symbol={:?}",
+            rent_data.currency_symbol
+        );
+        msg!(
+            "[RentShare] DUMP:deposit_refund_destination={}",
+            rent_data.deposit_refund_destination
+        );
+        msg!(
+            "[RentShare] DUMP:amendment_count={}",
+            rent_data.amendment_count
+        );
+        msg!(
+            "[RentShare] DUMP:defer_first_payment={}",
+            rent_data.defer_first_payment
+        );
+        msg!(
+            "[RentShare] DUMP:proration_min_unit={}",
+            rent_data.proration_min_unit
+        );
+        msg!(
+            "[RentShare] DUMP:document_hash={:?}",
+            rent_data.document_hash
+        );
+        msg!("[RentShare] DUMP:free_periods={}", rent_data.free_periods);
+        msg!(
+            "[RentShare] DUMP:total_rent_paid={}",
+            rent_data.total_rent_paid
+        );
+        msg!(
+            "[RentShare] DUMP:total_late_fees_paid={}",
+            rent_data.total_late_fees_paid
+        );
+        msg!(
+            "[RentShare] DUMP:max_prepay_periods={}",
+            rent_data.max_prepay_periods
+        );
+        msg!(
+            "[RentShare] DUMP:on_time_streak={}",
+            rent_data.on_time_streak
+        );
+        msg!(
+            "[RentShare] DUMP:reminder_lead_seconds={}",
+            rent_data.reminder_lead_seconds
+        );
+        msg!(
+            "[RentShare] DUMP:final_settlement_grace_seconds={}",
+            rent_data.final_settlement_grace_seconds
+        );
+        msg!(
+            "[RentShare] DUMP:settlement_started_at={}",
+            rent_data.settlement_started_at
+        );
+        msg!(
+            "[RentShare] DUMP:termination_penalty_bps={}",
+            rent_data.termination_penalty_bps
+        );
+        msg!(
+            "[RentShare] DUMP:termination_penalty_max={}",
+            rent_data.termination_penalty_max
+        );
+        msg!(
+            "[RentShare] DUMP:termination_penalty_paid={}",
+            rent_data.termination_penalty_paid
+        );
+        msg!("[RentShare] DUMP:bump={}", rent_data.bump);
+        msg!(
+            "[RentShare] DUMP:deposit_interest_paid={}",
+            rent_data.deposit_interest_paid
+        );
+        msg!(
+            "[RentShare] DUMP:end_date_extension_seconds={}",
+            rent_data.end_date_extension_seconds
+        );
+        msg!(
+            "[RentShare] DUMP:prepay_first_period={}",
+            rent_data.prepay_first_period
+        );
+        msg!("[RentShare] DUMP:escrowed_rent={}", rent_data.escrowed_rent);
+        msg!(
+            "[RentShare] DUMP:overpay_to_deposit={}",
+            rent_data.overpay_to_deposit
+        );
+        msg!(
+            "[RentShare] DUMP:platform_pubkey={}",
+            rent_data.platform_pubkey
+        );
+        msg!(
+            "[RentShare] DUMP:read_fee_lamports={}",
+            rent_data.read_fee_lamports
+        );
+        msg!(
+            "[RentShare] DUMP:observer_program={}",
+            rent_data.observer_program
+        );
+        msg!(
+            "[RentShare] DUMP:observer_required={}",
+            rent_data.observer_required
+        );
+        msg!("[RentShare] DUMP:admin_pubkey={}", rent_data.admin_pubkey);
+        msg!("[RentShare] DUMP:frozen={}", rent_data.frozen);
+        msg!(
+            "[RentShare] DUMP:assigned_payee={}",
+            rent_data.assigned_payee
+        );
+        msg!(
+            "[RentShare] DUMP:assigned_until_period={}",
+            rent_data.assigned_until_period
+        );
+        msg!(
+            "[RentShare] DUMP:late_fee_escalation_bps={}",
+            rent_data.late_fee_escalation_bps
+        );
+        msg!("[RentShare] DUMP:late_fee_max={}", rent_data.late_fee_max);
+        msg!(
+            "[RentShare] DUMP:late_fee_credit={}",
+            rent_data.late_fee_credit
+        );
+        msg!(
+            "[RentShare] DUMP:rent_change_cooldown_seconds={}",
+            rent_data.rent_change_cooldown_seconds
+        );
+        msg!(
+            "[RentShare] DUMP:last_rent_change_at={}",
+            rent_data.last_rent_change_at
+        );
+        msg!(
+            "[RentShare] DUMP:non_refundable_deposit={}",
+            rent_data.non_refundable_deposit
+        );
+        msg!(
+            "[RentShare] DUMP:termination_platform_fee={}",
+            rent_data.termination_platform_fee
+        );
+        msg!(
+            "[RentShare] DUMP:platform_fees_paid={}",
+            rent_data.platform_fees_paid
+        );
+
+        Ok(())
+    }
+
+    fn generate_settlement_report(accounts: &[AccountInfo]) -> ProgramResult {
+        let accounts_iter = &mut accounts.iter();
+
+        let rent_agreement_account = next_account_info(accounts_iter)?;
+        let rent_agreement_data = RentShareAccount::load(&rent_agreement_account.data.borrow());
+
+        if rent_agreement_data.is_err() {
+            msg!(
+                "[RentShare] Rent agreement account data size incorrect: {}",
+                rent_agreement_account.try_data_len()?
+            );
+            return Err(ProgramError::InvalidAccountData);
+        }
+
+        let rent_data = rent_agreement_data.unwrap();
+        let status = rent_data.agreement_status()?;
+        match status {
+            AgreementStatus::Completed | AgreementStatus::Terminated => {}
+            _ => {
+                msg!(
+                    "[RentShare] Settlement report is only available once the agreement has ended. Status: {:?}",
+                    status
+                );
+                return Err(RentShareError::AgreementNotSettled.into());
+            }
+        }
+
+        msg!(
+            "[RentShare] REPORT:status={:?};payments_made={};total_rent_paid={};total_late_fees_paid={};deposit_status={:?};deposit_held={};accrued_interest={};termination_penalty_paid={}",
+            status,
+            rent_data.payments_made(),
+            rent_data.total_rent_paid,
+            rent_data.total_late_fees_paid,
+            rent_data.deposit_status(),
+            rent_data.deposit,
+            rent_data.accrued_interest,
+            rent_data.termination_penalty_paid
+        );
+
+        Ok(())
+    }
+
+    fn get_health_score(accounts: &[AccountInfo]) -> ProgramResult {
+        let accounts_iter = &mut accounts.iter();
+
+        let rent_agreement_account = next_account_info(accounts_iter)?;
+        let rent_agreement_data = RentShareAccount::load(&rent_agreement_account.data.borrow());
+
+        if rent_agreement_data.is_err() {
+            msg!(
+                "[RentShare] Rent agreement account data size incorrect: {}",
+                rent_agreement_account.try_data_len()?
+            );
+            return Err(ProgramError::InvalidAccountData);
+        }
+
+        let rent_data = rent_agreement_data.unwrap();
+        rent_data.require_status(&[
+            AgreementStatus::Active,
+            AgreementStatus::AwaitingSettlement,
+            AgreementStatus::Completed,
+        ])?;
+        Self::charge_read_fee(&rent_data, accounts)?;
+
+        let now = Self::current_timestamp(accounts)?;
+        msg!(
+            "[RentShare] Health score at {}: {}/100 (on time streak {}, overdue periods {})",
+            now,
+            rent_data.health_score(now)?,
+            rent_data.on_time_streak,
+            rent_data.overdue_periods(now)?
+        );
+
+        Ok(())
+    }
+
+    fn check_reminders(accounts: &[AccountInfo]) -> ProgramResult {
+        let accounts_iter = &mut accounts.iter();
+
+        let rent_agreement_account = next_account_info(accounts_iter)?;
+        let rent_agreement_data = RentShareAccount::load(&rent_agreement_account.data.borrow());
+
+        if rent_agreement_data.is_err() {
+            msg!(
+                "[RentShare] Rent agreement account data size incorrect: {}",
+                rent_agreement_account.try_data_len()?
+            );
+            return Err(ProgramError::InvalidAccountData);
+        }
+
+        let rent_data = rent_agreement_data.unwrap();
+        rent_data.require_active()?;
+        Self::charge_read_fee(&rent_data, accounts)?;
+
+        let now = Self::current_timestamp(accounts)?;
+        match rent_data.reminder(now)? {
+            Some(Reminder::Upcoming { due_at }) => {
+                msg!("[RentShare] EVENT:REMINDER:{}:{}", due_at, now)
+            }
+            Some(Reminder::Overdue { due_at }) => {
+                msg!("[RentShare] EVENT:OVERDUE:{}:{}", due_at, now)
+            }
+            None => msg!("[RentShare] No rent payment reminder at {}", now),
+        }
+
+        Ok(())
+    }
+
+    fn complete_settlement(accounts: &[AccountInfo]) -> ProgramResult {
+        let accounts_iter = &mut accounts.iter();
+
+        let rent_agreement_account = next_account_info(accounts_iter)?;
+        let rent_agreement_data = RentShareAccount::load(&rent_agreement_account.data.borrow());
+
+        if rent_agreement_data.is_err() {
+            msg!(
+                "[RentShare] Rent agreement account data size incorrect: {}",
+                rent_agreement_account.try_data_len()?
+            );
+            return Err(ProgramError::InvalidAccountData);
+        }
+
+        let mut rent_data = rent_agreement_data.unwrap();
+        rent_data.require_status(&[AgreementStatus::AwaitingSettlement])?;
+
+        let now = Self::current_timestamp(accounts)?;
+        if !rent_data.settlement_grace_elapsed(now) {
+            msg!(
+                "[RentShare] Final settlement grace runs until {}",
+                rent_data.settlement_started_at.saturating_add(
+                    i64::try_from(rent_data.final_settlement_grace_seconds).unwrap_or(i64::MAX)
+                )
+            );
+            return Err(RentShareError::AgreementNotSettled.into());
+        }
+
+        rent_data.set_status(AgreementStatus::Completed, now)?;
+        rent_data.save(&mut rent_agreement_account.data.borrow_mut())?;
+
+        Ok(())
+    }
+
+    fn extend_end_date(accounts: &[AccountInfo], additional_seconds: i64) -> ProgramResult {
+        let (rent_agreement_account, mut rent_data) =
+            Self::load_amendment(accounts, &[AgreementStatus::Active])?;
+
+        if additional_seconds <= 0 {
+            msg!(
+                "[RentShare] End date extension must be positive: {} seconds",
+                additional_seconds
+            );
+            return Err(RentShareError::InvalidAgreementTerms.into());
+        }
+
+        rent_data.end_date_extension_seconds = rent_data
+            .end_date_extension_seconds
+            .checked_add(additional_seconds)
+            .ok_or(RentShareError::InvalidTimestamp)?;
+
+        let now = Self::current_timestamp(accounts)?;
+        msg!(
+            "[RentShare] Lease end extended by {} seconds to {}",
+            additional_seconds,
+            rent_data.lease_end_timestamp(now)?
+        );
+
+        rent_data.record_amendment(Amendment::EndDateExtension)?;
+        rent_data.save(&mut rent_agreement_account.data.borrow_mut())?;
+
+        Ok(())
+    }
+
+    /// Load an agreement in one of the `allowed` statuses for a change that must be signed by
+    /// both parties
+    fn load_amendment<'a, 'b>(
+        accounts: &'a [AccountInfo<'b>],
+        allowed: &[AgreementStatus],
+    ) -> Result<(&'a AccountInfo<'b>, RentShareAccount), ProgramError> {
+        let accounts_iter = &mut accounts.iter();
+
+        let rent_agreement_account = next_account_info(accounts_iter)?;
+        let payee_account = next_account_info(accounts_iter)?;
+        let payer_account = next_account_info(accounts_iter)?;
+
+        let rent_agreement_data = RentShareAccount::load(&rent_agreement_account.data.borrow());
+
+        if rent_agreement_data.is_err() {
+            msg!(
+                "[RentShare] Rent agreement account data size incorrect: {}",
+                rent_agreement_account.try_data_len()?
+            );
+            return Err(ProgramError::InvalidAccountData);
+        }
+
+        let rent_data = rent_agreement_data.unwrap();
+        rent_data.require_status(allowed)?;
+
+        if rent_data.payee_pubkey != *payee_account.key {
+            msg!("[RentShare] Payee must match payee key used during agreement initialization");
+            return Err(ProgramError::InvalidAccountData);
+        }
+
+        if rent_data.payer_pubkey != *payer_account.key {
+            msg!("[RentShare] Payer must match payer key used during agreement initialization");
+            return Err(ProgramError::InvalidAccountData);
+        }
+
+        Ok((rent_agreement_account, rent_data))
+    }
+
+    /// Read the rent sysvar, rejecting one that charges no rent since every account would then
+    /// look rent exempt
+    fn load_rent_sysvar(rent_sysvar_account: &AccountInfo) -> Result<Rent, ProgramError> {
+        let solana_rent = Rent::from_account_info(rent_sysvar_account)?;
+        if solana_rent.lamports_per_byte_year == 0 {
+            msg!("[RentShare] Rent sysvar reports zero lamports per byte year");
+            return Err(ProgramError::InvalidArgument);
+        }
+
+        Ok(solana_rent)
+    }
+
+    /// Minimum balance of the agreement account, which is also the escrow: the rent exempt minimum
+    /// for its data plus the held deposit and prepaid rent, which are not available to cover rent
+    /// exemption
+    fn required_agreement_balance(
+        rent_agreement_account: &AccountInfo,
+        solana_rent: &Rent,
+        rent_data: &RentShareAccount,
+    ) -> Result<u64, ProgramError> {
+        Self::require_sane_data_len(rent_agreement_account)?;
+        math::add(
+            solana_rent.minimum_balance(rent_agreement_account.data_len()),
+            math::add(rent_data.deposit, rent_data.escrowed_rent)?,
+        )
+    }
+
+    /// Reject agreement accounts with pathological data sizes before doing rent exemption math
+    fn require_sane_data_len(rent_agreement_account: &AccountInfo) -> ProgramResult {
+        if rent_agreement_account.data_len() > MAX_AGREEMENT_ACCOUNT_DATA_LEN {
+            msg!(
+                "[RentShare] Rent agreement account data length {} exceeds the maximum of {}",
+                rent_agreement_account.data_len(),
+                MAX_AGREEMENT_ACCOUNT_DATA_LEN
+            );
+            return Err(ProgramError::InvalidAccountData);
+        }
+
+        Ok(())
+    }
+
+    /// Check the accounts match the count, signer, writable, owner and address requirements of
+    /// the instruction before any handler runs, so handlers only check what the agreement records
+    fn validate_accounts(
+        instruction: &RentShareInstruction,
+        accounts: &[AccountInfo],
+        program_id: &Pubkey,
+    ) -> ProgramResult {
+        let specs = instruction.account_specs();
+        if accounts.len() < specs.len() {
+            msg!(
+                "[RentShare] {:?} expects {} accounts but received {}",
+                instruction,
+                specs.len(),
+                accounts.len()
+            );
+            return Err(ProgramError::NotEnoughAccountKeys);
+        }
+
+        // A clock sysvar account may follow the documented accounts for `current_timestamp`
+        let optional_specs = instruction.optional_account_specs();
+        let trailing_clock = accounts.len() > specs.len()
+            && *accounts[accounts.len() - 1].key == sysvar::clock::id();
+        let passed = accounts.len() - trailing_clock as usize;
+        if passed > specs.len() + optional_specs.len() {
+            msg!(
+                "[RentShare] {:?} expects {} accounts but received {}",
+                instruction,
+                specs.len(),
+                accounts.len()
+            );
+            return Err(ProgramError::InvalidArgument);
+        }
+
+        let all_specs = specs.iter().chain(optional_specs);
+        for (index, (account, spec)) in accounts[..passed].iter().zip(all_specs).enumerate() {
+            if spec.signer && !account.is_signer {
+                msg!("[RentShare] Account {} must sign the transaction", index);
+                return Err(ProgramError::MissingRequiredSignature);
+            }
+
+            if spec.writable && !account.is_writable {
+                msg!("[RentShare] Account {} must be writable", index);
+                return Err(ProgramError::InvalidArgument);
+            }
+
+            if !spec.writable && !spec.signer && account.is_writable {
+                msg!("[RentShare] Account {} must be read-only", index);
+                return Err(ProgramError::InvalidArgument);
+            }
+
+            if spec.program_owned && account.owner != program_id {
+                msg!("[RentShare] Account {} is not owned by this program", index);
+                return Err(ProgramError::IncorrectProgramId);
+            }
+
+            if let Some(key) = spec.key {
+                if *account.key != key {
+                    msg!("[RentShare] Account {} must be {}", index, key);
+                    return Err(ProgramError::InvalidArgument);
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Current cluster time from the clock syscall, falling back to a clock sysvar account
+    /// passed with the instruction on runtimes without the syscall
+    fn current_timestamp(accounts: &[AccountInfo]) -> Result<i64, ProgramError> {
+        if let Ok(clock) = Clock::get() {
+            return Ok(clock.unix_timestamp);
+        }
+
+        accounts
+            .iter()
+            .find(|account| *account.key == sysvar::clock::id())
+            .and_then(|account| Clock::from_account_info(account).ok())
+            .map(|clock| clock.unix_timestamp)
+            .ok_or_else(|| {
+                msg!("[RentShare] Clock unavailable. Pass the clock sysvar account");
+                RentShareError::ClockUnavailable.into()
+            })
+    }
+
+    /// Reject instructions modifying a frozen agreement before any handler runs. Accounts that do
+    /// not hold an agreement are left to the handler to reject.
+    fn require_not_frozen(
+        instruction: &RentShareInstruction,
+        accounts: &[AccountInfo],
+    ) -> ProgramResult {
+        if !instruction.modifies_agreement() {
+            return Ok(());
+        }
+
+        let frozen = RentShareAccount::load(&accounts[0].data.borrow())
+            .map(|rent_data| rent_data.frozen)
+            .unwrap_or(false);
+        if frozen {
+            msg!(
+                "[RentShare] Agreement is frozen. {:?} is blocked until its admin unfreezes it",
+                instruction
+            );
+            return Err(RentShareError::AgreementFrozen.into());
+        }
+        Ok(())
+    }
+
+    /// Account of the payee recorded at initialization: the payee account of `PayRent` unless rent is
+    /// assigned, in which case the recorded payee follows the `PayRent` accounts
+    fn recorded_payee_account<'a, 'b>(
+        rent_data: &RentShareAccount,
+        accounts: &'a [AccountInfo<'b>],
+    ) -> Result<&'a AccountInfo<'b>, ProgramError> {
+        let recorded_payee_account = [1, 4]
+            .iter()
+            .filter_map(|index| accounts.get(*index))
+            .find(|account| *account.key == rent_data.payee_pubkey);
+        match recorded_payee_account {
+            Some(account) => Ok(PayeeAccount::new(account)?.info()),
+            None => {
+                msg!(
+                    "[RentShare] Rent is assigned to {}. Pass the recorded payee {} after the payment accounts",
+                    rent_data.assigned_payee,
+                    rent_data.payee_pubkey
+                );
+                Err(ProgramError::NotEnoughAccountKeys)
+            }
+        }
+    }
+
+    /// Account receiving the deposit refunded by `TerminateEarly`: the payer, unless the agreement
+    /// records another refund destination, which then follows the termination fee accounts
+    fn deposit_refund_account<'a, 'b>(
+        rent_data: &RentShareAccount,
+        accounts: &'a [AccountInfo<'b>],
+    ) -> Result<&'a AccountInfo<'b>, ProgramError> {
+        let destination = rent_data.deposit_refund_destination;
+        let refund_account = accounts
+            .iter()
+            .enumerate()
+            .filter(|(index, _)| *index == 1 || *index >= 3)
+            .map(|(_, account)| account)
+            .find(|account| *account.key == destination);
+        let refund_account = match refund_account {
+            Some(account) => account,
+            None => {
+                msg!(
+                    "[RentShare] Deposit refund destination {} must be passed after the termination fee accounts",
+                    destination
+                );
+                return Err(ProgramError::NotEnoughAccountKeys);
+            }
+        };
+
+        // A program owned destination, including the agreement itself, could not spend the refund
+        if refund_account.key == accounts[0].key
+            || *refund_account.owner != system_program::id()
+            || !refund_account.is_writable
+        {
+            msg!(
+                "[RentShare] Refund destination must be a writable system owned wallet. Owner: {}",
+                refund_account.owner
+            );
+            return Err(ProgramError::InvalidAccountData);
+        }
+
+        Ok(refund_account)
+    }
+
+    /// Charge the agreement's read fee, when it has one, from the caller to the platform. The fee
+    /// accounts follow the agreement account: the signing caller, the platform and the system program.
+    fn charge_read_fee(rent_data: &RentShareAccount, accounts: &[AccountInfo]) -> ProgramResult {
+        if rent_data.read_fee_lamports == 0 {
+            return Ok(());
+        }
+
+        if accounts.len() < 4 {
+            msg!(
+                "[RentShare] Agreement charges a read fee of {} lamports. Pass the caller, platform and system program accounts",
+                rent_data.read_fee_lamports
+            );
+            return Err(ProgramError::NotEnoughAccountKeys);
+        }
+
+        let caller_account = &accounts[1];
+        let platform_account = &accounts[2];
+        let system_program_account = &accounts[3];
+
+        if *platform_account.key != rent_data.platform_pubkey {
+            msg!(
+                "[RentShare] Read fee must be paid to the agreement platform {}",
+                rent_data.platform_pubkey
+            );
+            return Err(ProgramError::InvalidArgument);
+        }
+
+        let instruction = system_instruction::transfer(
+            caller_account.key,
+            platform_account.key,
+            rent_data.read_fee_lamports,
+        );
+
+        invoke(
+            &instruction,
+            &[
+                system_program_account.clone(),
+                platform_account.clone(),
+                caller_account.clone(),
+            ],
+        )?;
+
+        msg!(
+            "[RentShare] Read fee of {} lamports paid to {}",
+            rent_data.read_fee_lamports,
+            platform_account.key
+        );
+        Ok(())
+    }
+
+    /// Send a payment notification to the agreement's observer program, when it has one. The
+    /// observer follows the `PayRent` accounts and the recorded payee. The runtime aborts the whole transaction when the
+    /// observer fails, so only a missing observer account can be skipped.
+    fn notify_observer(
+        rent_data: &RentShareAccount,
+        accounts: &[AccountInfo],
+        notification: PaymentNotification,
+    ) -> ProgramResult {
+        if rent_data.observer_program == Pubkey::default() {
+            return Ok(());
+        }
+
+        let observer_account = accounts
+            .iter()
+            .skip(4)
+            .find(|account| *account.key == rent_data.observer_program && account.executable);
+        let observer_account = match observer_account {
+            Some(account) => account,
+            None if rent_data.observer_required => {
+                msg!(
+                    "[RentShare] Observer program {} must be passed after the payment accounts",
+                    rent_data.observer_program
+                );
+                return Err(ProgramError::NotEnoughAccountKeys);
+            }
+            None => {
+                msg!(
+                    "[RentShare] Observer program {} not passed. Skipping payment notification",
+                    rent_data.observer_program
+                );
+                return Ok(());
+            }
+        };
+
+        let agreement_account = &accounts[0];
+        let payer_account = &accounts[2];
+        let instruction = Instruction {
+            program_id: rent_data.observer_program,
+            accounts: vec![
+                AccountMeta::new_readonly(*agreement_account.key, false),
+                AccountMeta::new_readonly(*payer_account.key, false),
+            ],
+            data: notification.pack(),
+        };
+
+        invoke(
+            &instruction,
+            &[
+                agreement_account.clone(),
+                payer_account.clone(),
+                observer_account.clone(),
+            ],
+        )?;
+
+        msg!(
+            "[RentShare] Payment notification sent to observer {}",
+            rent_data.observer_program
+        );
+        Ok(())
+    }
+
+    /// Pay the agreement's termination platform fee, when it has one, from the held deposit and
+    /// charge the payer for any part the deposit cannot cover. The platform account follows the
+    /// `TerminateEarly` accounts, then the system program when the payer is charged.
+    fn charge_termination_platform_fee(
+        rent_data: &mut RentShareAccount,
+        accounts: &[AccountInfo],
+    ) -> ProgramResult {
+        let fee = rent_data.termination_platform_fee;
+        if fee == 0 {
+            return Ok(());
+        }
+
+        let platform_account = match accounts.get(3) {
+            Some(account) if *account.key == rent_data.platform_pubkey => account,
+            _ => {
+                msg!(
+                    "[RentShare] Termination platform fee of {} lamports must be paid to the agreement platform {}",
+                    fee,
+                    rent_data.platform_pubkey
+                );
+                return Err(ProgramError::InvalidArgument);
+            }
+        };
+        let rent_agreement_account = &accounts[0];
+        let payer_account = &accounts[1];
+
+        // The non-refundable part of the deposit belongs to the payee, so only the part that would
+        // be returned to the payer covers the fee
+        let from_deposit = fee.min(rent_data.refundable_deposit());
+        let from_payer = math::sub(fee, from_deposit)?;
+        if from_payer > payer_account.lamports() {
+            msg!(
+                "[RentShare] Payer balance {} cannot cover the {} lamports of the termination platform fee the deposit does not",
+                payer_account.lamports(),
+                from_payer
+            );
+            return Err(ProgramError::InsufficientFunds);
+        }
+
+        if from_deposit > 0 {
+            Self::transfer_from_agreement(rent_agreement_account, platform_account, from_deposit)?;
+            rent_data.deposit = math::sub(rent_data.deposit, from_deposit)?;
+        }
+
+        if from_payer > 0 {
+            let system_program_account = match accounts.get(4) {
+                Some(account) if *account.key == system_program::id() => account,
+                _ => {
+                    msg!("[RentShare] Charging the payer the termination platform fee requires the system program account");
+                    return Err(ProgramError::NotEnoughAccountKeys);
+                }
+            };
+
+            let instruction =
+                system_instruction::transfer(payer_account.key, platform_account.key, from_payer);
+
+            invoke(
+                &instruction,
+                &[
+                    system_program_account.clone(),
+                    platform_account.clone(),
+                    payer_account.clone(),
+                ],
+            )?;
+        }
+
+        rent_data.platform_fees_paid = math::add(rent_data.platform_fees_paid, fee)?;
+        msg!(
+            "[RentShare] Termination platform fee of {} lamports paid to {}: {} from the deposit, {} from the payer",
+            fee,
+            platform_account.key,
+            from_deposit,
+            from_payer
+        );
+        Ok(())
+    }
+
+    /// Move lamports held by the program owned agreement account to another account
+    fn transfer_from_agreement(
+        rent_agreement_account: &AccountInfo,
+        destination_account: &AccountInfo,
+        amount: u64,
+    ) -> ProgramResult {
+        let agreement_balance = rent_agreement_account
+            .lamports()
+            .checked_sub(amount)
+            .ok_or(ProgramError::InsufficientFunds)?;
+        let destination_balance = math::add(destination_account.lamports(), amount)?;
+
+        **rent_agreement_account.try_borrow_mut_lamports()? = agreement_balance;
+        **destination_account.try_borrow_mut_lamports()? = destination_balance;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use solana_program::{
+        entrypoint::SUCCESS, instruction::Instruction, program_error::UNSUPPORTED_SYSVAR,
+        program_stubs, program_utils::limited_deserialize, system_instruction::SystemInstruction,
+    };
+    use std::{
+        cell::{Cell, RefCell},
+        sync::Once,
+    };
+
+    use crate::instruction::AccountSpec;
+    use crate::state::{
+        test_fixtures::{active_agreement, agreement_in, monthly_terms, NOW, RENT},
+        DepositStatus,
+    };
+
+    /// Backing storage for an `AccountInfo` passed to the processor
+    struct TestAccount {
+        key: Pubkey,
+        owner: Pubkey,
+        lamports: u64,
+        data: Vec<u8>,
+        is_signer: bool,
+        is_writable: bool,
+        executable: bool,
+    }
+
+    impl TestAccount {
+        fn wallet(key: Pubkey, is_signer: bool) -> Self {
+            Self {
+                key,
+                owner: system_program::id(),
+                lamports: 100 * RENT,
+                data: vec![],
+                is_signer,
+                is_writable: true,
+                executable: false,
+            }
+        }
+
+        fn agreement(program_id: &Pubkey, rent_data: &RentShareAccount) -> Self {
+            let mut data = vec![0; RentShareAccount::LEN];
+            rent_data.save(&mut data).unwrap();
+            Self {
+                key: Pubkey::new_unique(),
+                owner: *program_id,
+                lamports: 10 * RENT + rent_data.deposit,
+                data,
+                is_signer: false,
+                is_writable: true,
+                executable: false,
+            }
+        }
+
+        /// Not yet created agreement account at the address derived for `terms`, holding `lamports`
+        fn new_agreement(program_id: &Pubkey, terms: &AgreementTerms, lamports: u64) -> Self {
+            let (key, _) = find_agreement_address(
+                program_id,
+                &terms.payee_pubkey,
+                &terms.payer_pubkey,
+                terms.agreement_index,
+            );
+            Self {
+                key,
+                owner: system_program::id(),
+                lamports,
+                data: vec![0; AGREEMENT_ACCOUNT_SPACE],
+                is_signer: false,
+                is_writable: true,
+                executable: false,
+            }
+        }
+
+        fn program(key: Pubkey) -> Self {
+            Self {
+                key,
+                owner: Pubkey::default(),
+                lamports: 1,
+                data: vec![],
+                is_signer: false,
+                is_writable: false,
+                executable: true,
+            }
+        }
+
+        fn system_program() -> Self {
+            Self::program(system_program::id())
+        }
+
+        fn sysvar(key: Pubkey, data: Vec<u8>) -> Self {
+            Self {
+                key,
+                owner: sysvar::id(),
+                lamports: 1,
+                data,
+                is_signer: false,
+                is_writable: false,
+                executable: false,
+            }
+        }
+
+        fn clock(now: i64) -> Self {
+            // Bincode layout of `Clock`: slot, epoch_start_timestamp, epoch, leader_schedule_epoch,
+            // unix_timestamp
+            let mut data = vec![0; 32];
+            data.extend_from_slice(&now.to_le_bytes());
+            Self::sysvar(sysvar::clock::id(), data)
+        }
+
+        fn rent_sysvar(rent: &Rent) -> Self {
+            // Bincode layout of `Rent`: lamports_per_byte_year, exemption_threshold, burn_percent
+            let mut data = rent.lamports_per_byte_year.to_le_bytes().to_vec();
+            data.extend_from_slice(&rent.exemption_threshold.to_le_bytes());
+            data.push(rent.burn_percent);
+            Self::sysvar(sysvar::rent::id(), data)
+        }
+
+        fn info(&mut self) -> AccountInfo<'_> {
+            AccountInfo::new(
+                &self.key,
+                self.is_signer,
+                self.is_writable,
+                &mut self.lamports,
+                &mut self.data,
+                &self.owner,
+                self.executable,
+                0,
+            )
+        }
+
+        fn rent_data(&self) -> RentShareAccount {
+            RentShareAccount::load(&self.data).unwrap()
+        }
+    }
+
+    thread_local! {
+        /// Program whose instruction is running, which signs for the addresses derived from it
+        static RUNNING_PROGRAM: Cell<Pubkey> = Cell::new(Pubkey::default());
+        /// This program, which cross-program invocations are dispatched back into
+        static RENT_SHARE_PROGRAM: Cell<Pubkey> = Cell::new(Pubkey::default());
+        static RUNTIME: RefCell<Runtime> = RefCell::new(Runtime::default());
+        static LOGS: RefCell<Vec<String>> = const { RefCell::new(vec![]) };
+        static LOG_DATA: RefCell<Vec<Vec<Vec<u8>>>> = const { RefCell::new(vec![]) };
+        /// Every instruction invoked, in order
+        static INVOCATIONS: RefCell<Vec<Instruction>> = const { RefCell::new(vec![]) };
+    }
+
+    /// Runtime behaviour a test can change for a single `process_in` call
+    #[derive(Default)]
+    struct Runtime {
+        /// Timestamp returned by `Clock::get`, which otherwise fails as it does off-chain
+        clock: Option<i64>,
+        /// Programs whose invocations fail
+        failing_programs: Vec<Pubkey>,
+    }
+
+    /// Syscalls standing in for the runtime: system program instructions are carried out on the
+    /// accounts passed to `invoke`, invocations of this program are processed with the signatures
+    /// of the invoking program's derived addresses, and logs are captured. Accounts cannot be
+    /// resized here, so tests allocate new accounts at their final size.
+    struct RuntimeStubs;
+
+    impl RuntimeStubs {
+        fn signed(account: &AccountInfo, signers_seeds: &[&[&[u8]]]) -> bool {
+            let running_program = RUNNING_PROGRAM.with(Cell::get);
+            account.is_signer
+                || signers_seeds.iter().any(|seeds| {
+                    Pubkey::create_program_address(seeds, &running_program).as_ref()
+                        == Ok(account.key)
+                })
+        }
+
+        fn invoke_system_program(
+            instruction: &Instruction,
+            account_infos: &[AccountInfo],
+            signers_seeds: &[&[&[u8]]],
+        ) -> ProgramResult {
+            let account = |index: usize| {
+                let account = account_infos
+                    .iter()
+                    .find(|account| *account.key == instruction.accounts[index].pubkey)
+                    .ok_or(ProgramError::NotEnoughAccountKeys)?;
+                if instruction.accounts[index].is_signer && !Self::signed(account, signers_seeds) {
+                    return Err(ProgramError::MissingRequiredSignature);
+                }
+                Ok(account)
+            };
+            let transfer = |lamports: u64| {
+                let from = account(0)?;
+                let to = account(1)?;
+                let from_balance = math::sub(from.lamports(), lamports)?;
+                let to_balance = math::add(to.lamports(), lamports)?;
+                **from.try_borrow_mut_lamports()? = from_balance;
+                **to.try_borrow_mut_lamports()? = to_balance;
+                Ok(())
+            };
+            let allocate = |index: usize, space: u64| {
+                let account = account(index)?;
+                if account.data_len() as u64 != space {
+                    return Err(ProgramError::InvalidAccountData);
+                }
+                Ok(account)
+            };
+
+            match limited_deserialize(&instruction.data, 1_024)
+                .map_err(|_| ProgramError::InvalidInstructionData)?
+            {
+                SystemInstruction::Transfer { lamports } => transfer(lamports),
+                SystemInstruction::CreateAccount {
+                    lamports,
+                    space,
+                    owner,
+                } => {
+                    let new_account = allocate(1, space)?;
+                    // The system program refuses to create an address that already holds lamports
+                    if new_account.lamports() > 0 {
+                        return Err(ProgramError::AccountAlreadyInitialized);
+                    }
+                    transfer(lamports)?;
+                    new_account.assign(&owner);
+                    Ok(())
+                }
+                SystemInstruction::Allocate { space } => {
+                    allocate(0, space)?;
+                    Ok(())
+                }
+                SystemInstruction::Assign { owner } => {
+                    account(0)?.assign(&owner);
+                    Ok(())
+                }
+                _ => Err(ProgramError::InvalidInstructionData),
+            }
+        }
+
+        fn invoke_rent_share(
+            instruction: &Instruction,
+            account_infos: &[AccountInfo],
+            signers_seeds: &[&[&[u8]]],
+        ) -> ProgramResult {
+            let infos = instruction
+                .accounts
+                .iter()
+                .map(|meta| {
+                    let mut info = account_infos
+                        .iter()
+                        .find(|account| *account.key == meta.pubkey)
+                        .ok_or(ProgramError::NotEnoughAccountKeys)?
+                        .clone();
+                    info.is_signer = meta.is_signer && Self::signed(&info, signers_seeds);
+                    info.is_writable = meta.is_writable && info.is_writable;
+                    Ok(info)
+                })
+                .collect::<Result<Vec<_>, ProgramError>>()?;
+
+            let invoking_program =
+                RUNNING_PROGRAM.with(|program| program.replace(instruction.program_id));
+            let result = Processor::process(&instruction.program_id, &infos, &instruction.data);
+            RUNNING_PROGRAM.with(|program| program.set(invoking_program));
+            result
+        }
+    }
+
+    impl program_stubs::SyscallStubs for RuntimeStubs {
+        fn sol_log(&self, message: &str) {
+            LOGS.with(|logs| logs.borrow_mut().push(message.to_string()));
+        }
+
+        fn sol_log_data(&self, fields: &[&[u8]]) {
+            let fields = fields.iter().map(|field| field.to_vec()).collect();
+            LOG_DATA.with(|log_data| log_data.borrow_mut().push(fields));
+        }
+
+        fn sol_get_clock_sysvar(&self, var_addr: *mut u8) -> u64 {
+            match RUNTIME.with(|runtime| runtime.borrow().clock) {
+                Some(unix_timestamp) => {
+                    let clock = Clock {
+                        unix_timestamp,
+                        ..Clock::default()
+                    };
+                    unsafe { std::ptr::write(var_addr as *mut Clock, clock) };
+                    SUCCESS
+                }
+                None => UNSUPPORTED_SYSVAR,
+            }
+        }
+
+        fn sol_invoke_signed(
+            &self,
+            instruction: &Instruction,
+            account_infos: &[AccountInfo],
+            signers_seeds: &[&[&[u8]]],
+        ) -> ProgramResult {
+            INVOCATIONS.with(|invocations| invocations.borrow_mut().push(instruction.clone()));
+            if instruction.program_id == system_program::id() {
+                return Self::invoke_system_program(instruction, account_infos, signers_seeds);
+            }
+            if instruction.program_id == RENT_SHARE_PROGRAM.with(Cell::get) {
+                return Self::invoke_rent_share(instruction, account_infos, signers_seeds);
+            }
+
+            let fails = RUNTIME.with(|runtime| {
+                runtime
+                    .borrow()
+                    .failing_programs
+                    .contains(&instruction.program_id)
+            });
+            if fails {
+                return Err(ProgramError::Custom(0));
+            }
+            Ok(())
+        }
+    }
+
+    /// Run `run` as `running_program` under `runtime`, with the logs and invocations of any earlier
+    /// run cleared
+    fn run_as<T>(
+        running_program: &Pubkey,
+        program_id: &Pubkey,
+        runtime: Runtime,
+        run: impl FnOnce() -> T,
+    ) -> T {
+        static STUBS: Once = Once::new();
+        STUBS.call_once(|| {
+            program_stubs::set_syscall_stubs(Box::new(RuntimeStubs));
+        });
+
+        LOGS.with(|logs| logs.borrow_mut().clear());
+        LOG_DATA.with(|log_data| log_data.borrow_mut().clear());
+        INVOCATIONS.with(|invocations| invocations.borrow_mut().clear());
+        RUNNING_PROGRAM.with(|program| program.set(*running_program));
+        RENT_SHARE_PROGRAM.with(|program| program.set(*program_id));
+        RUNTIME.with(|current| current.replace(runtime));
+
+        let result = run();
+        RUNTIME.with(|current| current.take());
+        result
+    }
+
+    fn process_in(
+        runtime: Runtime,
+        program_id: &Pubkey,
+        accounts: &mut [TestAccount],
+        instruction: RentShareInstruction,
+    ) -> ProgramResult {
+        let infos: Vec<AccountInfo> = accounts.iter_mut().map(TestAccount::info).collect();
+        run_as(program_id, program_id, runtime, || {
+            Processor::process(program_id, &infos, &instruction.pack())
+        })
+    }
+
+    fn process(
+        program_id: &Pubkey,
+        accounts: &mut [TestAccount],
+        instruction: RentShareInstruction,
+    ) -> ProgramResult {
+        process_in(Runtime::default(), program_id, accounts, instruction)
+    }
+
+    fn logged(message: &str) -> bool {
+        LOGS.with(|logs| logs.borrow().iter().any(|log| log == message))
+    }
+
+    /// Instructions invoked by the last processed instruction
+    fn invocations() -> Vec<Instruction> {
+        INVOCATIONS.with(|invocations| invocations.borrow().clone())
+    }
+
+    fn pay_rent_accounts(
+        program_id: &Pubkey,
+        rent_data: &RentShareAccount,
+        payer: Pubkey,
+    ) -> Vec<TestAccount> {
+        vec![
+            TestAccount::agreement(program_id, rent_data),
+            TestAccount::wallet(rent_data.payee_pubkey, false),
+            TestAccount::wallet(payer, true),
+            TestAccount::system_program(),
+            TestAccount::clock(NOW),
+        ]
+    }
+
+    /// Accounts for an instruction only reading the agreement
+    fn view_accounts(program_id: &Pubkey, rent_data: &RentShareAccount) -> Vec<TestAccount> {
+        let mut agreement = TestAccount::agreement(program_id, rent_data);
+        agreement.is_writable = false;
+        vec![agreement, TestAccount::clock(NOW)]
+    }
+
+    fn pay_one_period() -> RentShareInstruction {
+        RentShareInstruction::PayRent {
+            rent_amount: RENT,
+            periods: 1,
+        }
+    }
+
+    #[test]
+    fn pay_rent_accepts_the_recorded_payer() {
+        let program_id = Pubkey::new_unique();
+        let rent_data = active_agreement();
+        let mut accounts = pay_rent_accounts(&program_id, &rent_data, rent_data.payer_pubkey);
+
+        process(&program_id, &mut accounts, pay_one_period()).unwrap();
+        assert_eq!(accounts[0].rent_data().remaining_payments, 11);
+    }
+
+    #[test]
+    fn pay_rent_rejects_a_stranger_signing_as_payer() {
+        let program_id = Pubkey::new_unique();
+        let rent_data = active_agreement();
+        let mut accounts = pay_rent_accounts(&program_id, &rent_data, Pubkey::new_unique());
+
+        assert_eq!(
+            process(&program_id, &mut accounts, pay_one_period()),
+            Err(ProgramError::InvalidAccountData)
+        );
+        assert_eq!(accounts[0].rent_data().remaining_payments, 12);
+    }
+
+    #[test]
+    fn pay_rent_rejects_the_previous_payer_after_transfer_tenant() {
+        let program_id = Pubkey::new_unique();
+        let rent_data = active_agreement();
+        let old_payer = rent_data.payer_pubkey;
+        let new_payer = Pubkey::new_unique();
+
+        let mut accounts = vec![
+            TestAccount::agreement(&program_id, &rent_data),
+            TestAccount::wallet(rent_data.payee_pubkey, true),
+            TestAccount::wallet(old_payer, true),
+        ];
+        process(
+            &program_id,
+            &mut accounts,
+            RentShareInstruction::TransferTenant { new_payer },
+        )
+        .unwrap();
+        let rent_data = accounts[0].rent_data();
+        assert_eq!(rent_data.payer_pubkey, new_payer);
+
+        let mut accounts = pay_rent_accounts(&program_id, &rent_data, old_payer);
+        assert_eq!(
+            process(&program_id, &mut accounts, pay_one_period()),
+            Err(ProgramError::InvalidAccountData)
+        );
+
+        let mut accounts = pay_rent_accounts(&program_id, &rent_data, new_payer);
+        process(&program_id, &mut accounts, pay_one_period()).unwrap();
+    }
+
+    #[test]
+    fn amendments_are_counted_but_payments_are_not() {
+        let program_id = Pubkey::new_unique();
+        let rent_data = active_agreement();
+        let mut accounts = vec![
+            TestAccount::agreement(&program_id, &rent_data),
+            TestAccount::wallet(rent_data.payee_pubkey, true),
+            TestAccount::wallet(rent_data.payer_pubkey, true),
+            TestAccount::clock(NOW),
+        ];
+
+        for (instruction, amendment_count, event) in [
+            (
+                RentShareInstruction::UpdateRentAmount {
+                    rent_amount: 2 * RENT,
+                },
+                1,
+                "RentAmountUpdate",
+            ),
+            (
+                RentShareInstruction::ExtendAgreement { periods: 6 },
+                2,
+                "Extension",
+            ),
+        ] {
+            process(&program_id, &mut accounts, instruction).unwrap();
+            assert_eq!(accounts[0].rent_data().amendment_count, amendment_count);
+            assert!(logged(&format!(
+                "[RentShare] EVENT:Amendment type={} count={}",
+                event, amendment_count
+            )));
+        }
+
+        let mut accounts = pay_rent_accounts(
+            &program_id,
+            &accounts[0].rent_data(),
+            rent_data.payer_pubkey,
+        );
+        process(
+            &program_id,
+            &mut accounts,
+            RentShareInstruction::PayRent {
+                rent_amount: 2 * RENT,
+                periods: 1,
+            },
+        )
+        .unwrap();
+        assert_eq!(accounts[0].rent_data().remaining_payments, 17);
+        assert_eq!(accounts[0].rent_data().amendment_count, 2);
+    }
+
+    #[test]
+    fn extend_end_date_moves_the_lease_end_without_adding_payments() {
+        let program_id = Pubkey::new_unique();
+        let rent_data = active_agreement();
+        let extension = 30 * 24 * 60 * 60;
+        let lease_end = rent_data.lease_end_timestamp(NOW).unwrap();
+
+        let mut accounts = vec![
+            TestAccount::agreement(&program_id, &rent_data),
+            TestAccount::wallet(rent_data.payee_pubkey, true),
+            TestAccount::wallet(rent_data.payer_pubkey, false),
+            TestAccount::clock(NOW),
+        ];
+        assert_eq!(
+            process(
+                &program_id,
+                &mut accounts,
+                RentShareInstruction::ExtendEndDate {
+                    additional_seconds: extension
+                }
+            ),
+            Err(ProgramError::MissingRequiredSignature)
+        );
+
+        accounts[2].is_signer = true;
+        for additional_seconds in [0, -1] {
+            assert_eq!(
+                process(
+                    &program_id,
+                    &mut accounts,
+                    RentShareInstruction::ExtendEndDate { additional_seconds }
+                ),
+                Err(RentShareError::InvalidAgreementTerms.into())
+            );
+        }
+
+        process(
+            &program_id,
+            &mut accounts,
+            RentShareInstruction::ExtendEndDate {
+                additional_seconds: extension,
+            },
+        )
+        .unwrap();
+        let extended = accounts[0].rent_data();
+        assert_eq!(
+            extended.lease_end_timestamp(NOW).unwrap(),
+            lease_end + extension
+        );
+        assert_eq!(extended.remaining_payments, rent_data.remaining_payments);
+        assert_eq!(
+            extended.next_due_timestamp(NOW).unwrap(),
+            rent_data.next_due_timestamp(NOW).unwrap()
+        );
+        assert!(logged(&format!(
+            "[RentShare] Lease end extended by {} seconds to {}",
+            extension,
+            lease_end + extension
+        )));
+    }
+
+    #[test]
+    fn update_rent_amount_treats_a_huge_cooldown_as_never_elapsing() {
+        let program_id = Pubkey::new_unique();
+        let mut rent_data = active_agreement();
+        rent_data.rent_change_cooldown_seconds = u64::MAX;
+        rent_data.last_rent_change_at = NOW - 1_000;
+
+        let mut accounts = vec![
+            TestAccount::agreement(&program_id, &rent_data),
+            TestAccount::wallet(rent_data.payee_pubkey, true),
+            TestAccount::wallet(rent_data.payer_pubkey, true),
+            TestAccount::clock(NOW),
+        ];
+        assert_eq!(
+            process(
+                &program_id,
+                &mut accounts,
+                RentShareInstruction::UpdateRentAmount {
+                    rent_amount: 2 * RENT
+                },
+            ),
+            Err(RentShareError::RentChangeTooSoon.into())
+        );
+    }
+
+    #[test]
+    fn pay_rent_auto_terminates_only_once_the_default_threshold_is_crossed() {
+        let program_id = Pubkey::new_unique();
+        let mut rent_data = active_agreement();
+        rent_data.deposit = 5 * RENT;
+        rent_data.required_deposit = 5 * RENT;
+        let before_default = NOW + Duration::Months.seconds_for(2, NOW).unwrap();
+        let in_default = NOW + Duration::Months.seconds_for(3, NOW).unwrap();
+        assert!(!rent_data.is_in_default(before_default).unwrap());
+        assert!(rent_data.is_in_default(in_default).unwrap());
+
+        for (auto_terminate, now, terminated) in [
+            (true, before_default, false),
+            (true, in_default, true),
+            (false, in_default, false),
+        ] {
+            rent_data.auto_terminate_on_default = auto_terminate;
+            let mut accounts = pay_rent_accounts(&program_id, &rent_data, rent_data.payer_pubkey);
+            accounts[4] = TestAccount::clock(now);
+            process(&program_id, &mut accounts, pay_one_period()).unwrap();
+
+            let agreement = accounts[0].rent_data();
+            assert_eq!(
+                agreement.is_terminated(),
+                terminated,
+                "auto terminate {} at {}",
+                auto_terminate,
+                now
+            );
+            if terminated {
+                assert_eq!(agreement.deposit, 0);
+                assert_eq!(accounts[1].lamports, 105 * RENT);
+                assert_eq!(accounts[2].lamports, 100 * RENT);
+            } else {
+                assert_eq!(agreement.deposit, 5 * RENT);
+                assert_eq!(agreement.payments_made(), 1);
+                assert_eq!(accounts[1].lamports, 101 * RENT);
+            }
+        }
+    }
+
+    #[test]
+    fn auto_terminate_forfeits_the_deposit_to_the_recorded_payee_while_rent_is_assigned() {
+        let program_id = Pubkey::new_unique();
+        let lender = Pubkey::new_unique();
+        let mut rent_data = active_agreement();
+        rent_data.auto_terminate_on_default = true;
+        rent_data.deposit = 5 * RENT;
+        rent_data.required_deposit = 5 * RENT;
+        rent_data.assigned_payee = lender;
+        rent_data.assigned_until_period = 6;
+        rent_data.accrued_interest = RENT;
+        let now = NOW + Duration::Months.seconds_for(3, NOW).unwrap();
+
+        let mut accounts = pay_rent_accounts(&program_id, &rent_data, rent_data.payer_pubkey);
+        accounts[1] = TestAccount::wallet(lender, false);
+        accounts[4] = TestAccount::clock(now);
+        assert_eq!(
+            process(&program_id, &mut accounts, pay_one_period()),
+            Err(ProgramError::NotEnoughAccountKeys)
+        );
+
+        accounts.insert(4, TestAccount::wallet(rent_data.payee_pubkey, false));
+        process(&program_id, &mut accounts, pay_one_period()).unwrap();
+        assert_eq!(accounts[1].lamports, 100 * RENT);
+        assert_eq!(accounts[4].lamports, 105 * RENT);
+        assert_eq!(accounts[0].lamports, 10 * RENT);
+        assert!(accounts[0].rent_data().is_terminated());
+        assert_eq!(accounts[0].rent_data().accrued_interest, 0);
+    }
+
+    fn increase_deposit_accounts(
+        program_id: &Pubkey,
+        rent_data: &RentShareAccount,
+    ) -> Vec<TestAccount> {
+        vec![
+            TestAccount::agreement(program_id, rent_data),
+            TestAccount::wallet(rent_data.payer_pubkey, true),
+            TestAccount::system_program(),
+            TestAccount::clock(NOW),
+        ]
+    }
+
+    #[test]
+    fn close_agreement_waits_for_the_agreement_to_be_settled() {
+        let program_id = Pubkey::new_unique();
+        let mut refund_pending = agreement_in(AgreementStatus::Completed);
+        refund_pending.deposit = 2 * RENT;
+
+        for rent_data in [active_agreement(), refund_pending] {
+            let mut accounts = vec![
+                TestAccount::agreement(&program_id, &rent_data),
+                TestAccount::wallet(rent_data.payer_pubkey, true),
+            ];
+            let lamports = accounts[0].lamports;
+            assert_eq!(
+                process(
+                    &program_id,
+                    &mut accounts,
+                    RentShareInstruction::CloseAgreement {}
+                ),
+                Err(RentShareError::AgreementNotSettled.into())
+            );
+            assert_eq!(accounts[0].lamports, lamports);
+            assert_eq!(accounts[0].rent_data().status, rent_data.status);
+        }
+    }
+
+    #[test]
+    fn close_agreement_returns_the_balance_of_a_settled_agreement_to_the_payer() {
+        let program_id = Pubkey::new_unique();
+        for status in [AgreementStatus::Completed, AgreementStatus::Terminated] {
+            let rent_data = agreement_in(status);
+            let mut accounts = vec![
+                TestAccount::agreement(&program_id, &rent_data),
+                TestAccount::wallet(Pubkey::new_unique(), true),
+            ];
+            assert_eq!(
+                process(
+                    &program_id,
+                    &mut accounts,
+                    RentShareInstruction::CloseAgreement {}
+                ),
+                Err(ProgramError::InvalidAccountData)
+            );
+
+            accounts[1] = TestAccount::wallet(rent_data.payer_pubkey, true);
+            process(
+                &program_id,
+                &mut accounts,
+                RentShareInstruction::CloseAgreement {},
+            )
+            .unwrap();
+            assert_eq!(accounts[0].lamports, 0);
+            assert!(accounts[0].data.iter().all(|byte| *byte == 0));
+            assert_eq!(accounts[1].lamports, 110 * RENT);
+        }
+    }
+
+    #[test]
+    fn set_document_hash_requires_both_parties_while_pending() {
+        let program_id = Pubkey::new_unique();
+        let rent_data = agreement_in(AgreementStatus::PendingAcceptance);
+        let hash = [7; 32];
+
+        let mut accounts = vec![
+            TestAccount::agreement(&program_id, &rent_data),
+            TestAccount::wallet(rent_data.payee_pubkey, true),
+            TestAccount::wallet(rent_data.payer_pubkey, false),
+        ];
+        assert_eq!(
+            process(
+                &program_id,
+                &mut accounts,
+                RentShareInstruction::SetDocumentHash { hash }
+            ),
+            Err(ProgramError::MissingRequiredSignature)
+        );
+
+        accounts[2].is_signer = true;
+        process(
+            &program_id,
+            &mut accounts,
+            RentShareInstruction::SetDocumentHash { hash },
+        )
+        .unwrap();
+        assert_eq!(accounts[0].rent_data().document_hash, hash);
+
+        process(
+            &program_id,
+            &mut accounts,
+            RentShareInstruction::SetDocumentHash { hash: [8; 32] },
+        )
+        .unwrap();
+        assert_eq!(accounts[0].rent_data().document_hash, [8; 32]);
+    }
+
+    #[test]
+    fn set_document_hash_is_frozen_once_active() {
+        let program_id = Pubkey::new_unique();
+        let mut rent_data = active_agreement();
+        rent_data.document_hash = [7; 32];
+
+        let mut accounts = vec![
+            TestAccount::agreement(&program_id, &rent_data),
+            TestAccount::wallet(rent_data.payee_pubkey, true),
+            TestAccount::wallet(rent_data.payer_pubkey, true),
+        ];
+        assert_eq!(
+            process(
+                &program_id,
+                &mut accounts,
+                RentShareInstruction::SetDocumentHash { hash: [8; 32] }
+            ),
+            Err(ProgramError::InvalidAccountData)
+        );
+        assert!(logged("[RentShare] Rent agreement already active"));
+        assert_eq!(accounts[0].rent_data().document_hash, [7; 32]);
+    }
+
+    #[test]
+    fn correct_payee_replaces_the_payee_of_a_pending_agreement() {
+        let program_id = Pubkey::new_unique();
+        let rent_data = agreement_in(AgreementStatus::PendingAcceptance);
+        let new_payee = Pubkey::new_unique();
+
+        let mut accounts = vec![
+            TestAccount::agreement(&program_id, &rent_data),
+            TestAccount::wallet(rent_data.payee_pubkey, true),
+        ];
+        assert_eq!(
+            process(
+                &program_id,
+                &mut accounts,
+                RentShareInstruction::CorrectPayee { new_payee }
+            ),
+            Err(ProgramError::InvalidAccountData)
+        );
+
+        assert!(logged(
+            "[RentShare] Payer must match payer key used during agreement initialization"
+        ));
+
+        accounts[1] = TestAccount::wallet(rent_data.payer_pubkey, true);
+        process(
+            &program_id,
+            &mut accounts,
+            RentShareInstruction::CorrectPayee { new_payee },
+        )
+        .unwrap();
+        assert_eq!(accounts[0].rent_data().payee_pubkey, new_payee);
+    }
+
+    #[test]
+    fn correct_payee_rejects_an_accepted_agreement() {
+        let program_id = Pubkey::new_unique();
+        let rent_data = active_agreement();
+
+        let mut accounts = vec![
+            TestAccount::agreement(&program_id, &rent_data),
+            TestAccount::wallet(rent_data.payer_pubkey, true),
+        ];
+        assert_eq!(
+            process(
+                &program_id,
+                &mut accounts,
+                RentShareInstruction::CorrectPayee {
+                    new_payee: Pubkey::new_unique()
+                }
+            ),
+            Err(ProgramError::InvalidAccountData)
+        );
+        assert!(logged("[RentShare] Rent agreement already active"));
+        assert_eq!(accounts[0].rent_data().payee_pubkey, rent_data.payee_pubkey);
+    }
+
+    #[test]
+    fn final_payment_awaits_settlement_until_the_refund_or_the_grace_ends() {
+        let program_id = Pubkey::new_unique();
+        let mut rent_data = active_agreement();
+        rent_data.remaining_payments = 1;
+        rent_data.deposit = 2 * RENT;
+        rent_data.required_deposit = 2 * RENT;
+        rent_data.final_settlement_grace_seconds = 3_600;
+        let paid_at = rent_data.next_due_timestamp(NOW).unwrap();
+
+        for refund in [false, true] {
+            let mut accounts = pay_rent_accounts(&program_id, &rent_data, rent_data.payer_pubkey);
+            accounts[4] = TestAccount::clock(paid_at);
+            process(&program_id, &mut accounts, pay_one_period()).unwrap();
+            let agreement = accounts[0].rent_data();
+            assert!(agreement.is_awaiting_settlement());
+            assert_eq!(agreement.settlement_started_at, paid_at);
+
+            let mut settle = vec![accounts.remove(0), TestAccount::clock(paid_at + 3_599)];
+            assert_eq!(
+                process(
+                    &program_id,
+                    &mut settle,
+                    RentShareInstruction::CompleteSettlement {}
+                ),
+                Err(RentShareError::AgreementNotSettled.into())
+            );
+
+            if refund {
+                let mut accounts = vec![
+                    settle.remove(0),
+                    TestAccount::wallet(rent_data.payee_pubkey, true),
+                    TestAccount::wallet(rent_data.payer_pubkey, false),
+                    TestAccount::system_program(),
+                    TestAccount::clock(paid_at + 60),
+                ];
+                process(
+                    &program_id,
+                    &mut accounts,
+                    RentShareInstruction::RefundDeposit {},
+                )
+                .unwrap();
+                assert_eq!(accounts[2].lamports, 102 * RENT);
+                settle.insert(0, accounts.remove(0));
+            } else {
+                settle[1] = TestAccount::clock(paid_at + 3_600);
+                process(
+                    &program_id,
+                    &mut settle,
+                    RentShareInstruction::CompleteSettlement {},
+                )
+                .unwrap();
+            }
+            assert_eq!(
+                settle[0].rent_data().status,
+                AgreementStatus::Completed as u8,
+                "refund {}",
+                refund
+            );
+        }
+    }
+
+    #[test]
+    fn return_deposit_itemized_splits_the_deposit_between_the_parties() {
+        let program_id = Pubkey::new_unique();
+        let deduction_codes = [1, 2, 0, 0];
+        for (return_amount, result) in [
+            (4 * RENT, Ok(())),
+            (RENT, Ok(())),
+            (0, Ok(())),
+            (4 * RENT + 1, Err(ProgramError::InvalidArgument)),
+        ] {
+            let mut rent_data = agreement_in(AgreementStatus::Completed);
+            rent_data.deposit = 4 * RENT;
+
+            let mut accounts = vec![
+                TestAccount::agreement(&program_id, &rent_data),
+                TestAccount::wallet(rent_data.payee_pubkey, true),
+                TestAccount::wallet(rent_data.payer_pubkey, false),
+            ];
+            assert_eq!(
+                process(
+                    &program_id,
+                    &mut accounts,
+                    RentShareInstruction::ReturnDepositItemized {
+                        return_amount,
+                        deduction_codes,
+                    },
+                ),
+                result,
+                "return {}",
+                return_amount
+            );
+            if result.is_err() {
+                assert_eq!(accounts[0].rent_data().deposit, 4 * RENT);
+                assert_eq!(accounts[2].lamports, 100 * RENT);
+                continue;
+            }
+
+            let deducted = 4 * RENT - return_amount;
+            assert!(logged(&format!(
+                "[RentShare] Deposit of {} lamports returned to {}, {} lamports deducted for codes {:?}",
+                return_amount, rent_data.payer_pubkey, deducted, deduction_codes
+            )));
+            assert_eq!(accounts[1].lamports, 100 * RENT + deducted);
+            assert_eq!(accounts[2].lamports, 100 * RENT + return_amount);
+            assert_eq!(accounts[0].lamports, 10 * RENT);
+            let rent_data = accounts[0].rent_data();
+            assert_eq!(rent_data.deposit, 0);
+            assert_eq!(rent_data.is_deposit_refunded(), return_amount > 0);
+        }
+    }
+
+    #[test]
+    fn refund_deposit_marks_the_deposit_refunded_and_rejects_a_second_refund() {
+        let program_id = Pubkey::new_unique();
+        let mut rent_data = agreement_in(AgreementStatus::Completed);
+        rent_data.deposit = 2 * RENT;
+
+        let mut accounts = vec![
+            TestAccount::agreement(&program_id, &rent_data),
+            TestAccount::wallet(rent_data.payee_pubkey, true),
+            TestAccount::wallet(rent_data.payer_pubkey, false),
+            TestAccount::system_program(),
+            TestAccount::clock(NOW),
+        ];
+        assert!(!accounts[0].rent_data().is_deposit_refunded());
+        process(
+            &program_id,
+            &mut accounts,
+            RentShareInstruction::RefundDeposit {},
+        )
+        .unwrap();
+        assert!(accounts[0].rent_data().is_deposit_refunded());
+        assert_eq!(accounts[0].rent_data().deposit, 0);
+        assert_eq!(accounts[2].lamports, 102 * RENT);
+
+        assert_eq!(
+            process(
+                &program_id,
+                &mut accounts,
+                RentShareInstruction::RefundDeposit {},
+            ),
+            Err(RentShareError::DepositNotRefundable.into())
+        );
+        assert!(logged("[RentShare] Deposit already refunded"));
+        assert_eq!(accounts[2].lamports, 102 * RENT);
+    }
+
+    #[test]
+    fn refund_deposit_pays_the_non_refundable_portion_to_the_payee() {
+        let program_id = Pubkey::new_unique();
+        let mut rent_data = agreement_in(AgreementStatus::Completed);
+        rent_data.deposit = 5 * RENT;
+        rent_data.non_refundable_deposit = 2 * RENT;
+
+        let mut accounts = vec![
+            TestAccount::agreement(&program_id, &rent_data),
+            TestAccount::wallet(rent_data.payee_pubkey, true),
+            TestAccount::wallet(rent_data.payer_pubkey, false),
+            TestAccount::system_program(),
+            TestAccount::clock(NOW),
+        ];
+        let agreement_lamports = accounts[0].lamports;
+        process(
+            &program_id,
+            &mut accounts,
+            RentShareInstruction::RefundDeposit {},
+        )
+        .unwrap();
+        assert_eq!(accounts[0].lamports, agreement_lamports - 5 * RENT);
+        assert_eq!(accounts[1].lamports, 102 * RENT);
+        assert_eq!(accounts[2].lamports, 103 * RENT);
+        assert!(logged(&format!(
+            "[RentShare] Non-refundable deposit of {} lamports paid to payee",
+            2 * RENT
+        )));
+        assert_eq!(accounts[0].rent_data().deposit, 0);
+    }
+
+    #[test]
+    fn accept_agreement_waits_for_the_required_deposit() {
+        let program_id = Pubkey::new_unique();
+        let mut rent_data = agreement_in(AgreementStatus::PendingAcceptance);
+        rent_data.deposit = RENT;
+        rent_data.required_deposit = 3 * RENT;
+
+        let mut accounts = vec![
+            TestAccount::agreement(&program_id, &rent_data),
+            TestAccount::wallet(rent_data.payee_pubkey, true),
+            TestAccount::clock(NOW),
+        ];
+        assert_eq!(
+            process(
+                &program_id,
+                &mut accounts,
+                RentShareInstruction::AcceptAgreement {}
+            ),
+            Err(RentShareError::DepositNotFullyCollected.into())
+        );
+        assert!(logged(&format!(
+            "[RentShare] Deposit of {} lamports is below the required deposit of {}",
+            RENT,
+            3 * RENT
+        )));
+
+        let mut deposit = increase_deposit_accounts(&program_id, &rent_data);
+        deposit[0] = accounts.remove(0);
+        process(
+            &program_id,
+            &mut deposit,
+            RentShareInstruction::IncreaseDeposit { amount: 2 * RENT },
+        )
+        .unwrap();
+        assert_eq!(deposit[0].rent_data().deposit, 3 * RENT);
+
+        accounts.insert(0, deposit.remove(0));
+        process(
+            &program_id,
+            &mut accounts,
+            RentShareInstruction::AcceptAgreement {},
+        )
+        .unwrap();
+        assert!(accounts[0].rent_data().is_active());
+    }
+
+    #[test]
+    fn increase_deposit_moves_the_amount_into_escrow() {
+        let program_id = Pubkey::new_unique();
+        let mut rent_data = active_agreement();
+        rent_data.deposit = 2 * RENT;
+
+        let mut accounts = increase_deposit_accounts(&program_id, &rent_data);
+        process(
+            &program_id,
+            &mut accounts,
+            RentShareInstruction::IncreaseDeposit { amount: RENT },
+        )
+        .unwrap();
+        assert_eq!(accounts[0].rent_data().deposit, 3 * RENT);
+        assert_eq!(accounts[0].lamports, 13 * RENT);
+        assert_eq!(accounts[1].lamports, 99 * RENT);
+    }
+
+    #[test]
+    fn increase_deposit_rejects_an_ended_agreement() {
+        let program_id = Pubkey::new_unique();
+        let mut accounts =
+            increase_deposit_accounts(&program_id, &agreement_in(AgreementStatus::Terminated));
+        assert_eq!(
+            process(
+                &program_id,
+                &mut accounts,
+                RentShareInstruction::IncreaseDeposit { amount: RENT },
+            ),
+            Err(RentShareError::RentAgreementTerminated.into())
+        );
+        assert_eq!(accounts[1].lamports, 100 * RENT);
+    }
+
+    #[test]
+    fn verify_escrow_requires_the_deposit_and_escrowed_rent() {
+        let program_id = Pubkey::new_unique();
+        let mut rent_data = active_agreement();
+        rent_data.deposit = 2 * RENT;
+        rent_data.escrowed_rent = RENT;
+        let required_balance = Rent::default().minimum_balance(RentShareAccount::LEN) + 3 * RENT;
+
+        for (lamports, result, log) in [
+            (required_balance, Ok(()), "Escrow solvent"),
+            (
+                required_balance - 1,
+                Err(RentShareError::EscrowInsolvent.into()),
+                "Escrow insolvent",
+            ),
+        ] {
+            let mut agreement = TestAccount::agreement(&program_id, &rent_data);
+            agreement.lamports = lamports;
+            agreement.is_writable = false;
+            let mut accounts = vec![agreement, TestAccount::rent_sysvar(&Rent::default())];
+            assert_eq!(
+                process(
+                    &program_id,
+                    &mut accounts,
+                    RentShareInstruction::VerifyEscrow {}
+                ),
+                result
+            );
+            assert!(logged(&format!(
+                "[RentShare] {}. Balance: {} Required: {}",
+                log, lamports, required_balance
+            )));
+            assert_eq!(accounts[0].lamports, lamports);
+        }
+    }
+
+    #[test]
+    fn rent_exemption_checks_reject_an_over_large_agreement_account() {
+        let program_id = Pubkey::new_unique();
+        let rent_data = active_agreement();
+        for (data_len, result) in [
+            (MAX_AGREEMENT_ACCOUNT_DATA_LEN, Ok(())),
+            (
+                MAX_AGREEMENT_ACCOUNT_DATA_LEN + 1,
+                Err(ProgramError::InvalidAccountData),
+            ),
+        ] {
+            let mut agreement = TestAccount::agreement(&program_id, &rent_data);
+            agreement.data.resize(data_len, 0);
+            agreement.lamports = Rent::default().minimum_balance(data_len);
+            agreement.is_writable = false;
+            let mut accounts = vec![agreement, TestAccount::rent_sysvar(&Rent::default())];
+            assert_eq!(
+                process(
+                    &program_id,
+                    &mut accounts,
+                    RentShareInstruction::VerifyEscrow {}
+                ),
+                result,
+                "{} bytes",
+                data_len
+            );
+        }
+
+        let terms = monthly_terms();
+        let mut accounts = initialize_accounts(&program_id, &terms, 100 * RENT);
+        accounts[0].owner = program_id;
+        accounts[0].data = vec![0; MAX_AGREEMENT_ACCOUNT_DATA_LEN + 1];
+        assert_eq!(
+            process(
+                &program_id,
+                &mut accounts,
+                RentShareInstruction::InitializeRentContract { terms },
+            ),
+            Err(ProgramError::InvalidAccountData)
+        );
+        assert!(logged(&format!(
+            "[RentShare] Rent agreement account data length {} exceeds the maximum of {}",
+            MAX_AGREEMENT_ACCOUNT_DATA_LEN + 1,
+            MAX_AGREEMENT_ACCOUNT_DATA_LEN
+        )));
+    }
+
+    #[test]
+    fn top_up_rent_exemption_restores_an_underfunded_agreement() {
+        let program_id = Pubkey::new_unique();
+        let mut rent_data = active_agreement();
+        rent_data.deposit = 2 * RENT;
+        let required_balance =
+            Rent::default().minimum_balance(RentShareAccount::LEN) + rent_data.deposit;
+
+        let mut agreement = TestAccount::agreement(&program_id, &rent_data);
+        agreement.lamports = required_balance - 1_000;
+        agreement.is_writable = false;
+        let mut accounts = vec![agreement, TestAccount::rent_sysvar(&Rent::default())];
+        assert_eq!(
+            process(
+                &program_id,
+                &mut accounts,
+                RentShareInstruction::VerifyEscrow {}
+            ),
+            Err(RentShareError::EscrowInsolvent.into())
+        );
+
+        let mut agreement = accounts.remove(0);
+        agreement.is_writable = true;
+        let mut accounts = vec![
+            agreement,
+            TestAccount::wallet(Pubkey::new_unique(), true),
+            TestAccount::system_program(),
+            TestAccount::rent_sysvar(&Rent::default()),
+        ];
+        process(
+            &program_id,
+            &mut accounts,
+            RentShareInstruction::TopUpRentExemption {},
+        )
+        .unwrap();
+        assert_eq!(accounts[0].lamports, required_balance);
+        assert_eq!(accounts[1].lamports, 100 * RENT - 1_000);
+
+        let mut agreement = accounts.remove(0);
+        agreement.is_writable = false;
+        let mut accounts = vec![agreement, TestAccount::rent_sysvar(&Rent::default())];
+        process(
+            &program_id,
+            &mut accounts,
+            RentShareInstruction::VerifyEscrow {},
+        )
+        .unwrap();
+
+        // The topped up agreement is written by the next payment
+        let mut agreement = accounts.remove(0);
+        agreement.is_writable = true;
+        let mut accounts = vec![
+            agreement,
+            TestAccount::wallet(rent_data.payee_pubkey, false),
+            TestAccount::wallet(rent_data.payer_pubkey, true),
+            TestAccount::system_program(),
+            TestAccount::clock(NOW),
+        ];
+        process(&program_id, &mut accounts, pay_one_period()).unwrap();
+        assert_eq!(accounts[0].rent_data().remaining_payments, 11);
+    }
+
+    #[test]
+    fn view_agreement_logs_the_payments_made() {
+        let program_id = Pubkey::new_unique();
+        let mut rent_data = active_agreement();
+        rent_data.remaining_payments = 7;
+
+        let mut accounts = view_accounts(&program_id, &rent_data);
+        process(
+            &program_id,
+            &mut accounts,
+            RentShareInstruction::ViewAgreement {},
+        )
+        .unwrap();
+        assert!(logged("[RentShare] Payments made: 5 of 12"));
+    }
+
+    #[test]
+    fn read_instructions_charge_the_read_fee_only_when_configured() {
+        let program_id = Pubkey::new_unique();
+        let platform = Pubkey::new_unique();
+        let mut rent_data = active_agreement();
+        rent_data.platform_pubkey = platform;
+
+        let mut accounts = view_accounts(&program_id, &rent_data);
+        process(
+            &program_id,
+            &mut accounts,
+            RentShareInstruction::ViewAgreement {},
+        )
+        .unwrap();
+        assert!(invocations().is_empty());
+
+        rent_data.read_fee_lamports = 5_000;
+        let mut accounts = view_accounts(&program_id, &rent_data);
+        assert_eq!(
+            process(
+                &program_id,
+                &mut accounts,
+                RentShareInstruction::ViewAgreement {}
+            ),
+            Err(ProgramError::NotEnoughAccountKeys)
+        );
+
+        for instruction in [
+            RentShareInstruction::ViewAgreement {},
+            RentShareInstruction::CheckReminders {},
+        ] {
+            let mut accounts = view_accounts(&program_id, &rent_data);
+            accounts.splice(
+                1..1,
+                [
+                    TestAccount::wallet(Pubkey::new_unique(), true),
+                    TestAccount::wallet(platform, false),
+                    TestAccount::system_program(),
+                ],
+            );
+            process(&program_id, &mut accounts, instruction).unwrap();
+            assert_eq!(accounts[1].lamports, 100 * RENT - 5_000);
+            assert_eq!(accounts[2].lamports, 100 * RENT + 5_000);
+            assert!(logged(&format!(
+                "[RentShare] Read fee of 5000 lamports paid to {}",
+                platform
+            )));
+        }
+    }
+
+    #[test]
+    fn check_reminders_logs_the_reminder_and_overdue_events() {
+        let program_id = Pubkey::new_unique();
+        let mut rent_data = active_agreement();
+        rent_data.remaining_payments = 11;
+        rent_data.reminder_lead_seconds = 60;
+        let due_at = NOW + Duration::Months.seconds_for(1, NOW).unwrap();
+
+        for (now, log) in [
+            (
+                due_at - 61,
+                format!("[RentShare] No rent payment reminder at {}", due_at - 61),
+            ),
+            (
+                due_at - 60,
+                format!("[RentShare] EVENT:REMINDER:{}:{}", due_at, due_at - 60),
+            ),
+            (
+                due_at + 1,
+                format!("[RentShare] EVENT:OVERDUE:{}:{}", due_at, due_at + 1),
+            ),
+        ] {
+            let mut accounts = view_accounts(&program_id, &rent_data);
+            accounts[1] = TestAccount::clock(now);
+            process(
+                &program_id,
+                &mut accounts,
+                RentShareInstruction::CheckReminders {},
+            )
+            .unwrap();
+            assert!(logged(&log), "{}", log);
+        }
+    }
+
+    #[test]
+    fn current_timestamp_prefers_the_clock_syscall_over_the_clock_account() {
+        let program_id = Pubkey::new_unique();
+        let rent_data = active_agreement();
+
+        let mut accounts = view_accounts(&program_id, &rent_data);
+        process_in(
+            Runtime {
+                clock: Some(NOW + 60),
+                ..Runtime::default()
+            },
+            &program_id,
+            &mut accounts,
+            RentShareInstruction::ViewAgreement {},
+        )
+        .unwrap();
+        assert!(logged("[RentShare] Seconds until due: -60"));
+
+        accounts.pop();
+        process_in(
+            Runtime {
+                clock: Some(NOW + 60),
+                ..Runtime::default()
+            },
+            &program_id,
+            &mut accounts,
+            RentShareInstruction::ViewAgreement {},
+        )
+        .unwrap();
+        assert!(logged("[RentShare] Seconds until due: -60"));
+    }
+
+    #[test]
+    fn current_timestamp_falls_back_to_the_clock_account() {
+        let program_id = Pubkey::new_unique();
+        let rent_data = active_agreement();
+
+        let mut accounts = view_accounts(&program_id, &rent_data);
+        accounts[1] = TestAccount::clock(NOW + 30);
+        process(
+            &program_id,
+            &mut accounts,
+            RentShareInstruction::ViewAgreement {},
+        )
+        .unwrap();
+        assert!(logged("[RentShare] Seconds until due: -30"));
+
+        accounts.pop();
+        assert_eq!(
+            process(
+                &program_id,
+                &mut accounts,
+                RentShareInstruction::ViewAgreement {}
+            ),
+            Err(RentShareError::ClockUnavailable.into())
+        );
+        assert!(logged(
+            "[RentShare] Clock unavailable. Pass the clock sysvar account"
+        ));
+    }
+
+    #[test]
+    fn settlement_report_totals_a_lease_paid_once_late() {
+        let program_id = Pubkey::new_unique();
+        let mut rent_data = active_agreement();
+        rent_data.duration = 2;
+        rent_data.remaining_payments = 2;
+        rent_data.max_prepay_periods = 2;
+        rent_data.late_fee_bps = 1_000;
+        rent_data.deposit = 2 * RENT;
+        rent_data.required_deposit = 2 * RENT;
+        let late_fee = RENT / 10;
+
+        let mut accounts = pay_rent_accounts(&program_id, &rent_data, rent_data.payer_pubkey);
+        let mut view = view_accounts(&program_id, &rent_data);
+        assert_eq!(
+            process(
+                &program_id,
+                &mut view,
+                RentShareInstruction::GenerateSettlementReport {}
+            ),
+            Err(RentShareError::AgreementNotSettled.into())
+        );
+
+        // A period is overdue once it has fully elapsed, so the second is late after the lease end
+        let lease_end = NOW + Duration::Months.seconds_for(2, NOW).unwrap();
+        for (now, rent_amount) in [
+            (NOW, RENT),
+            (lease_end + 10 * 24 * 60 * 60, RENT + late_fee),
+        ] {
+            accounts[4] = TestAccount::clock(now);
+            process(
+                &program_id,
+                &mut accounts,
+                RentShareInstruction::PayRent {
+                    rent_amount,
+                    periods: 1,
+                },
+            )
+            .unwrap();
+        }
+        assert_eq!(accounts[1].lamports, 100 * RENT + 2 * RENT + late_fee);
+
+        let mut view = vec![accounts.remove(0), TestAccount::clock(NOW)];
+        view[0].is_writable = false;
+        let data = view[0].data.clone();
+        process(
+            &program_id,
+            &mut view,
+            RentShareInstruction::GenerateSettlementReport {},
+        )
+        .unwrap();
+        assert!(logged(&format!(
+            "[RentShare] REPORT:status=Completed;payments_made=2;total_rent_paid={};total_late_fees_paid={};deposit_status=Held;deposit_held={};accrued_interest=0;termination_penalty_paid=0",
+            2 * RENT,
+            late_fee,
+            2 * RENT
+        )));
+        assert_eq!(view[0].data, data);
+    }
+
+    #[test]
+    fn pay_rent_rejects_a_program_owned_payee_account() {
+        let program_id = Pubkey::new_unique();
+        let rent_data = active_agreement();
+
+        let mut accounts = pay_rent_accounts(&program_id, &rent_data, rent_data.payer_pubkey);
+        let mut agreement_as_payee = TestAccount::agreement(&program_id, &rent_data);
+        agreement_as_payee.key = accounts[0].key;
+        accounts[1] = agreement_as_payee;
+        // The agreement passed as the payee is rejected as an alias before its owner is checked
+        assert_eq!(
+            process(&program_id, &mut accounts, pay_one_period()),
+            Err(ProgramError::InvalidArgument)
+        );
+        assert!(logged(
+            "[RentShare] Rent agreement account must differ from the payer and payee"
+        ));
+
+        let other_program = Pubkey::new_unique();
+        accounts[1] = TestAccount::wallet(rent_data.payee_pubkey, false);
+        accounts[1].owner = other_program;
+        assert_eq!(
+            process(&program_id, &mut accounts, pay_one_period()),
+            Err(ProgramError::InvalidAccountData)
+        );
+        assert!(logged(&format!(
+            "[RentShare] Payee account must be a system owned wallet. Owner: {}",
+            other_program
+        )));
+        assert_eq!(accounts[1].lamports, 100 * RENT);
+        assert_eq!(accounts[2].lamports, 100 * RENT);
+    }
+
+    #[test]
+    fn pay_rent_rejects_the_agreement_aliased_as_either_party() {
+        let program_id = Pubkey::new_unique();
+        let rent_data = active_agreement();
+
+        for party in [1, 2] {
+            let mut accounts = pay_rent_accounts(&program_id, &rent_data, rent_data.payer_pubkey);
+            let mut alias = TestAccount::agreement(&program_id, &rent_data);
+            alias.key = accounts[0].key;
+            alias.is_signer = accounts[party].is_signer;
+            accounts[party] = alias;
+            assert_eq!(
+                process(&program_id, &mut accounts, pay_one_period()),
+                Err(ProgramError::InvalidArgument),
+                "account {}",
+                party
+            );
+            assert!(logged(
+                "[RentShare] Rent agreement account must differ from the payer and payee"
+            ));
+            assert_eq!(accounts[0].rent_data().remaining_payments, 12);
+        }
+    }
+
+    #[test]
+    fn pay_rent_treats_an_active_agreement_with_nothing_left_to_pay_as_complete() {
+        let program_id = Pubkey::new_unique();
+        let rent_data = active_agreement();
+        let mut accounts = pay_rent_accounts(&program_id, &rent_data, rent_data.payer_pubkey);
+
+        // `save` refuses to write the inconsistent state, so it is serialized directly
+        let mut inconsistent = accounts[0].rent_data();
+        inconsistent.remaining_payments = 0;
+        accounts[0].data = borsh::BorshSerialize::try_to_vec(&inconsistent).unwrap();
+        assert_eq!(
+            process(&program_id, &mut accounts, pay_one_period()),
+            Err(RentShareError::RentAlreadyPaidInFull.into())
+        );
+        assert!(logged(
+            "[RentShare] Active agreement has no remaining payments. Treating it as complete"
+        ));
+        assert_eq!(accounts[0].rent_data().remaining_payments, 0);
+        assert_eq!(accounts[1].lamports, 100 * RENT);
+        assert_eq!(accounts[2].lamports, 100 * RENT);
+    }
+
+    #[test]
+    fn pay_rent_notifies_the_observer_program() {
+        let program_id = Pubkey::new_unique();
+        let observer = Pubkey::new_unique();
+        let mut rent_data = active_agreement();
+        rent_data.observer_program = observer;
+
+        let with_observer = |rent_data: &RentShareAccount| {
+            let mut accounts = pay_rent_accounts(&program_id, rent_data, rent_data.payer_pubkey);
+            accounts.insert(4, TestAccount::wallet(rent_data.payee_pubkey, false));
+            accounts.insert(5, TestAccount::program(observer));
+            accounts
+        };
+
+        let mut accounts = with_observer(&rent_data);
+        process(&program_id, &mut accounts, pay_one_period()).unwrap();
+        let notification = PaymentNotification {
+            rent_paid: RENT,
+            late_fees: 0,
+            periods: 1,
+            remaining_payments: 11,
+            paid_at: NOW,
+        };
+        let notified: Vec<Instruction> = invocations()
+            .into_iter()
+            .filter(|instruction| instruction.program_id == observer)
+            .collect();
+        assert_eq!(notified.len(), 1);
+        assert_eq!(notified[0].data, notification.pack());
+        assert_eq!(notified[0].accounts[0].pubkey, accounts[0].key);
+        assert_eq!(notified[0].accounts[1].pubkey, rent_data.payer_pubkey);
+
+        // A failing observer fails the payment
+        let mut accounts = with_observer(&rent_data);
+        assert_eq!(
+            process_in(
+                Runtime {
+                    failing_programs: vec![observer],
+                    ..Runtime::default()
+                },
+                &program_id,
+                &mut accounts,
+                pay_one_period()
+            ),
+            Err(ProgramError::Custom(0))
+        );
+
+        // Without the observer passed, the notification is skipped unless it is required
+        let mut accounts = pay_rent_accounts(&program_id, &rent_data, rent_data.payer_pubkey);
+        process(&program_id, &mut accounts, pay_one_period()).unwrap();
+        assert!(logged(&format!(
+            "[RentShare] Observer program {} not passed. Skipping payment notification",
+            observer
+        )));
+
+        rent_data.observer_required = true;
+        let mut accounts = pay_rent_accounts(&program_id, &rent_data, rent_data.payer_pubkey);
+        assert_eq!(
+            process(&program_id, &mut accounts, pay_one_period()),
+            Err(ProgramError::NotEnoughAccountKeys)
+        );
+    }
+
+    #[test]
+    fn freeze_blocks_payments_until_the_admin_unfreezes() {
+        let program_id = Pubkey::new_unique();
+        let admin = Pubkey::new_unique();
+        let mut rent_data = active_agreement();
+        rent_data.admin_pubkey = admin;
+
+        let mut admin_accounts = vec![
+            TestAccount::agreement(&program_id, &rent_data),
+            TestAccount::wallet(rent_data.payee_pubkey, true),
+        ];
+        assert_eq!(
+            process(
+                &program_id,
+                &mut admin_accounts,
+                RentShareInstruction::Freeze {}
+            ),
+            Err(ProgramError::InvalidAccountData)
+        );
+
+        admin_accounts[1] = TestAccount::wallet(admin, true);
+        process(
+            &program_id,
+            &mut admin_accounts,
+            RentShareInstruction::Freeze {},
+        )
+        .unwrap();
+        assert!(admin_accounts[0].rent_data().frozen);
+
+        let mut accounts = pay_rent_accounts(&program_id, &rent_data, rent_data.payer_pubkey);
+        accounts[0] = admin_accounts.remove(0);
+        assert_eq!(
+            process(&program_id, &mut accounts, pay_one_period()),
+            Err(RentShareError::AgreementFrozen.into())
+        );
+        assert_eq!(accounts[2].lamports, 100 * RENT);
+
+        // Read-only instructions still work while frozen
+        let mut view = vec![accounts.remove(0), TestAccount::clock(NOW)];
+        view[0].is_writable = false;
+        process(
+            &program_id,
+            &mut view,
+            RentShareInstruction::ViewAgreement {},
+        )
+        .unwrap();
+
+        let mut admin_accounts = vec![view.remove(0), TestAccount::wallet(admin, true)];
+        admin_accounts[0].is_writable = true;
+        process(
+            &program_id,
+            &mut admin_accounts,
+            RentShareInstruction::Unfreeze {},
+        )
+        .unwrap();
+        accounts.insert(0, admin_accounts.remove(0));
+        process(&program_id, &mut accounts, pay_one_period()).unwrap();
+        assert_eq!(accounts[0].rent_data().remaining_payments, 11);
+    }
+
+    #[test]
+    fn pay_rent_rejects_every_status_but_active() {
+        let program_id = Pubkey::new_unique();
+        for (status, error) in [
+            (
+                AgreementStatus::Uninitialized,
+                ProgramError::UninitializedAccount,
+            ),
+            (
+                AgreementStatus::PendingAcceptance,
+                RentShareError::AgreementNotAccepted.into(),
+            ),
+            (
+                AgreementStatus::Completed,
+                RentShareError::RentAlreadyPaidInFull.into(),
+            ),
+            (
+                AgreementStatus::AwaitingSettlement,
+                RentShareError::RentAlreadyPaidInFull.into(),
+            ),
+            (
+                AgreementStatus::Terminated,
+                RentShareError::RentAgreementTerminated.into(),
+            ),
+        ] {
+            let rent_data = agreement_in(status);
+            let mut accounts = pay_rent_accounts(&program_id, &rent_data, rent_data.payer_pubkey);
+            assert_eq!(
+                process(&program_id, &mut accounts, pay_one_period()),
+                Err(error),
+                "{:?}",
+                status
+            );
+            assert_eq!(accounts[2].lamports, 100 * RENT, "{:?}", status);
+        }
+    }
+
+    #[test]
+    fn pay_rent_rejects_an_agreement_created_in_the_future() {
+        let program_id = Pubkey::new_unique();
+        let mut rent_data = active_agreement();
+        rent_data.created_at = NOW + 86_400;
+        rent_data.accepted_at = NOW + 86_400;
+
+        let mut accounts = pay_rent_accounts(&program_id, &rent_data, rent_data.payer_pubkey);
+        assert_eq!(
+            process(&program_id, &mut accounts, pay_one_period()),
+            Err(RentShareError::InvalidTimestamp.into())
+        );
+        assert_eq!(accounts[2].lamports, 100 * RENT);
+    }
+
+    #[test]
+    fn pay_rent_accepts_a_payer_signed_for_by_the_calling_program() {
+        let program_id = Pubkey::new_unique();
+        let caller_program_id = Pubkey::new_unique();
+        let (payer, bump) = Pubkey::find_program_address(&[b"tenant"], &caller_program_id);
+        let mut rent_data = active_agreement();
+        rent_data.payer_pubkey = payer;
+
+        let mut accounts = pay_rent_accounts(&program_id, &rent_data, payer);
+        accounts[2].is_signer = false;
+        accounts.push(TestAccount::program(program_id));
+        let instruction = crate::instruction_builder::pay_rent_many_ix(
+            &program_id,
+            &accounts[0].key,
+            &payer,
+            &rent_data.payee_pubkey,
+            &system_program::id(),
+            RENT,
+            1,
+        )
+        .unwrap();
+        let infos: Vec<AccountInfo> = accounts.iter_mut().map(TestAccount::info).collect();
+        // The clock account is not part of the instruction, so the clock syscall serves the time
+        let pay = |signers_seeds: &[&[&[u8]]]| {
+            let runtime = Runtime {
+                clock: Some(NOW),
+                ..Runtime::default()
+            };
+            run_as(&caller_program_id, &program_id, runtime, || {
+                invoke_signed(&instruction, &infos, signers_seeds)
+            })
+        };
+
+        assert_eq!(pay(&[]), Err(ProgramError::MissingRequiredSignature));
+        pay(&[&[b"tenant", &[bump]]]).unwrap();
+        drop(infos);
+
+        assert_eq!(accounts[0].rent_data().remaining_payments, 11);
+        assert_eq!(accounts[1].lamports, 101 * RENT);
+        assert_eq!(accounts[2].lamports, 99 * RENT);
+    }
+
+    #[test]
+    fn pay_rent_routes_an_overpayment_to_the_deposit_only_when_enabled() {
+        let program_id = Pubkey::new_unique();
+        let overpayment = RENT / 4;
+        for overpay_to_deposit in [false, true] {
+            let mut rent_data = active_agreement();
+            rent_data.deposit = 2 * RENT;
+            rent_data.required_deposit = 2 * RENT;
+            rent_data.overpay_to_deposit = overpay_to_deposit;
+
+            let mut accounts = pay_rent_accounts(&program_id, &rent_data, rent_data.payer_pubkey);
+            let result = process(
+                &program_id,
+                &mut accounts,
+                RentShareInstruction::PayRent {
+                    rent_amount: RENT + overpayment,
+                    periods: 1,
+                },
+            );
+
+            if overpay_to_deposit {
+                result.unwrap();
+                assert!(logged(&format!(
+                    "[RentShare] Overpayment of {} lamports added to the deposit",
+                    overpayment
+                )));
+                assert_eq!(accounts[0].rent_data().deposit, 2 * RENT + overpayment);
+                assert_eq!(accounts[0].rent_data().remaining_payments, 11);
+                assert_eq!(accounts[0].lamports, 12 * RENT + overpayment);
+                assert_eq!(accounts[1].lamports, 101 * RENT);
+                assert_eq!(accounts[2].lamports, 99 * RENT - overpayment);
+            } else {
+                assert_eq!(
+                    result,
+                    Err(RentShareError::RentPaymentAmountMismatch.into())
+                );
+                assert_eq!(accounts[0].rent_data().deposit, 2 * RENT);
+                assert_eq!(accounts[2].lamports, 100 * RENT);
+            }
+        }
+    }
+
+    #[test]
+    fn pay_rent_replenishes_the_deposit_before_paying_rent() {
+        let program_id = Pubkey::new_unique();
+        let mut rent_data = active_agreement();
+        rent_data.deposit = RENT / 2;
+        rent_data.required_deposit = 2 * RENT;
+        rent_data.replenish_deposit_first = true;
+
+        let mut accounts = pay_rent_accounts(&program_id, &rent_data, rent_data.payer_pubkey);
+        assert_eq!(
+            process(&program_id, &mut accounts, pay_one_period()),
+            Err(RentShareError::RentPaymentAmountMismatch.into())
+        );
+
+        process(
+            &program_id,
+            &mut accounts,
+            RentShareInstruction::PayRent {
+                rent_amount: RENT + 3 * RENT / 2,
+                periods: 1,
+            },
+        )
+        .unwrap();
+        let rent_data = accounts[0].rent_data();
+        assert_eq!(rent_data.deposit, 2 * RENT);
+        assert_eq!(rent_data.total_rent_paid, RENT);
+        assert_eq!(rent_data.remaining_payments, 11);
+        assert_eq!(accounts[0].lamports, 10 * RENT + 2 * RENT);
+        assert_eq!(accounts[1].lamports, 101 * RENT);
+        assert_eq!(accounts[2].lamports, 100 * RENT - RENT - 3 * RENT / 2);
+    }
+
+    #[test]
+    fn pay_rent_zeroes_the_tail_of_an_oversized_agreement_account() {
+        let program_id = Pubkey::new_unique();
+        let rent_data = active_agreement();
+
+        let mut accounts = pay_rent_accounts(&program_id, &rent_data, rent_data.payer_pubkey);
+        accounts[0].data.resize(RentShareAccount::LEN + 64, 0xff);
+        process(&program_id, &mut accounts, pay_one_period()).unwrap();
+
+        assert_eq!(accounts[0].data.len(), RentShareAccount::LEN + 64);
+        assert!(accounts[0].data[RentShareAccount::LEN..]
+            .iter()
+            .all(|byte| *byte == 0));
+        assert_eq!(accounts[0].rent_data().remaining_payments, 11);
+    }
+
+    #[test]
+    fn pay_rent_prepays_several_periods_with_a_single_transfer() {
+        let program_id = Pubkey::new_unique();
+        let rent_data = active_agreement();
+
+        let mut accounts = pay_rent_accounts(&program_id, &rent_data, rent_data.payer_pubkey);
+        process(
+            &program_id,
+            &mut accounts,
+            RentShareInstruction::PayRent {
+                rent_amount: 3 * RENT,
+                periods: 3,
+            },
+        )
+        .unwrap();
+
+        let invocations = invocations();
+        assert_eq!(invocations.len(), 1);
+        assert_eq!(invocations[0].program_id, system_program::id());
+        assert_eq!(
+            limited_deserialize(&invocations[0].data, 1_024),
+            Ok(SystemInstruction::Transfer { lamports: 3 * RENT })
+        );
+        assert_eq!(accounts[0].rent_data().remaining_payments, 9);
+        assert_eq!(accounts[1].lamports, 103 * RENT);
+        assert_eq!(accounts[2].lamports, 97 * RENT);
+    }
+
+    #[test]
+    fn pay_rent_prepays_up_to_the_prepay_limit() {
+        let program_id = Pubkey::new_unique();
+        let mut rent_data = active_agreement();
+        rent_data.max_prepay_periods = 3;
+
+        let mut accounts = pay_rent_accounts(&program_id, &rent_data, rent_data.payer_pubkey);
+        assert_eq!(
+            process(
+                &program_id,
+                &mut accounts,
+                RentShareInstruction::PayRent {
+                    rent_amount: 4 * RENT,
+                    periods: 4,
+                },
+            ),
+            Err(RentShareError::PrepayLimitExceeded.into())
+        );
+        assert_eq!(accounts[2].lamports, 100 * RENT);
+
+        process(
+            &program_id,
+            &mut accounts,
+            RentShareInstruction::PayRent {
+                rent_amount: 3 * RENT,
+                periods: 3,
+            },
+        )
+        .unwrap();
+        assert_eq!(accounts[0].rent_data().remaining_payments, 9);
+        assert_eq!(accounts[2].lamports, 97 * RENT);
+    }
+
+    #[test]
+    fn initialize_defaults_the_prepay_limit_to_the_duration() {
+        let program_id = Pubkey::new_unique();
+        for (max_prepay_periods, stored) in [(0, 12), (4, 4)] {
+            let terms = AgreementTerms {
+                max_prepay_periods,
+                ..monthly_terms()
+            };
+
+            let mut accounts = initialize_accounts(&program_id, &terms, 0);
+            process(
+                &program_id,
+                &mut accounts,
+                RentShareInstruction::InitializeRentContract { terms },
+            )
+            .unwrap();
+            assert_eq!(accounts[0].rent_data().max_prepay_periods, stored);
+        }
+    }
+
+    #[test]
+    fn prepay_whole_lease_is_not_capped_by_the_prepay_limit() {
+        let program_id = Pubkey::new_unique();
+        let mut rent_data = active_agreement();
+        rent_data.max_prepay_periods = 3;
+
+        let mut accounts = pay_rent_accounts(&program_id, &rent_data, rent_data.payer_pubkey);
+        assert_eq!(
+            process(
+                &program_id,
+                &mut accounts,
+                RentShareInstruction::PayRent {
+                    rent_amount: 12 * RENT,
+                    periods: 12,
+                },
+            ),
+            Err(RentShareError::PrepayLimitExceeded.into())
+        );
+
+        process(
+            &program_id,
+            &mut accounts,
+            RentShareInstruction::PrepayWholeLease {},
+        )
+        .unwrap();
+        assert_eq!(accounts[0].rent_data().remaining_payments, 0);
+        assert_eq!(accounts[1].lamports, 112 * RENT);
+        assert_eq!(accounts[2].lamports, 88 * RENT);
+    }
+
+    #[test]
+    fn prepay_whole_lease_pays_each_side_of_the_rent_assignment_to_its_payee() {
+        let program_id = Pubkey::new_unique();
+        let lender = Pubkey::new_unique();
+        let mut rent_data = active_agreement();
+        rent_data.assigned_payee = lender;
+        rent_data.assigned_until_period = 4;
+
+        let mut accounts = pay_rent_accounts(&program_id, &rent_data, rent_data.payer_pubkey);
+        accounts[1] = TestAccount::wallet(lender, false);
+        accounts.insert(4, TestAccount::wallet(rent_data.payee_pubkey, false));
+        process(
+            &program_id,
+            &mut accounts,
+            RentShareInstruction::PrepayWholeLease {},
+        )
+        .unwrap();
+
+        let rent_data = accounts[0].rent_data();
+        assert_eq!(rent_data.remaining_payments, 0);
+        assert_eq!(rent_data.total_rent_paid, 12 * RENT);
+        assert_eq!(accounts[1].lamports, 104 * RENT);
+        assert_eq!(accounts[2].lamports, 88 * RENT);
+        assert_eq!(accounts[4].lamports, 108 * RENT);
+    }
+
+    #[test]
+    fn prepay_whole_lease_completes_a_partially_paid_lease() {
+        let program_id = Pubkey::new_unique();
+        let mut rent_data = active_agreement();
+        rent_data.deposit = 2 * RENT;
+        rent_data.remaining_payments = 7;
+        rent_data.total_rent_paid = 5 * RENT;
+        let now = NOW + Duration::Months.seconds_for(4, NOW).unwrap();
+
+        let mut accounts = pay_rent_accounts(&program_id, &rent_data, rent_data.payer_pubkey);
+        accounts[4] = TestAccount::clock(now);
+        process(
+            &program_id,
+            &mut accounts,
+            RentShareInstruction::PrepayWholeLease {},
+        )
+        .unwrap();
+
+        let rent_data = accounts[0].rent_data();
+        assert_eq!(rent_data.status, AgreementStatus::Completed as u8);
+        assert_eq!(rent_data.remaining_payments, 0);
+        assert_eq!(rent_data.total_rent_paid, 12 * RENT);
+        assert_eq!(accounts[1].lamports, 107 * RENT);
+        assert_eq!(accounts[2].lamports, 93 * RENT);
+        // The deposit stays held until the payee refunds it
+        assert_eq!(rent_data.deposit, 2 * RENT);
+        assert!(!rent_data.deposit_refunded);
+        assert_eq!(accounts[0].lamports, 12 * RENT);
+    }
+
+    fn terminate_accounts(program_id: &Pubkey, rent_data: &RentShareAccount) -> Vec<TestAccount> {
+        vec![
+            TestAccount::agreement(program_id, rent_data),
+            TestAccount::wallet(rent_data.payer_pubkey, true),
+            TestAccount::wallet(rent_data.payee_pubkey, false),
+            TestAccount::clock(NOW),
+        ]
+    }
+
+    #[test]
+    fn terminate_early_rejects_an_ended_agreement_with_its_status_error() {
+        let program_id = Pubkey::new_unique();
+        for (status, error) in [
+            (
+                AgreementStatus::Completed,
+                RentShareError::RentAlreadyPaidInFull,
+            ),
+            (
+                AgreementStatus::Terminated,
+                RentShareError::RentAgreementTerminated,
+            ),
+        ] {
+            let mut accounts = terminate_accounts(&program_id, &agreement_in(status));
+            assert_eq!(
+                process(
+                    &program_id,
+                    &mut accounts,
+                    RentShareInstruction::TerminateEarly {},
+                ),
+                Err(error.into()),
+                "{:?}",
+                status
+            );
+        }
+    }
+
+    #[test]
+    fn terminate_early_waits_for_the_notice_period() {
+        let program_id = Pubkey::new_unique();
+        let notice_period = 30 * 86_400;
+        let mut rent_data = active_agreement();
+        rent_data.notice_period_seconds = notice_period as u64;
+
+        let mut accounts = terminate_accounts(&program_id, &rent_data);
+        let terminate_at = |accounts: &mut Vec<TestAccount>, now: i64| {
+            accounts[3] = TestAccount::clock(now);
+            process(
+                &program_id,
+                accounts,
+                RentShareInstruction::TerminateEarly {},
+            )
+        };
+        assert_eq!(
+            terminate_at(&mut accounts, NOW + notice_period),
+            Err(RentShareError::NoticePeriodNotElapsed.into())
+        );
+
+        let agreement = accounts.remove(0);
+        let payer = accounts.remove(0);
+        let mut notice_accounts = vec![agreement, payer, TestAccount::clock(NOW)];
+        process(
+            &program_id,
+            &mut notice_accounts,
+            RentShareInstruction::GiveNotice {},
+        )
+        .unwrap();
+        assert_eq!(notice_accounts[0].rent_data().notice_given_at, NOW);
+
+        accounts.insert(0, notice_accounts.remove(1));
+        accounts.insert(0, notice_accounts.remove(0));
+        assert_eq!(
+            terminate_at(&mut accounts, NOW + notice_period - 1),
+            Err(RentShareError::NoticePeriodNotElapsed.into())
+        );
+        terminate_at(&mut accounts, NOW + notice_period).unwrap();
+        assert_eq!(
+            accounts[0].rent_data().status,
+            AgreementStatus::Terminated as u8
+        );
+    }
+
+    #[test]
+    fn terminate_early_returns_the_deposit_unless_it_is_forfeit() {
+        let program_id = Pubkey::new_unique();
+        for forfeit in [false, true] {
+            let mut rent_data = active_agreement();
+            rent_data.deposit = 5 * RENT;
+            rent_data.required_deposit = 5 * RENT;
+            rent_data.deposit_forfeit_on_early_termination = forfeit;
+
+            let mut accounts = terminate_accounts(&program_id, &rent_data);
+            process(
+                &program_id,
+                &mut accounts,
+                RentShareInstruction::TerminateEarly {},
+            )
+            .unwrap();
+
+            let (payer_lamports, payee_lamports, deposit_status) = if forfeit {
+                (100 * RENT, 105 * RENT, DepositStatus::Forfeited)
+            } else {
+                (105 * RENT, 100 * RENT, DepositStatus::Refunded)
+            };
+            assert_eq!(accounts[0].lamports, 10 * RENT, "forfeit {}", forfeit);
+            assert_eq!(accounts[1].lamports, payer_lamports, "forfeit {}", forfeit);
+            assert_eq!(accounts[2].lamports, payee_lamports, "forfeit {}", forfeit);
+
+            let rent_data = accounts[0].rent_data();
+            assert_eq!(rent_data.status, AgreementStatus::Terminated as u8);
+            assert_eq!(rent_data.deposit, 0);
+            assert_eq!(rent_data.deposit_status(), deposit_status);
+        }
+    }
+
+    #[test]
+    fn terminate_early_emits_the_status_transition_event() {
+        let program_id = Pubkey::new_unique();
+        let rent_data = active_agreement();
+
+        let mut accounts = terminate_accounts(&program_id, &rent_data);
+        accounts[3] = TestAccount::clock(NOW + 60);
+        process(
+            &program_id,
+            &mut accounts,
+            RentShareInstruction::TerminateEarly {},
+        )
+        .unwrap();
+        assert!(logged(&format!(
+            "[RentShare] EVENT:STATUS:Active:Terminated:{}",
+            NOW + 60
+        )));
+        assert!(!logged(&format!(
+            "[RentShare] EVENT:STATUS:Active:Completed:{}",
+            NOW + 60
+        )));
+    }
+
+    #[test]
+    fn termination_wins_over_a_payment_in_the_same_block() {
+        let program_id = Pubkey::new_unique();
+        let rent_data = active_agreement();
+
+        // Terminating first leaves the payment nothing to pay
+        let mut accounts = terminate_accounts(&program_id, &rent_data);
+        process(
+            &program_id,
+            &mut accounts,
+            RentShareInstruction::TerminateEarly {},
+        )
+        .unwrap();
+        let mut payment = pay_rent_accounts(&program_id, &rent_data, rent_data.payer_pubkey);
+        payment[0] = accounts.remove(0);
+        assert_eq!(
+            process(&program_id, &mut payment, pay_one_period()),
+            Err(RentShareError::RentAgreementTerminated.into())
+        );
+        assert!(payment[0].rent_data().is_terminated());
+        assert_eq!(payment[2].lamports, 100 * RENT);
+
+        // Paying first still lets the termination that follows end the agreement
+        let mut payment = pay_rent_accounts(&program_id, &rent_data, rent_data.payer_pubkey);
+        process(&program_id, &mut payment, pay_one_period()).unwrap();
+        assert_eq!(payment[1].lamports, 101 * RENT);
+        let mut accounts = terminate_accounts(&program_id, &rent_data);
+        accounts[0] = payment.remove(0);
+        process(
+            &program_id,
+            &mut accounts,
+            RentShareInstruction::TerminateEarly {},
+        )
+        .unwrap();
+        assert!(accounts[0].rent_data().is_terminated());
+    }
+
+    #[test]
+    fn terminate_early_pays_accrued_interest_from_the_payees_share_of_the_deposit() {
+        let program_id = Pubkey::new_unique();
+        let mut rent_data = active_agreement();
+        rent_data.deposit = 5 * RENT;
+        rent_data.non_refundable_deposit = 2 * RENT;
+        rent_data.accrued_interest = RENT / 2;
+
+        let mut accounts = terminate_accounts(&program_id, &rent_data);
+        process(
+            &program_id,
+            &mut accounts,
+            RentShareInstruction::TerminateEarly {},
+        )
+        .unwrap();
+        assert_eq!(accounts[0].lamports, 10 * RENT);
+        assert_eq!(accounts[1].lamports, 100 * RENT + 3 * RENT + RENT / 2);
+        assert_eq!(accounts[2].lamports, 100 * RENT + RENT + RENT / 2);
+
+        let rent_data = accounts[0].rent_data();
+        assert_eq!(rent_data.accrued_interest, 0);
+        assert_eq!(rent_data.deposit_interest_paid, RENT / 2);
+    }
+
+    #[test]
+    fn terminate_early_records_interest_the_payees_share_cannot_cover() {
+        let program_id = Pubkey::new_unique();
+        let mut rent_data = active_agreement();
+        rent_data.deposit = 5 * RENT;
+        rent_data.non_refundable_deposit = RENT;
+        rent_data.accrued_interest = 3 * RENT;
+
+        let mut accounts = terminate_accounts(&program_id, &rent_data);
+        process(
+            &program_id,
+            &mut accounts,
+            RentShareInstruction::TerminateEarly {},
+        )
+        .unwrap();
+        assert_eq!(accounts[1].lamports, 105 * RENT);
+        assert_eq!(accounts[2].lamports, 100 * RENT);
+
+        let rent_data = accounts[0].rent_data();
+        assert_eq!(rent_data.accrued_interest, 2 * RENT);
+        assert_eq!(rent_data.deposit_interest_paid, RENT);
+    }
+
+    #[test]
+    fn terminate_early_forfeits_accrued_interest_with_the_deposit() {
+        let program_id = Pubkey::new_unique();
+        let mut rent_data = active_agreement();
+        rent_data.deposit = 5 * RENT;
+        rent_data.deposit_forfeit_on_early_termination = true;
+        rent_data.accrued_interest = RENT;
+
+        let mut accounts = terminate_accounts(&program_id, &rent_data);
+        process(
+            &program_id,
+            &mut accounts,
+            RentShareInstruction::TerminateEarly {},
+        )
+        .unwrap();
+        assert_eq!(accounts[1].lamports, 100 * RENT);
+        assert_eq!(accounts[2].lamports, 105 * RENT);
+
+        let rent_data = accounts[0].rent_data();
+        assert_eq!(rent_data.accrued_interest, 0);
+        assert_eq!(rent_data.deposit_interest_paid, 0);
+    }
+
+    #[test]
+    fn terminate_early_refunds_the_deposit_to_the_refund_destination() {
+        let program_id = Pubkey::new_unique();
+        let mut rent_data = active_agreement();
+        rent_data.deposit = 5 * RENT;
+        rent_data.deposit_refund_destination = Pubkey::new_unique();
+
+        let mut accounts = terminate_accounts(&program_id, &rent_data);
+        assert_eq!(
+            process(
+                &program_id,
+                &mut accounts,
+                RentShareInstruction::TerminateEarly {}
+            ),
+            Err(ProgramError::NotEnoughAccountKeys)
+        );
+
+        accounts.insert(
+            3,
+            TestAccount::wallet(rent_data.deposit_refund_destination, false),
+        );
+        process(
+            &program_id,
+            &mut accounts,
+            RentShareInstruction::TerminateEarly {},
+        )
+        .unwrap();
+        assert_eq!(accounts[0].lamports, 10 * RENT);
+        assert_eq!(accounts[1].lamports, 100 * RENT);
+        assert_eq!(accounts[3].lamports, 105 * RENT);
+        assert!(accounts[0].rent_data().is_deposit_refunded());
+    }
+
+    #[test]
+    fn termination_platform_fee_is_not_taken_from_the_non_refundable_deposit() {
+        let program_id = Pubkey::new_unique();
+        let mut rent_data = active_agreement();
+        rent_data.deposit = 5 * RENT;
+        rent_data.non_refundable_deposit = 4 * RENT;
+        rent_data.platform_pubkey = Pubkey::new_unique();
+        rent_data.termination_platform_fee = 3 * RENT;
+
+        let mut accounts = terminate_accounts(&program_id, &rent_data);
+        accounts.insert(3, TestAccount::wallet(rent_data.platform_pubkey, false));
+        accounts.insert(4, TestAccount::system_program());
+        process(
+            &program_id,
+            &mut accounts,
+            RentShareInstruction::TerminateEarly {},
+        )
+        .unwrap();
+        assert_eq!(accounts[0].lamports, 10 * RENT);
+        assert_eq!(accounts[1].lamports, 98 * RENT);
+        assert_eq!(accounts[2].lamports, 104 * RENT);
+        assert_eq!(accounts[3].lamports, 103 * RENT);
+        assert_eq!(accounts[0].rent_data().platform_fees_paid, 3 * RENT);
+    }
+
+    fn initialize_accounts(
+        program_id: &Pubkey,
+        terms: &AgreementTerms,
+        agreement_lamports: u64,
+    ) -> Vec<TestAccount> {
+        vec![
+            TestAccount::new_agreement(program_id, terms, agreement_lamports),
+            TestAccount::rent_sysvar(&Rent::default()),
+            TestAccount::wallet(terms.payer_pubkey, true),
+            TestAccount::system_program(),
+            TestAccount::clock(NOW),
+        ]
+    }
+
+    #[test]
+    fn initialize_creates_the_agreement_account() {
+        let program_id = Pubkey::new_unique();
+        let terms = monthly_terms();
+        let required_lamports = Rent::default().minimum_balance(AGREEMENT_ACCOUNT_SPACE);
+
+        let mut accounts = initialize_accounts(&program_id, &terms, 0);
+        process(
+            &program_id,
+            &mut accounts,
+            RentShareInstruction::InitializeRentContract { terms },
+        )
+        .unwrap();
+        assert_eq!(accounts[0].owner, program_id);
+        assert_eq!(accounts[0].lamports, required_lamports);
+        assert_eq!(accounts[2].lamports, 100 * RENT - required_lamports);
+        let rent_data = accounts[0].rent_data();
+        assert!(rent_data.is_initialized());
+        assert_eq!(
+            Pubkey::create_program_address(
+                &[
+                    AGREEMENT_SEED,
+                    rent_data.payee_pubkey.as_ref(),
+                    rent_data.payer_pubkey.as_ref(),
+                    &rent_data.agreement_index.to_le_bytes(),
+                    &[rent_data.bump],
+                ],
+                &program_id,
+            ),
+            Ok(accounts[0].key)
+        );
+    }
+
+    #[test]
+    fn initialize_accepts_amounts_up_to_the_maximum() {
+        let program_id = Pubkey::new_unique();
+        for (rent_amount, deposit, result) in [
+            (MAX_RENT_AMOUNT, MAX_DEPOSIT, Ok(())),
+            (
+                MAX_RENT_AMOUNT + 1,
+                MAX_DEPOSIT,
+                Err(RentShareError::InvalidAgreementTerms.into()),
+            ),
+            (
+                MAX_RENT_AMOUNT,
+                MAX_DEPOSIT + 1,
+                Err(RentShareError::InvalidAgreementTerms.into()),
+            ),
+        ] {
+            let terms = AgreementTerms {
+                rent_amount,
+                deposit,
+                ..monthly_terms()
+            };
+
+            let mut accounts = initialize_accounts(&program_id, &terms, 0);
+            accounts[2].lamports = 2 * MAX_DEPOSIT;
+            assert_eq!(
+                process(
+                    &program_id,
+                    &mut accounts,
+                    RentShareInstruction::InitializeRentContract { terms },
+                ),
+                result,
+                "rent {} deposit {}",
+                rent_amount,
+                deposit
+            );
+        }
+    }
+
+    #[test]
+    fn initialize_bounds_the_duration_in_each_unit() {
+        let program_id = Pubkey::new_unique();
+        for unit in [Duration::Months, Duration::Years] {
+            for (duration, result) in [
+                (
+                    unit.min_duration() - 1,
+                    Err(RentShareError::InvalidAgreementTerms.into()),
+                ),
+                (unit.min_duration(), Ok(())),
+                (unit.min_duration() + 1, Ok(())),
+                (unit.max_duration(), Ok(())),
+                (
+                    unit.max_duration() + 1,
+                    Err(RentShareError::InvalidAgreementTerms.into()),
+                ),
+            ] {
+                let terms = AgreementTerms {
+                    duration,
+                    duration_unit: unit as u8,
+                    ..monthly_terms()
+                };
+
+                let mut accounts = initialize_accounts(&program_id, &terms, 0);
+                assert_eq!(
+                    process(
+                        &program_id,
+                        &mut accounts,
+                        RentShareInstruction::InitializeRentContract { terms },
+                    ),
+                    result,
+                    "{} {:?}",
+                    duration,
+                    unit
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn initialize_stores_the_currency_display_metadata() {
+        let program_id = Pubkey::new_unique();
+        for (currency_decimals, currency_symbol, stored_decimals, symbol) in [
+            (0, [0; 8], SOL_DECIMALS, "SOL"),
+            (6, *b"USDC\0\0\0\0", 6, "USDC"),
+            (
+                MAX_CURRENCY_DECIMALS,
+                *b"WRAPPED!",
+                MAX_CURRENCY_DECIMALS,
+                "WRAPPED!",
+            ),
+        ] {
+            let terms = AgreementTerms {
+                currency_decimals,
+                currency_symbol,
+                ..monthly_terms()
+            };
+
+            let mut accounts = initialize_accounts(&program_id, &terms, 0);
+            process(
+                &program_id,
+                &mut accounts,
+                RentShareInstruction::InitializeRentContract { terms },
+            )
+            .unwrap();
+            let rent_data = accounts[0].rent_data();
+            assert_eq!(rent_data.currency_decimals, stored_decimals);
+            assert_eq!(rent_data.currency_symbol(), symbol);
+        }
+
+        let terms = AgreementTerms {
+            currency_decimals: MAX_CURRENCY_DECIMALS + 1,
+            ..monthly_terms()
+        };
+        let mut accounts = initialize_accounts(&program_id, &terms, 0);
+        assert_eq!(
+            process(
+                &program_id,
+                &mut accounts,
+                RentShareInstruction::InitializeRentContract { terms },
+            ),
+            Err(RentShareError::InvalidAgreementTerms.into())
+        );
+    }
+
+    #[test]
+    fn initialize_flags_amounts_below_the_suspicious_threshold() {
+        let program_id = Pubkey::new_unique();
+        for (reject_suspicious_amounts, result) in [
+            (false, Ok(())),
+            (true, Err(RentShareError::InvalidAgreementTerms.into())),
+        ] {
+            let terms = AgreementTerms {
+                rent_amount: 5,
+                reject_suspicious_amounts,
+                ..monthly_terms()
+            };
+
+            let mut accounts = initialize_accounts(&program_id, &terms, 0);
+            assert_eq!(
+                process(
+                    &program_id,
+                    &mut accounts,
+                    RentShareInstruction::InitializeRentContract { terms },
+                ),
+                result,
+                "reject {}",
+                reject_suspicious_amounts
+            );
+            assert!(logged(&format!(
+                "[RentShare] WARNING: Rent amount of 5 lamports is below {} lamports and may have been entered in SOL",
+                DEFAULT_SUSPICIOUS_AMOUNT_THRESHOLD
+            )));
+        }
+
+        let terms = AgreementTerms {
+            rent_amount: 4 * RENT,
+            deposit: 2 * RENT,
+            reject_suspicious_amounts: true,
+            suspicious_amount_threshold: 3 * RENT,
+            ..monthly_terms()
+        };
+        let mut accounts = initialize_accounts(&program_id, &terms, 0);
+        assert_eq!(
+            process(
+                &program_id,
+                &mut accounts,
+                RentShareInstruction::InitializeRentContract { terms },
+            ),
+            Err(RentShareError::InvalidAgreementTerms.into())
+        );
+        assert!(logged(&format!(
+            "[RentShare] WARNING: Deposit of {} lamports is below {} lamports and may have been entered in SOL",
+            2 * RENT,
+            3 * RENT
+        )));
+    }
+
+    #[test]
+    fn initialize_escrows_the_first_period_only_when_prepaid() {
+        let program_id = Pubkey::new_unique();
+        let required_lamports = Rent::default().minimum_balance(AGREEMENT_ACCOUNT_SPACE);
+        for (prepay_first_period, prepaid) in [(false, 0), (true, RENT)] {
+            let terms = AgreementTerms {
+                deposit: 2 * RENT,
+                prepay_first_period,
+                ..monthly_terms()
+            };
+
+            let mut accounts = initialize_accounts(&program_id, &terms, 0);
+            process(
+                &program_id,
+                &mut accounts,
+                RentShareInstruction::InitializeRentContract { terms },
+            )
+            .unwrap();
+            let rent_data = accounts[0].rent_data();
+            assert_eq!(rent_data.escrowed_rent, prepaid);
+            assert_eq!(rent_data.remaining_payments, 12 - prepaid / RENT);
+            assert_eq!(accounts[0].lamports, required_lamports + 2 * RENT + prepaid);
+            assert_eq!(
+                accounts[2].lamports,
+                100 * RENT - required_lamports - 2 * RENT - prepaid
+            );
+
+            let mut accounts = vec![
+                accounts.remove(0),
+                TestAccount::wallet(terms.payee_pubkey, true),
+                TestAccount::clock(NOW),
+            ];
+            process(
+                &program_id,
+                &mut accounts,
+                RentShareInstruction::AcceptAgreement {},
+            )
+            .unwrap();
+            let rent_data = accounts[0].rent_data();
+            assert_eq!(rent_data.escrowed_rent, 0);
+            assert_eq!(rent_data.total_rent_paid, prepaid);
+            assert_eq!(accounts[1].lamports, 100 * RENT + prepaid);
+            assert_eq!(accounts[0].lamports, required_lamports + 2 * RENT);
+        }
+    }
+
+    #[test]
+    fn initialize_keeps_the_escrow_rent_exempt_after_collecting_the_deposit() {
+        let program_id = Pubkey::new_unique();
+        let terms = AgreementTerms {
+            deposit: 3 * RENT,
+            prepay_first_period: true,
+            ..monthly_terms()
+        };
+        let required_lamports = Rent::default().minimum_balance(AGREEMENT_ACCOUNT_SPACE);
+
+        let mut accounts = initialize_accounts(&program_id, &terms, required_lamports);
+        process(
+            &program_id,
+            &mut accounts,
+            RentShareInstruction::InitializeRentContract { terms },
+        )
+        .unwrap();
+        let held = 3 * RENT + RENT;
+        assert_eq!(accounts[0].lamports, required_lamports + held);
+        assert!(Rent::default().is_exempt(accounts[0].lamports - held, AGREEMENT_ACCOUNT_SPACE));
+
+        let mut accounts = vec![
+            accounts.remove(0),
+            TestAccount::rent_sysvar(&Rent::default()),
+        ];
+        accounts[0].is_writable = false;
+        process(
+            &program_id,
+            &mut accounts,
+            RentShareInstruction::VerifyEscrow {},
+        )
+        .unwrap();
+    }
+
+    #[test]
+    fn a_rent_sysvar_charging_no_rent_is_rejected() {
+        let program_id = Pubkey::new_unique();
+        let zero_rent = Rent {
+            lamports_per_byte_year: 0,
+            ..Rent::default()
+        };
+
+        let terms = monthly_terms();
+        let mut accounts = initialize_accounts(&program_id, &terms, 0);
+        accounts[1] = TestAccount::rent_sysvar(&zero_rent);
+        assert_eq!(
+            process(
+                &program_id,
+                &mut accounts,
+                RentShareInstruction::InitializeRentContract { terms },
+            ),
+            Err(ProgramError::InvalidArgument)
+        );
+        assert!(logged(
+            "[RentShare] Rent sysvar reports zero lamports per byte year"
+        ));
+        assert_eq!(accounts[2].lamports, 100 * RENT);
+
+        let mut agreement = TestAccount::agreement(&program_id, &active_agreement());
+        agreement.is_writable = false;
+        let mut accounts = vec![agreement, TestAccount::rent_sysvar(&zero_rent)];
+        assert_eq!(
+            process(
+                &program_id,
+                &mut accounts,
+                RentShareInstruction::VerifyEscrow {}
+            ),
+            Err(ProgramError::InvalidArgument)
+        );
+    }
+
+    #[test]
+    fn initialize_rejects_a_late_fee_above_100_percent() {
+        let program_id = Pubkey::new_unique();
+        let terms = AgreementTerms {
+            late_fee_bps: MAX_BASIS_POINTS + 1,
+            ..monthly_terms()
+        };
+
+        let mut accounts = initialize_accounts(&program_id, &terms, 0);
+        assert_eq!(
+            process(
+                &program_id,
+                &mut accounts,
+                RentShareInstruction::InitializeRentContract { terms },
+            ),
+            Err(RentShareError::InvalidAgreementTerms.into())
+        );
+    }
+
+    #[test]
+    fn initialize_rejects_a_non_refundable_portion_above_the_deposit() {
+        let program_id = Pubkey::new_unique();
+        for (required_deposit, non_refundable_deposit, accepted) in [
+            (0, 2 * RENT, true),
+            (0, 2 * RENT + 1, false),
+            (3 * RENT, 3 * RENT, true),
+            (3 * RENT, 3 * RENT + 1, false),
+        ] {
+            let terms = AgreementTerms {
+                deposit: 2 * RENT,
+                required_deposit,
+                non_refundable_deposit,
+                ..monthly_terms()
+            };
+
+            let mut accounts = initialize_accounts(&program_id, &terms, 0);
+            let result = process(
+                &program_id,
+                &mut accounts,
+                RentShareInstruction::InitializeRentContract { terms },
+            );
+            if accepted {
+                assert_eq!(result, Ok(()), "{}", non_refundable_deposit);
+                assert_eq!(
+                    accounts[0].rent_data().non_refundable_deposit,
+                    non_refundable_deposit
+                );
+            } else {
+                assert_eq!(
+                    result,
+                    Err(RentShareError::InvalidAgreementTerms.into()),
+                    "{}",
+                    non_refundable_deposit
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn initialize_tops_up_an_agreement_address_that_already_holds_lamports() {
+        let program_id = Pubkey::new_unique();
+        let terms = monthly_terms();
+        let required_lamports = Rent::default().minimum_balance(AGREEMENT_ACCOUNT_SPACE);
+
+        let mut accounts = initialize_accounts(&program_id, &terms, 1_000);
+        process(
+            &program_id,
+            &mut accounts,
+            RentShareInstruction::InitializeRentContract { terms },
+        )
+        .unwrap();
+        assert_eq!(accounts[0].owner, program_id);
+        assert_eq!(accounts[0].lamports, required_lamports);
+        assert_eq!(accounts[2].lamports, 100 * RENT - required_lamports + 1_000);
+        assert!(accounts[0].rent_data().is_initialized());
+    }
+
+    #[test]
+    fn pay_rent_rejects_an_agreement_the_payee_has_not_accepted() {
+        let program_id = Pubkey::new_unique();
+        let terms = monthly_terms();
+
+        let mut accounts = initialize_accounts(&program_id, &terms, 0);
+        process(
+            &program_id,
+            &mut accounts,
+            RentShareInstruction::InitializeRentContract { terms },
+        )
+        .unwrap();
+        let agreement = accounts.remove(0);
+        let payer = accounts.remove(1);
+
+        let mut accounts = vec![
+            agreement,
+            TestAccount::wallet(terms.payee_pubkey, false),
+            payer,
+            TestAccount::system_program(),
+            TestAccount::clock(NOW),
+        ];
+        assert_eq!(
+            process(&program_id, &mut accounts, pay_one_period()),
+            Err(RentShareError::AgreementNotAccepted.into())
+        );
+        assert!(logged(
+            "[RentShare] Rent agreement has not been accepted by the payee yet"
+        ));
+    }
+
+    #[test]
+    fn lease_completes_once_every_period_is_paid() {
+        let program_id = Pubkey::new_unique();
+        let terms = AgreementTerms {
+            deposit: 2 * RENT,
+            ..monthly_terms()
+        };
+        let rent_amount = terms.rent_amount;
+        let payments = terms.duration;
+
+        let mut accounts = initialize_accounts(&program_id, &terms, 0);
+        process(
+            &program_id,
+            &mut accounts,
+            RentShareInstruction::InitializeRentContract { terms },
+        )
+        .unwrap();
+        let mut agreement = accounts.remove(0);
+        let mut payer = accounts.remove(1);
+        let mut payee = TestAccount::wallet(agreement.rent_data().payee_pubkey, true);
+        assert_eq!(
+            agreement.rent_data().status,
+            AgreementStatus::PendingAcceptance as u8
+        );
+
+        let mut accounts = vec![agreement, payee, TestAccount::clock(NOW)];
+        process(
+            &program_id,
+            &mut accounts,
+            RentShareInstruction::AcceptAgreement {},
+        )
+        .unwrap();
+        agreement = accounts.remove(0);
+        payee = accounts.remove(0);
+        payee.is_signer = false;
+        assert_eq!(agreement.rent_data().status, AgreementStatus::Active as u8);
+
+        let payer_lamports = payer.lamports;
+        let payee_lamports = payee.lamports;
+        for payment in 0..payments {
+            let now = agreement.rent_data().next_due_timestamp(NOW).unwrap();
+            let mut accounts = vec![
+                agreement,
+                payee,
+                payer,
+                TestAccount::system_program(),
+                TestAccount::clock(now),
+            ];
+            process(
+                &program_id,
+                &mut accounts,
+                RentShareInstruction::PayRent {
+                    rent_amount,
+                    periods: 1,
+                },
+            )
+            .unwrap();
+            agreement = accounts.remove(0);
+            payee = accounts.remove(0);
+            payer = accounts.remove(0);
+            assert_eq!(
+                agreement.rent_data().remaining_payments,
+                payments - payment - 1
+            );
+        }
+
+        let rent_data = agreement.rent_data();
+        assert_eq!(rent_data.status, AgreementStatus::Completed as u8);
+        assert_eq!(rent_data.total_rent_paid, payments * rent_amount);
+        assert_eq!(rent_data.deposit, 2 * RENT);
+        assert_eq!(payer.lamports, payer_lamports - payments * rent_amount);
+        assert_eq!(payee.lamports, payee_lamports + payments * rent_amount);
+    }
+
+    /// One instruction of every variant compiled in
+    fn every_instruction() -> Vec<RentShareInstruction> {
+        use RentShareInstruction::*;
+
+        let key = Pubkey::new_unique();
+        #[allow(unused_mut)]
+        let mut instructions = vec![
+            InitializeRentContract {
+                terms: monthly_terms(),
+            },
+            pay_one_period(),
+            TerminateEarly {},
+            ViewAgreement {},
+            IncreaseDeposit { amount: RENT },
+            TopUpRentExemption {},
+            AcceptAgreement {},
+            GiveNotice {},
+            RefundDeposit {},
+            VerifyEscrow {},
+            AccrueDepositInterest {},
+            CloseAgreement {},
+            CorrectPayee { new_payee: key },
+            PrepayWholeLease {},
+            UpdateRentAmount { rent_amount: RENT },
+            ExtendAgreement { periods: 1 },
+            TransferTenant { new_payer: key },
+            GetPayoffAmount {},
+            SetDocumentHash { hash: [1; 32] },
+            GenerateSettlementReport {},
+            GetHealthScore {},
+            CheckReminders {},
+            CompleteSettlement {},
+            ExtendEndDate {
+                additional_seconds: 1,
+            },
+            WriteLeaseTemplate {
+                duration: 12,
+                duration_unit: 2,
+                late_fee_bps: 0,
+                notice_period_seconds: 0,
+                termination_penalty_bps: 0,
+                termination_penalty_max: 0,
+            },
+            InitializeFromTemplate {
+                payee_pubkey: key,
+                payer_pubkey: key,
+                deposit: 0,
+                rent_amount: RENT,
+                agreement_index: 0,
+                overrides: TemplateOverrides::default(),
+            },
+            ReturnDepositItemized {
+                return_amount: 0,
+                deduction_codes: [0; 4],
+            },
+            Freeze {},
+            Unfreeze {},
+            AssignRent {
+                assigned_payee: key,
+                until_period: 1,
+            },
+            PayLateFees { amount: 1 },
+        ];
+        #[cfg(feature = "dev-reset")]
+        instructions.push(ResetAgreement {});
+        #[cfg(feature = "debug-instructions")]
+        instructions.extend(vec![DumpState {}, EmitAccountBlob {}]);
+        instructions
+    }
+
+    /// Accounts meeting `specs` exactly
+    fn accounts_meeting(program_id: &Pubkey, specs: &[AccountSpec]) -> Vec<TestAccount> {
+        specs
+            .iter()
+            .map(|spec| TestAccount {
+                key: spec.key.unwrap_or_else(Pubkey::new_unique),
+                owner: if spec.program_owned {
+                    *program_id
+                } else {
+                    system_program::id()
+                },
+                lamports: RENT,
+                data: vec![],
+                is_signer: spec.signer,
+                is_writable: spec.writable,
+                executable: false,
+            })
+            .collect()
+    }
+
+    fn validate(
+        instruction: &RentShareInstruction,
+        program_id: &Pubkey,
+        accounts: &mut [TestAccount],
+    ) -> ProgramResult {
+        let infos: Vec<AccountInfo> = accounts.iter_mut().map(TestAccount::info).collect();
+        Processor::validate_accounts(instruction, &infos, program_id)
+    }
+
+    #[test]
+    fn validate_accounts_accepts_the_documented_accounts() {
+        let program_id = Pubkey::new_unique();
+        for instruction in every_instruction() {
+            let specs = instruction.account_specs();
+            let optional_specs = instruction.optional_account_specs();
+
+            for optional in 0..=optional_specs.len() {
+                let all_specs = [specs, &optional_specs[..optional]].concat();
+                let mut accounts = accounts_meeting(&program_id, &all_specs);
+                assert_eq!(
+                    validate(&instruction, &program_id, &mut accounts),
+                    Ok(()),
+                    "{:?} with {} optional accounts",
+                    instruction,
+                    optional
+                );
+
+                accounts.push(TestAccount::clock(NOW));
+                assert_eq!(
+                    validate(&instruction, &program_id, &mut accounts),
+                    Ok(()),
+                    "{:?} with {} optional accounts and the clock",
+                    instruction,
+                    optional
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn validate_accounts_rejects_a_wrong_account_count() {
+        let program_id = Pubkey::new_unique();
+        for instruction in every_instruction() {
+            let all_specs = [
+                instruction.account_specs(),
+                instruction.optional_account_specs(),
+            ]
+            .concat();
+
+            let mut accounts = accounts_meeting(&program_id, &instruction.account_specs()[1..]);
+            assert_eq!(
+                validate(&instruction, &program_id, &mut accounts),
+                Err(ProgramError::NotEnoughAccountKeys),
+                "{:?} missing an account",
+                instruction
+            );
+
+            let mut accounts = accounts_meeting(&program_id, &all_specs);
+            accounts.push(TestAccount::wallet(Pubkey::new_unique(), false));
+            assert_eq!(
+                validate(&instruction, &program_id, &mut accounts),
+                Err(ProgramError::InvalidArgument),
+                "{:?} with an extra account",
+                instruction
+            );
+        }
+    }
+
+    #[test]
+    fn process_names_the_instruction_missing_an_account() {
+        let program_id = Pubkey::new_unique();
+        for instruction in every_instruction() {
+            let expected = instruction.account_specs().len();
+            let mut accounts =
+                accounts_meeting(&program_id, &instruction.account_specs()[..expected - 1]);
+            let message = format!(
+                "[RentShare] {:?} expects {} accounts but received {}",
+                instruction,
+                expected,
+                expected - 1
+            );
+
+            assert_eq!(
+                process(&program_id, &mut accounts, instruction),
+                Err(ProgramError::NotEnoughAccountKeys),
+                "{}",
+                message
+            );
+            assert!(logged(&message), "{}", message);
+        }
+    }
+
+    #[test]
+    fn validate_accounts_rejects_each_violated_spec() {
+        let program_id = Pubkey::new_unique();
+        for instruction in every_instruction() {
+            let all_specs = [
+                instruction.account_specs(),
+                instruction.optional_account_specs(),
+            ]
+            .concat();
+
+            for (index, spec) in all_specs.iter().enumerate() {
+                let violate = |violation: &dyn Fn(&mut TestAccount), error: ProgramError| {
+                    let mut accounts = accounts_meeting(&program_id, &all_specs);
+                    violation(&mut accounts[index]);
+                    assert_eq!(
+                        validate(&instruction, &program_id, &mut accounts),
+                        Err(error),
+                        "{:?} account {}",
+                        instruction,
+                        index
+                    );
+                };
+
+                if spec.signer {
+                    violate(
+                        &|account| account.is_signer = false,
+                        ProgramError::MissingRequiredSignature,
+                    );
+                }
+                if spec.writable {
+                    violate(
+                        &|account| account.is_writable = false,
+                        ProgramError::InvalidArgument,
+                    );
+                }
+                if !spec.writable && !spec.signer {
+                    violate(
+                        &|account| account.is_writable = true,
+                        ProgramError::InvalidArgument,
+                    );
+                }
+                if spec.program_owned {
+                    violate(
+                        &|account| account.owner = system_program::id(),
+                        ProgramError::IncorrectProgramId,
+                    );
+                }
+                if spec.key.is_some() {
+                    violate(
+                        &|account| account.key = Pubkey::new_unique(),
+                        ProgramError::InvalidArgument,
+                    );
+                }
+            }
+        }
+    }
+
+    /// The `[signer, writable]` flags of the accounts documented on `instruction`'s variant
+    fn documented_accounts(instruction: &RentShareInstruction) -> Vec<(bool, bool)> {
+        let debug = format!("{:?}", instruction);
+        let name = debug.split(|c: char| !c.is_alphanumeric()).next().unwrap();
+        let source = include_str!("instruction.rs");
+        let lines: Vec<&str> = source.lines().collect();
+        let variant = lines
+            .iter()
+            .position(|line| line.starts_with(&format!("    {} {{", name)))
+            .unwrap_or_else(|| panic!("{} is not declared", name));
+
+        let mut documented: Vec<(bool, bool)> = lines[..variant]
+            .iter()
+            .rev()
+            .take_while(|line| line.starts_with("    ///") || line.starts_with("    #["))
+            .filter_map(|line| {
+                let (_, flags) = line.split_once(". `[")?;
+                let (flags, _) = flags.split_once("]`")?;
+                Some((flags.contains("signer"), flags.contains("writable")))
+            })
+            .collect();
+        documented.reverse();
+        documented
+    }
+
+    #[test]
+    fn account_specs_follow_the_documented_writability_contract() {
+        for instruction in every_instruction() {
+            let specs: Vec<(bool, bool)> = instruction
+                .account_specs()
+                .iter()
+                .chain(instruction.optional_account_specs())
+                .map(|spec| (spec.signer, spec.writable))
+                .collect();
+
+            assert_eq!(
+                specs,
+                documented_accounts(&instruction),
+                "{:?}",
+                instruction
+            );
+        }
+    }
+
+    #[test]
+    fn a_writable_read_only_account_is_rejected() {
+        let program_id = Pubkey::new_unique();
+        let mut accounts = vec![
+            TestAccount::agreement(&program_id, &active_agreement()),
+            TestAccount::clock(NOW),
+        ];
+        accounts[0].is_writable = true;
+
+        assert_eq!(
+            process(
+                &program_id,
+                &mut accounts,
+                RentShareInstruction::ViewAgreement {}
+            ),
+            Err(ProgramError::InvalidArgument)
+        );
+        assert!(logged("[RentShare] Account 0 must be read-only"));
+    }
+
+    #[test]
+    fn pay_rent_expects_the_rent_after_a_step() {
+        let program_id = Pubkey::new_unique();
+        let rent_data = active_agreement();
+        let mut accounts = vec![
+            TestAccount::agreement(&program_id, &rent_data),
+            TestAccount::wallet(rent_data.payee_pubkey, true),
+            TestAccount::wallet(rent_data.payer_pubkey, true),
+            TestAccount::clock(NOW),
+        ];
+        process(
+            &program_id,
+            &mut accounts,
+            RentShareInstruction::UpdateRentAmount {
+                rent_amount: 2 * RENT,
+            },
+        )
+        .unwrap();
+
+        let mut accounts = pay_rent_accounts(
+            &program_id,
+            &accounts[0].rent_data(),
+            rent_data.payer_pubkey,
+        );
+        assert_eq!(
+            process(&program_id, &mut accounts, pay_one_period()),
+            Err(RentShareError::RentPaymentAmountMismatch.into())
+        );
+        process(
+            &program_id,
+            &mut accounts,
+            RentShareInstruction::PayRent {
+                rent_amount: 2 * RENT,
+                periods: 1,
+            },
+        )
+        .unwrap();
+        assert_eq!(accounts[0].rent_data().total_rent_paid, 2 * RENT);
+    }
+
+    #[test]
+    fn initialize_rejects_an_allocated_account_holding_stray_bytes() {
+        let program_id = Pubkey::new_unique();
+        let terms = monthly_terms();
+        let agreement_lamports = Rent::default().minimum_balance(AGREEMENT_ACCOUNT_SPACE);
+
+        for dirty_byte in [
+            None,
+            Some(1),
+            Some(RentShareAccount::LEN - 1),
+            Some(AGREEMENT_ACCOUNT_SPACE - 1),
+        ] {
+            let mut accounts = initialize_accounts(&program_id, &terms, agreement_lamports);
+            accounts[0].owner = program_id;
+            if let Some(index) = dirty_byte {
+                accounts[0].data[index] = 0xab;
+            }
+            let result = process(
+                &program_id,
+                &mut accounts,
+                RentShareInstruction::InitializeRentContract { terms },
+            );
+
+            if dirty_byte.is_some() {
+                assert_eq!(
+                    result,
+                    Err(ProgramError::AccountAlreadyInitialized),
+                    "byte {:?}",
+                    dirty_byte
+                );
+                assert!(logged(
+                    "[RentShare] Rent agreement account data is not empty"
+                ));
+            } else {
+                result.unwrap();
+            }
+        }
+    }
+
+    #[test]
+    fn initialize_rejects_an_agreement_with_only_its_status_cleared() {
+        let program_id = Pubkey::new_unique();
+        let terms = monthly_terms();
+
+        let mut accounts = initialize_accounts(&program_id, &terms, 0);
+        process(
+            &program_id,
+            &mut accounts,
+            RentShareInstruction::InitializeRentContract { terms },
+        )
+        .unwrap();
+
+        // The status is the first byte of the agreement
+        accounts[0].data[0] = AgreementStatus::Uninitialized as u8;
+        assert!(!accounts[0].rent_data().is_initialized());
+        assert_eq!(
+            process(
+                &program_id,
+                &mut accounts,
+                RentShareInstruction::InitializeRentContract { terms },
+            ),
+            Err(ProgramError::AccountAlreadyInitialized)
+        );
+        assert!(logged(
+            "[RentShare] Rent agreement account data is not empty"
+        ));
+    }
+
+    #[cfg(feature = "dev-reset")]
+    #[test]
+    fn reset_agreement_lets_the_account_be_initialized_again() {
+        let program_id = Pubkey::new_unique();
+        let terms = AgreementTerms {
+            deposit: 2 * RENT,
+            ..monthly_terms()
+        };
+
+        let mut accounts = initialize_accounts(&program_id, &terms, 0);
+        process(
+            &program_id,
+            &mut accounts,
+            RentShareInstruction::InitializeRentContract { terms },
+        )
+        .unwrap();
+        let agreement_lamports = accounts[0].lamports;
+        let payer_lamports = accounts[2].lamports;
+
+        let mut reset = vec![
+            accounts.remove(0),
+            TestAccount::wallet(terms.payee_pubkey, true),
+        ];
+        assert_eq!(
+            process(
+                &program_id,
+                &mut reset,
+                RentShareInstruction::ResetAgreement {}
+            ),
+            Err(ProgramError::InvalidAccountData)
+        );
+
+        reset[1] = accounts.remove(1);
+        process(
+            &program_id,
+            &mut reset,
+            RentShareInstruction::ResetAgreement {},
+        )
+        .unwrap();
+        assert!(reset[0].data.iter().all(|byte| *byte == 0));
+        assert_eq!(reset[0].lamports, agreement_lamports - 2 * RENT);
+        assert_eq!(reset[1].lamports, payer_lamports + 2 * RENT);
+
+        accounts.insert(0, reset.remove(0));
+        accounts.insert(2, reset.remove(0));
+        process(
+            &program_id,
+            &mut accounts,
+            RentShareInstruction::InitializeRentContract { terms },
+        )
+        .unwrap();
+        assert_eq!(
+            accounts[0].rent_data().status,
+            AgreementStatus::PendingAcceptance as u8
+        );
+        assert_eq!(accounts[0].rent_data().deposit, 2 * RENT);
+    }
+
+    #[cfg(feature = "debug-instructions")]
+    #[test]
+    fn dump_state_logs_every_field_without_mutating() {
+        let program_id = Pubkey::new_unique();
+        let mut accounts = view_accounts(&program_id, &active_agreement());
+        let data = accounts[0].data.clone();
+        process(
+            &program_id,
+            &mut accounts,
+            RentShareInstruction::DumpState {},
+        )
+        .unwrap();
+        assert_eq!(accounts[0].data, data);
+
+        for field in [
+            "status",
+            "payee_pubkey",
+            "payer_pubkey",
+            "deposit",
+            "rent_amount",
+            "duration",
+            "duration_unit",
+            "remaining_payments",
+            "deposit_forfeit_on_early_termination",
+            "created_at",
+            "late_fee_bps",
+            "agreement_index",
+            "accepted_at",
+            "notice_period_seconds",
+            "notice_given_at",
+            "required_deposit",
+            "replenish_deposit_first",
+            "deposit_refunded",
+            "max_missed_payments",
+            "auto_terminate_on_default",
+            "deposit_interest_bps",
+            "accrued_interest",
+            "interest_periods_accrued",
+            "currency_decimals",
+            "currency_symbol",
+            "deposit_refund_destination",
+            "amendment_count",
+            "defer_first_payment",
+            "proration_min_unit",
+            "document_hash",
+            "free_periods",
+            "total_rent_paid",
+            "total_late_fees_paid",
+            "max_prepay_periods",
+            "on_time_streak",
+            "reminder_lead_seconds",
+            "final_settlement_grace_seconds",
+            "settlement_started_at",
+            "termination_penalty_bps",
+            "termination_penalty_max",
+            "termination_penalty_paid",
+            "bump",
+            "deposit_interest_paid",
+            "end_date_extension_seconds",
+            "prepay_first_period",
+            "escrowed_rent",
+            "overpay_to_deposit",
+            "platform_pubkey",
+            "read_fee_lamports",
+            "observer_program",
+            "observer_required",
+            "admin_pubkey",
+            "frozen",
+            "assigned_payee",
+            "assigned_until_period",
+            "late_fee_escalation_bps",
+            "late_fee_max",
+            "late_fee_credit",
+            "rent_change_cooldown_seconds",
+            "last_rent_change_at",
+            "non_refundable_deposit",
+            "termination_platform_fee",
+            "platform_fees_paid",
+        ] {
+            let prefix = format!("[RentShare] DUMP:{}=", field);
+            assert!(
+                LOGS.with(|logs| logs.borrow().iter().any(|log| log.starts_with(&prefix))),
+                "{}",
+                field
+            );
+        }
+    }
+
+    #[cfg(feature = "debug-instructions")]
+    #[test]
+    fn emit_account_blob_logs_data_decoding_to_the_agreement() {
+        let program_id = Pubkey::new_unique();
+        let rent_data = active_agreement();
+        let mut accounts = vec![TestAccount::agreement(&program_id, &rent_data)];
+        accounts[0].is_writable = false;
+        let data = accounts[0].data.clone();
+
+        process(
+            &program_id,
+            &mut accounts,
+            RentShareInstruction::EmitAccountBlob {},
+        )
+        .unwrap();
+
+        let log_data = LOG_DATA.with(|log_data| log_data.borrow().clone());
+        assert_eq!(log_data.len(), 1);
+        assert_eq!(log_data[0].len(), 1);
+        let blob = &log_data[0][0];
+        assert_eq!(blob.len(), RentShareAccount::LEN);
+        assert_eq!(
+            RentShareAccount::load(blob).unwrap().try_to_vec().unwrap(),
+            rent_data.try_to_vec().unwrap()
+        );
+        assert_eq!(accounts[0].data, data);
+    }
+
+    fn lease_template_account(program_id: &Pubkey) -> TestAccount {
+        let template = LeaseTemplate {
+            is_initialized: true,
+            authority: Pubkey::new_unique(),
+            duration: 24,
+            duration_unit: Duration::Months as u8,
+            late_fee_bps: 500,
+            notice_period_seconds: 86_400,
+            termination_penalty_bps: 1_000,
+            termination_penalty_max: 3 * RENT,
+        };
+        let mut data = vec![0; LeaseTemplate::LEN];
+        template.save(&mut data).unwrap();
+        TestAccount {
+            key: Pubkey::new_unique(),
+            owner: *program_id,
+            lamports: RENT,
+            data,
+            is_signer: false,
+            is_writable: false,
+            executable: false,
+        }
+    }
+
+    fn initialize_from_template(
+        program_id: &Pubkey,
+        overrides: TemplateOverrides,
+    ) -> RentShareAccount {
+        let terms = monthly_terms();
+        let mut accounts = initialize_accounts(program_id, &terms, 0);
+        accounts.insert(4, lease_template_account(program_id));
+        process(
+            program_id,
+            &mut accounts,
+            RentShareInstruction::InitializeFromTemplate {
+                payee_pubkey: terms.payee_pubkey,
+                payer_pubkey: terms.payer_pubkey,
+                deposit: 2 * RENT,
+                rent_amount: RENT,
+                agreement_index: terms.agreement_index,
+                overrides,
+            },
+        )
+        .unwrap();
+        accounts[0].rent_data()
+    }
+
+    #[test]
+    fn initialize_from_template_takes_the_template_terms() {
+        let rent_data =
+            initialize_from_template(&Pubkey::new_unique(), TemplateOverrides::default());
+
+        assert_eq!(rent_data.status, AgreementStatus::PendingAcceptance as u8);
+        assert_eq!(rent_data.rent_amount, RENT);
+        assert_eq!(rent_data.deposit, 2 * RENT);
+        assert_eq!(rent_data.duration, 24);
+        assert_eq!(rent_data.remaining_payments, 24);
+        assert_eq!(rent_data.duration_unit, Duration::Months as u8);
+        assert_eq!(rent_data.late_fee_bps, 500);
+        assert_eq!(rent_data.notice_period_seconds, 86_400);
+        assert_eq!(rent_data.termination_penalty_bps, 1_000);
+        assert_eq!(rent_data.termination_penalty_max, 3 * RENT);
+    }
+
+    #[test]
+    fn initialize_from_template_applies_the_overrides() {
+        let rent_data = initialize_from_template(
+            &Pubkey::new_unique(),
+            TemplateOverrides {
+                duration: Some(6),
+                late_fee_bps: Some(0),
+                termination_penalty_max: Some(0),
+                ..TemplateOverrides::default()
+            },
+        );
+
+        assert_eq!(rent_data.duration, 6);
+        assert_eq!(rent_data.remaining_payments, 6);
+        assert_eq!(rent_data.late_fee_bps, 0);
+        assert_eq!(rent_data.termination_penalty_max, 0);
+        // Terms without an override keep the template's
+        assert_eq!(rent_data.duration_unit, Duration::Months as u8);
+        assert_eq!(rent_data.notice_period_seconds, 86_400);
+        assert_eq!(rent_data.termination_penalty_bps, 1_000);
+    }
 }
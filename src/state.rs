@@ -1,9 +1,93 @@
 use borsh::{BorshDeserialize, BorshSerialize};
 use solana_program::{
-    program_pack::{IsInitialized, Sealed},
+    entrypoint::ProgramResult,
+    msg,
+    native_token::LAMPORTS_PER_SOL,
+    program_error::ProgramError,
+    program_pack::{IsInitialized, Pack, Sealed},
     pubkey::Pubkey,
 };
 
+use std::convert::TryFrom;
+#[cfg(feature = "client")]
+use std::convert::TryInto;
+
+use crate::{error::RentShareError, fees, math};
+
+/// Allowed drift between a stored timestamp and the cluster clock
+pub const MAX_CLOCK_SKEW_SECONDS: i64 = 5 * 60;
+
+/// Stored timestamps older than this are treated as corrupted state
+pub const MAX_TIMESTAMP_AGE_SECONDS: i64 = 100 * 365 * 24 * 60 * 60;
+
+/// Largest rent payment accepted in agreement terms, in lamports
+pub const MAX_RENT_AMOUNT: u64 = 1_000_000 * LAMPORTS_PER_SOL;
+
+/// Largest deposit accepted in agreement terms, in lamports
+pub const MAX_DEPOSIT: u64 = 1_000_000 * LAMPORTS_PER_SOL;
+
+/// Non-zero rent or deposit amounts below this many lamports are likely entered in SOL by mistake
+pub const DEFAULT_SUSPICIOUS_AMOUNT_THRESHOLD: u64 = LAMPORTS_PER_SOL / 1_000;
+
+/// Shortest lease accepted in agreement terms, in months
+pub const MIN_DURATION_MONTHS: u64 = 1;
+
+/// Longest lease accepted in agreement terms, in months
+pub const MAX_DURATION_MONTHS: u64 = 10 * 12;
+
+/// Shortest lease accepted in agreement terms, in years
+pub const MIN_DURATION_YEARS: u64 = 1;
+
+/// Longest lease accepted in agreement terms, in years
+pub const MAX_DURATION_YEARS: u64 = 10;
+
+/// Overdue periods after which an agreement is in default when its terms do not set a threshold
+pub const DEFAULT_MAX_MISSED_PAYMENTS: u8 = 3;
+
+/// Largest fee an agreement may charge for `ViewAgreement`, `GetHealthScore` and `CheckReminders`
+pub const MAX_READ_FEE_LAMPORTS: u64 = LAMPORTS_PER_SOL / 1_000;
+
+/// Basis points in 100%
+pub const MAX_BASIS_POINTS: u16 = 10_000;
+
+/// Most decimals a display currency may use
+pub const MAX_CURRENCY_DECIMALS: u8 = 18;
+
+/// Display decimals of lamport denominated agreements
+pub const SOL_DECIMALS: u8 = 9;
+
+/// Display symbol of lamport denominated agreements, zero padded
+pub const SOL_SYMBOL: [u8; 8] = *b"SOL\0\0\0\0\0";
+
+const SECONDS_PER_DAY: i64 = 24 * 60 * 60;
+
+/// Space to allocate for a rent agreement account
+pub const AGREEMENT_ACCOUNT_SPACE: usize = RentShareAccount::LEN;
+
+/// Largest agreement account data accepted, well above `AGREEMENT_ACCOUNT_SPACE`
+pub const MAX_AGREEMENT_ACCOUNT_DATA_LEN: usize = 10 * 1024;
+
+/// Seed prefix for rent agreement program derived addresses
+pub const AGREEMENT_SEED: &[u8] = b"rentshare";
+
+/// Find the rent agreement address for the `index`-th agreement between a payee and payer
+pub fn find_agreement_address(
+    program_id: &Pubkey,
+    payee_pubkey: &Pubkey,
+    payer_pubkey: &Pubkey,
+    index: u32,
+) -> (Pubkey, u8) {
+    Pubkey::find_program_address(
+        &[
+            AGREEMENT_SEED,
+            payee_pubkey.as_ref(),
+            payer_pubkey.as_ref(),
+            &index.to_le_bytes(),
+        ],
+        program_id,
+    )
+}
+
 /// Rent Share Account state stored in the Agreement Account
 #[derive(BorshSerialize, BorshDeserialize, Debug)]
 pub struct RentShareAccount {
@@ -15,6 +99,64 @@ pub struct RentShareAccount {
     pub duration: u64,
     pub duration_unit: u8,
     pub remaining_payments: u64,
+    pub deposit_forfeit_on_early_termination: bool,
+    pub created_at: i64,
+    pub late_fee_bps: u16,
+    pub agreement_index: u32,
+    pub accepted_at: i64,
+    pub notice_period_seconds: u64,
+    pub notice_given_at: i64,
+    pub required_deposit: u64,
+    pub replenish_deposit_first: bool,
+    pub deposit_refunded: bool,
+    pub max_missed_payments: u8,
+    pub auto_terminate_on_default: bool,
+    pub deposit_interest_bps: u16,
+    pub accrued_interest: u64,
+    pub interest_periods_accrued: u64,
+    pub currency_decimals: u8,
+    pub currency_symbol: [u8; 8],
+    pub deposit_refund_destination: Pubkey,
+    pub amendment_count: u32,
+    pub defer_first_payment: bool,
+    pub proration_min_unit: u64,
+    pub document_hash: [u8; 32],
+    pub free_periods: u64,
+    pub total_rent_paid: u64,
+    pub total_late_fees_paid: u64,
+    pub max_prepay_periods: u64,
+    pub on_time_streak: u64,
+    pub reminder_lead_seconds: u64,
+    pub final_settlement_grace_seconds: u64,
+    pub settlement_started_at: i64,
+    pub termination_penalty_bps: u16,
+    pub termination_penalty_max: u64,
+    pub termination_penalty_paid: u64,
+    /// Bump seed of the agreement address, for clients only. The program signs for the address
+    /// just once, when creating it; lamports later leave the program owned account by debiting it
+    /// directly, which needs no signature.
+    pub bump: u8,
+    pub deposit_interest_paid: u64,
+    pub end_date_extension_seconds: i64,
+    pub prepay_first_period: bool,
+    pub escrowed_rent: u64,
+    pub overpay_to_deposit: bool,
+    pub platform_pubkey: Pubkey,
+    pub read_fee_lamports: u64,
+    pub observer_program: Pubkey,
+    pub observer_required: bool,
+    pub admin_pubkey: Pubkey,
+    pub frozen: bool,
+    pub assigned_payee: Pubkey,
+    pub assigned_until_period: u64,
+    pub late_fee_escalation_bps: u16,
+    pub late_fee_max: u64,
+    pub late_fee_credit: u64,
+    pub rent_change_cooldown_seconds: u64,
+    pub last_rent_change_at: i64,
+    pub non_refundable_deposit: u64,
+    pub termination_platform_fee: u64,
+    pub platform_fees_paid: u64,
 }
 
 impl Sealed for RentShareAccount {}
@@ -25,7 +167,277 @@ impl IsInitialized for RentShareAccount {
     }
 }
 
+/// Borsh backed `Pack` so spl style account loaders can read agreement accounts
+impl Pack for RentShareAccount {
+    const LEN: usize = RentShareAccount::LEN;
+
+    fn pack_into_slice(&self, dst: &mut [u8]) {
+        // `Pack::pack` only calls this with exactly `LEN` bytes, which always fits the account
+        let mut dst = dst;
+        self.serialize(&mut dst)
+            .expect("agreement account serializes into LEN bytes");
+    }
+
+    fn unpack_from_slice(src: &[u8]) -> Result<Self, ProgramError> {
+        Self::load(src)
+    }
+}
+
 impl RentShareAccount {
+    /// Serialized size of the account data
+    pub const LEN: usize = 1
+        + 32
+        + 32
+        + 8
+        + 8
+        + 8
+        + 1
+        + 8
+        + 1
+        + 8
+        + 2
+        + 4
+        + 8
+        + 8
+        + 8
+        + 8
+        + 1
+        + 1
+        + 1
+        + 1
+        + 2
+        + 8
+        + 8
+        + 1
+        + 8
+        + 32
+        + 4
+        + 1
+        + 8
+        + 32
+        + 8
+        + 8
+        + 8
+        + 8
+        + 8
+        + 8
+        + 8
+        + 8
+        + 2
+        + 8
+        + 8
+        + 1
+        + 8
+        + 8
+        + 1
+        + 8
+        + 1
+        + 32
+        + 8
+        + 32
+        + 1
+        + 32
+        + 1
+        + 32
+        + 8
+        + 2
+        + 8
+        + 8
+        + 8
+        + 8
+        + 8
+        + 8
+        + 8;
+
+    /// Deserialize the account from the start of `data`, ignoring any trailing bytes
+    pub fn load(data: &[u8]) -> Result<Self, ProgramError> {
+        Ok(Self::deserialize(&mut &data[..])?)
+    }
+
+    /// Concatenate agreements into a batch for client caching, each Borsh record prefixed with its
+    /// little-endian `u32` length
+    #[cfg(feature = "client")]
+    pub fn pack_many(accounts: &[RentShareAccount]) -> Vec<u8> {
+        let mut buf = Vec::with_capacity(accounts.len() * (4 + Self::LEN));
+        for account in accounts {
+            let record = account
+                .try_to_vec()
+                .expect("agreement serializes into a vector");
+            buf.extend_from_slice(&(record.len() as u32).to_le_bytes());
+            buf.extend_from_slice(&record);
+        }
+        buf
+    }
+
+    /// Split a batch written by `pack_many` back into agreements
+    #[cfg(feature = "client")]
+    pub fn unpack_many(data: &[u8]) -> Result<Vec<RentShareAccount>, ProgramError> {
+        let mut accounts = Vec::new();
+        let mut rest = data;
+        while !rest.is_empty() {
+            let len = rest
+                .get(..4)
+                .and_then(|prefix| prefix.try_into().ok())
+                .map(u32::from_le_bytes)
+                .ok_or(ProgramError::InvalidAccountData)? as usize;
+            let record = rest
+                .get(4..4 + len)
+                .ok_or(ProgramError::InvalidAccountData)?;
+            accounts.push(Self::try_from_slice(record)?);
+            rest = &rest[4 + len..];
+        }
+        Ok(accounts)
+    }
+
+    /// Whether `data` holds no agreement at all. Freshly allocated and reset accounts are all zero, and
+    /// an uninitialized status with any other byte set means the account was partially written
+    pub fn is_blank(data: &[u8]) -> bool {
+        data.iter().all(|byte| *byte == 0)
+    }
+
+    /// Validate and serialize the account into `data`, zeroing any trailing bytes so accounts
+    /// allocated larger than `LEN` have deterministic contents
+    pub fn save(&self, data: &mut [u8]) -> ProgramResult {
+        self.assert_valid_state()?;
+
+        // Serialize up front so an account allocated under an older, smaller `LEN` is rejected
+        // before any of its bytes are overwritten
+        let serialized = self.try_to_vec()?;
+        // Catches fields added without updating `LEN`, which would undersize new accounts
+        debug_assert_eq!(serialized.len(), Self::LEN);
+        if serialized.len() > data.len() {
+            msg!(
+                "[RentShare] Agreement account holds {} bytes but needs {}",
+                data.len(),
+                serialized.len()
+            );
+            return Err(ProgramError::AccountDataTooSmall);
+        }
+
+        let (used, remaining) = data.split_at_mut(serialized.len());
+        used.copy_from_slice(&serialized);
+        remaining.fill(0);
+        Ok(())
+    }
+
+    /// Check the invariants between the status and the payment and deposit fields, so a handler
+    /// that produced an inconsistent agreement fails instead of saving it
+    pub fn assert_valid_state(&self) -> ProgramResult {
+        let status = self.agreement_status()?;
+        let violation = if self.remaining_payments > self.duration {
+            Some("remaining payments exceed the duration")
+        } else if self.free_periods > self.duration {
+            Some("free periods exceed the duration")
+        } else if self.deposit_refunded && self.deposit > 0 {
+            Some("refunded deposit is still held")
+        } else {
+            match status {
+                AgreementStatus::PendingAcceptance
+                    if self.total_rent_paid > 0 || self.total_late_fees_paid > 0 =>
+                {
+                    Some("rent paid before acceptance")
+                }
+                AgreementStatus::PendingAcceptance if self.accepted_at != 0 => {
+                    Some("pending agreement has an acceptance time")
+                }
+                AgreementStatus::Active if self.remaining_payments == 0 => {
+                    Some("active agreement has no remaining payments")
+                }
+                AgreementStatus::Completed
+                | AgreementStatus::AwaitingSettlement
+                | AgreementStatus::Terminated
+                    if self.remaining_payments > 0 =>
+                {
+                    Some("ended agreement has remaining payments")
+                }
+                _ => None,
+            }
+        };
+
+        match violation {
+            Some(violation) => {
+                msg!(
+                    "[RentShare] Invalid agreement state {:?}: {}",
+                    status,
+                    violation
+                );
+                Err(ProgramError::InvalidAccountData)
+            }
+            None => Ok(()),
+        }
+    }
+
+    /// Move the agreement to `status`, logging the transition as a status event for indexers
+    pub fn set_status(&mut self, status: AgreementStatus, now: i64) -> ProgramResult {
+        let previous = self.agreement_status()?;
+        self.status = status as u8;
+
+        msg!(
+            "[RentShare] EVENT:STATUS:{:?}:{:?}:{}",
+            previous,
+            status,
+            now
+        );
+        Ok(())
+    }
+
+    pub fn is_active(&self) -> bool {
+        self.status == AgreementStatus::Active as u8
+    }
+
+    pub fn agreement_status(&self) -> Result<AgreementStatus, ProgramError> {
+        AgreementStatus::try_from(self.status)
+    }
+
+    /// Ensure the agreement can still be modified, returning the error specific to its status
+    pub fn require_active(&self) -> Result<(), ProgramError> {
+        self.require_status(&[AgreementStatus::Active])?;
+        Ok(())
+    }
+
+    /// Ensure the agreement is in one of the statuses an instruction permits, returning the
+    /// error specific to the current status otherwise
+    pub fn require_status(
+        &self,
+        allowed: &[AgreementStatus],
+    ) -> Result<AgreementStatus, ProgramError> {
+        let status = self.agreement_status()?;
+        if allowed.contains(&status) {
+            return Ok(status);
+        }
+
+        match status {
+            AgreementStatus::Uninitialized => {
+                msg!("[RentShare] Rent agreement account not initialized");
+                Err(ProgramError::UninitializedAccount)
+            }
+            AgreementStatus::Active => {
+                msg!("[RentShare] Rent agreement already active");
+                Err(ProgramError::InvalidAccountData)
+            }
+            AgreementStatus::Completed => {
+                msg!("[RentShare] Rent already paid in full");
+                Err(RentShareError::RentAlreadyPaidInFull.into())
+            }
+            AgreementStatus::Terminated => {
+                msg!("[RentShare] Rent agreement already terminated");
+                Err(RentShareError::RentAgreementTerminated.into())
+            }
+            AgreementStatus::PendingAcceptance => {
+                msg!("[RentShare] Rent agreement has not been accepted by the payee yet");
+                Err(RentShareError::AgreementNotAccepted.into())
+            }
+            AgreementStatus::AwaitingSettlement => {
+                msg!("[RentShare] Rent paid in full and awaiting final settlement");
+                Err(RentShareError::RentAlreadyPaidInFull.into())
+            }
+        }
+    }
+
+    pub fn is_pending_acceptance(&self) -> bool {
+        self.status == AgreementStatus::PendingAcceptance as u8
+    }
+
     pub fn is_complete(&self) -> bool {
         self.status == AgreementStatus::Completed as u8
     }
@@ -33,17 +445,1702 @@ impl RentShareAccount {
     pub fn is_terminated(&self) -> bool {
         self.status == AgreementStatus::Terminated as u8
     }
+
+    pub fn is_awaiting_settlement(&self) -> bool {
+        self.status == AgreementStatus::AwaitingSettlement as u8
+    }
+
+    /// Whether `key` is one of the parties to the agreement: the payee, the payer, the admin when
+    /// one is set, or the payee rent is assigned to while the assignment lasts. Lease templates
+    /// are not recorded on the agreement, so their authority is not a party.
+    pub fn is_party(&self, key: &Pubkey) -> bool {
+        *key == self.payee_pubkey
+            || *key == self.payer_pubkey
+            || *key == self.active_payee()
+            || (self.admin_pubkey != Pubkey::default() && *key == self.admin_pubkey)
+    }
+
+    /// Payee the next payment goes to: the assigned payee while the next period falls before the end
+    /// of a rent assignment, otherwise the payee recorded at initialization
+    pub fn active_payee(&self) -> Pubkey {
+        if self.payments_made() < self.assigned_until_period {
+            self.assigned_payee
+        } else {
+            self.payee_pubkey
+        }
+    }
+
+    /// Whether paying the next `periods` periods would cover periods both inside and after the
+    /// rent assignment
+    pub fn spans_assignment_end(&self, periods: u64) -> bool {
+        let payments_made = self.payments_made();
+        payments_made < self.assigned_until_period
+            && payments_made.saturating_add(periods) > self.assigned_until_period
+    }
+
+    /// Status once the final payment is made. A held deposit keeps the agreement awaiting
+    /// settlement for `final_settlement_grace_seconds` so the refund can still land.
+    pub fn final_payment_status(&self) -> AgreementStatus {
+        if self.final_settlement_grace_seconds > 0 && self.deposit > 0 && !self.deposit_refunded {
+            AgreementStatus::AwaitingSettlement
+        } else {
+            AgreementStatus::Completed
+        }
+    }
+
+    /// Whether the settlement grace that started with the final payment has elapsed by `now`
+    pub fn settlement_grace_elapsed(&self, now: i64) -> bool {
+        let grace = i64::try_from(self.final_settlement_grace_seconds).unwrap_or(i64::MAX);
+        now >= self.settlement_started_at.saturating_add(grace)
+    }
+
+    /// Number of rent payments made so far
+    pub fn payments_made(&self) -> u64 {
+        self.duration.saturating_sub(self.remaining_payments)
+    }
+
+    /// Whether the deposit was returned to the payer, which distinguishes a refunded deposit
+    /// from a forfeited or genuinely zero one
+    pub fn is_deposit_refunded(&self) -> bool {
+        self.deposit_refunded
+    }
+
+    /// Where the deposit stands relative to the parties
+    pub fn deposit_status(&self) -> DepositStatus {
+        if self.deposit_refunded {
+            DepositStatus::Refunded
+        } else if self.deposit > 0 {
+            DepositStatus::Held
+        } else if self.required_deposit > 0 && !self.is_pending_acceptance() {
+            DepositStatus::Forfeited
+        } else {
+            DepositStatus::None
+        }
+    }
+
+    /// Whether the deposit held has reached the required deposit, which a staged deposit must do
+    /// before the agreement can be accepted
+    pub fn is_deposit_fully_collected(&self) -> bool {
+        self.deposit >= self.required_deposit
+    }
+
+    /// Part of the held deposit returned to the payer when it is refunded
+    pub fn refundable_deposit(&self) -> u64 {
+        self.deposit.saturating_sub(self.non_refundable_deposit)
+    }
+
+    /// Lamports needed to restore the deposit to its required level before rent is applied
+    pub fn deposit_shortfall(&self) -> u64 {
+        if self.replenish_deposit_first {
+            self.required_deposit.saturating_sub(self.deposit)
+        } else {
+            0
+        }
+    }
+
+    /// Currency symbol for display with the zero padding removed
+    pub fn currency_symbol(&self) -> String {
+        String::from_utf8_lossy(&self.currency_symbol)
+            .trim_end_matches('\0')
+            .to_string()
+    }
+
+    /// Count a term change agreed by both parties and log it as an amendment event
+    pub fn record_amendment(&mut self, amendment: Amendment) -> ProgramResult {
+        self.amendment_count = self
+            .amendment_count
+            .checked_add(1)
+            .ok_or(RentShareError::ArithmeticOverflow)?;
+
+        msg!(
+            "[RentShare] EVENT:Amendment type={:?} count={}",
+            amendment,
+            self.amendment_count
+        );
+        Ok(())
+    }
+
+    /// Typed view of the agreement including derived scheduling values
+    pub fn snapshot(&self, now: i64) -> Result<AgreementSnapshot, ProgramError> {
+        let status = AgreementStatus::try_from(self.status)?;
+        let (next_due, lease_end) = if self.is_active() {
+            (
+                Some(self.next_due_timestamp(now)?),
+                Some(self.lease_end_timestamp(now)?),
+            )
+        } else {
+            (None, None)
+        };
+
+        Ok(AgreementSnapshot {
+            status,
+            payee_pubkey: self.payee_pubkey,
+            payer_pubkey: self.payer_pubkey,
+            deposit: self.deposit,
+            rent_amount: self.rent_amount,
+            duration: self.duration,
+            duration_unit: Duration::try_from(self.duration_unit)?,
+            remaining_payments: self.remaining_payments,
+            payments_made: self.payments_made(),
+            next_due,
+            lease_end,
+            created_at: self.created_at,
+            accepted_at: self.accepted_at,
+        })
+    }
+
+    /// Action the agreement is waiting on at `now`, for guiding each party through the lease
+    pub fn next_action(&self, now: i64) -> Result<NextAction, ProgramError> {
+        Ok(match self.agreement_status()? {
+            AgreementStatus::Uninitialized => NextAction::None,
+            AgreementStatus::PendingAcceptance => NextAction::PayeeMustAccept,
+            AgreementStatus::Completed
+            | AgreementStatus::Terminated
+            | AgreementStatus::AwaitingSettlement => NextAction::Complete,
+            AgreementStatus::Active => {
+                if self.next_due_timestamp(now)? <= now {
+                    NextAction::PayerMustPay
+                } else if self.notice_given_at != 0 && self.notice_period_elapsed(now)? {
+                    NextAction::PayerMayTerminate
+                } else {
+                    NextAction::None
+                }
+            }
+        })
+    }
+
+    /// Timestamp the payment schedule starts from, once the payee accepted the agreement
+    pub fn schedule_start(&self, now: i64) -> Result<i64, ProgramError> {
+        Self::validate_timestamp(self.created_at, now)?;
+        Self::validate_timestamp(self.accepted_at, now)?;
+
+        if self.accepted_at < self.created_at {
+            msg!(
+                "[RentShare] Agreement accepted at {} before it was created at {}",
+                self.accepted_at,
+                self.created_at
+            );
+            return Err(RentShareError::InvalidTimestamp.into());
+        }
+
+        Ok(self.accepted_at)
+    }
+
+    /// Timestamp the lease ends: the end of its final period, pushed out by any end date extension
+    pub fn lease_end_timestamp(&self, now: i64) -> Result<i64, ProgramError> {
+        let schedule_start = self.schedule_start(now)?;
+        self.due_timestamp(schedule_start, self.duration)?
+            .checked_add(self.end_date_extension_seconds)
+            .ok_or_else(|| RentShareError::InvalidTimestamp.into())
+    }
+
+    /// Timestamp the next rent payment is due, measured in periods from the schedule start
+    pub fn next_due_timestamp(&self, now: i64) -> Result<i64, ProgramError> {
+        let schedule_start = self.schedule_start(now)?;
+        self.due_timestamp(schedule_start, self.due_period(self.payments_made()))
+    }
+
+    /// Seconds from `now` until the next rent payment is due, negative once it is overdue
+    pub fn seconds_until_due(&self, now: i64) -> Result<i64, ProgramError> {
+        self.next_due_timestamp(now)?
+            .checked_sub(now)
+            .ok_or_else(|| RentShareError::InvalidTimestamp.into())
+    }
+
+    /// Period whose start the payment for the zero based `payment` is due at. Agreements that
+    /// defer the first payment collect each payment one period later.
+    fn due_period(&self, payment: u64) -> u64 {
+        if self.defer_first_payment {
+            payment.saturating_add(1)
+        } else {
+            payment
+        }
+    }
+
+    /// Whether a payment at `now` is early because the first payment waits one period after signing
+    pub fn is_before_first_payment(&self, now: i64) -> Result<bool, ProgramError> {
+        Ok(self.payments_made() == 0 && now < self.next_due_timestamp(now)?)
+    }
+
+    /// Timestamp the zero based `period` starts, on the same calendar day as the schedule start
+    fn due_timestamp(&self, schedule_start: i64, period: u64) -> Result<i64, ProgramError> {
+        let offset = Duration::try_from(self.duration_unit)?.seconds_for(period, schedule_start)?;
+        schedule_start
+            .checked_add(offset)
+            .ok_or_else(|| RentShareError::InvalidTimestamp.into())
+    }
+
+    /// Due timestamp and amount of every payment in the agreement, for rendering the full schedule.
+    /// Free periods and a first period prepaid with the deposit are due with an amount of 0.
+    #[cfg(feature = "client")]
+    pub fn payment_schedule(&self) -> Result<Vec<(i64, u64)>, ProgramError> {
+        if self.is_pending_acceptance() {
+            return Err(RentShareError::AgreementNotAccepted.into());
+        }
+
+        (0..self.duration)
+            .map(|period| {
+                let prepaid = self.prepay_first_period && period == 0;
+                let amount = if period < self.free_periods || prepaid {
+                    0
+                } else {
+                    self.rent_amount
+                };
+                Ok((
+                    self.due_timestamp(self.accepted_at, self.due_period(period))?,
+                    amount,
+                ))
+            })
+            .collect()
+    }
+
+    /// Number of periods of the agreement that have fully elapsed by `now`
+    fn elapsed_periods(&self, now: i64) -> Result<u64, ProgramError> {
+        let schedule_start = self.schedule_start(now)?;
+
+        // Calendar periods vary in length, so search for the last period start at or before `now`
+        let (mut low, mut high) = (0, self.duration);
+        while low < high {
+            let mid = high - (high - low) / 2;
+            if self.due_timestamp(schedule_start, mid)? <= now {
+                low = mid;
+            } else {
+                high = mid - 1;
+            }
+        }
+        Ok(low)
+    }
+
+    /// Number of periods that have fully elapsed without being paid
+    pub fn overdue_periods(&self, now: i64) -> Result<u64, ProgramError> {
+        Ok(self
+            .elapsed_periods(now)?
+            .saturating_sub(self.payments_made()))
+    }
+
+    /// Interest owed on the held deposit for a single period, rounded down
+    pub fn deposit_interest_per_period(&self) -> Result<u64, ProgramError> {
+        if self.deposit_interest_bps > MAX_BASIS_POINTS {
+            msg!(
+                "[RentShare] Deposit interest exceeds 100%: {} bps",
+                self.deposit_interest_bps
+            );
+            return Err(RentShareError::InvalidAgreementTerms.into());
+        }
+
+        fees::bps_of(self.deposit, self.deposit_interest_bps, false)
+    }
+
+    /// Add interest on the held deposit for every period elapsed since the last accrual,
+    /// returning the newly accrued amount
+    pub fn accrue_deposit_interest(&mut self, now: i64) -> Result<u64, ProgramError> {
+        let elapsed_periods = self.elapsed_periods(now)?;
+        let periods = elapsed_periods.saturating_sub(self.interest_periods_accrued);
+        let interest = math::mul(self.deposit_interest_per_period()?, periods)?;
+
+        self.accrued_interest = math::add(self.accrued_interest, interest)?;
+        self.interest_periods_accrued = elapsed_periods;
+        Ok(interest)
+    }
+
+    /// Overdue periods at which the agreement is in default, falling back to
+    /// `DEFAULT_MAX_MISSED_PAYMENTS` when the terms leave it unset
+    pub fn missed_payments_threshold(&self) -> u64 {
+        if self.max_missed_payments == 0 {
+            DEFAULT_MAX_MISSED_PAYMENTS as u64
+        } else {
+            self.max_missed_payments as u64
+        }
+    }
+
+    /// Risk signal from 0 (heavily overdue) to 100 (paid in full). Each overdue period costs 20 points
+    /// up to 80, and up to 10 points each are lost for payments made since the on time streak broke
+    /// and for the share of the lease still owed.
+    pub fn health_score(&self, now: i64) -> Result<u8, ProgramError> {
+        if self.is_complete() || self.is_awaiting_settlement() {
+            return Ok(100);
+        }
+
+        let overdue_penalty = self.overdue_periods(now)?.saturating_mul(20).min(80);
+
+        let payments_made = self.payments_made();
+        let streak_penalty = (10 * (payments_made - self.on_time_streak.min(payments_made)))
+            .checked_div(payments_made)
+            .unwrap_or(0);
+        let remaining_penalty = (10 * self.remaining_payments.min(self.duration))
+            .checked_div(self.duration)
+            .unwrap_or(0);
+
+        Ok(100u64.saturating_sub(overdue_penalty + streak_penalty + remaining_penalty) as u8)
+    }
+
+    /// Reminder frontends should surface at `now` for the next payment. Payments are upcoming within
+    /// `reminder_lead_seconds` of their due date and overdue once it has passed.
+    pub fn reminder(&self, now: i64) -> Result<Option<Reminder>, ProgramError> {
+        if self.remaining_payments == 0 {
+            return Ok(None);
+        }
+
+        let due_at = self.next_due_timestamp(now)?;
+        if now > due_at {
+            return Ok(Some(Reminder::Overdue { due_at }));
+        }
+
+        let lead = i64::try_from(self.reminder_lead_seconds).unwrap_or(i64::MAX);
+        if due_at - now <= lead {
+            Ok(Some(Reminder::Upcoming { due_at }))
+        } else {
+            Ok(None)
+        }
+    }
+
+    /// Whether the payer has missed enough payments by `now` to be in default
+    pub fn is_in_default(&self, now: i64) -> Result<bool, ProgramError> {
+        Ok(self.overdue_periods(now)? >= self.missed_payments_threshold())
+    }
+
+    /// Whether the agreement terminates itself by `now`, forfeiting the deposit, rather than
+    /// waiting for the payee to act on the default
+    pub fn should_auto_terminate(&self, now: i64) -> Result<bool, ProgramError> {
+        Ok(self.auto_terminate_on_default && self.is_in_default(now)?)
+    }
+
+    /// Late fee charged for each overdue period, rounded up so a fee is always charged when
+    /// both `late_fee_bps` and `rent_amount` are non-zero
+    pub fn late_fee(&self) -> Result<u64, ProgramError> {
+        if self.late_fee_bps > MAX_BASIS_POINTS {
+            msg!(
+                "[RentShare] Late fee exceeds 100%: {} bps",
+                self.late_fee_bps
+            );
+            return Err(RentShareError::InvalidAgreementTerms.into());
+        }
+
+        fees::bps_of(self.rent_amount, self.late_fee_bps, true)
+    }
+
+    /// Late fee for a period paid `periods_overdue` periods after its due date. Every period
+    /// overdue beyond the first adds `late_fee_escalation_bps` of the base fee, and the result is
+    /// capped at `late_fee_max` when non-zero.
+    pub fn escalated_late_fee(&self, periods_overdue: u64) -> Result<u64, ProgramError> {
+        let base_fee = self.late_fee()?;
+        let escalation = math::mul(
+            fees::bps_of(base_fee, self.late_fee_escalation_bps, true)?,
+            periods_overdue.saturating_sub(1),
+        )?;
+        let fee = math::add(base_fee, escalation)?;
+
+        if self.late_fee_max > 0 {
+            Ok(fee.min(self.late_fee_max))
+        } else {
+            Ok(fee)
+        }
+    }
+
+    /// Late fees still owed for the next `periods` periods at `now`, after the late fees already
+    /// paid with `PayLateFees`
+    pub fn late_fees_due(&self, periods: u64, now: i64) -> Result<u64, ProgramError> {
+        math::sub(
+            self.late_fees_charged(periods, now)?,
+            self.late_fee_credit_applied(periods, now)?,
+        )
+    }
+
+    /// Part of the late fee credit paying the next `periods` periods at `now` uses up
+    pub fn late_fee_credit_applied(&self, periods: u64, now: i64) -> Result<u64, ProgramError> {
+        Ok(self
+            .late_fee_credit
+            .min(self.late_fees_charged(periods, now)?))
+    }
+
+    /// Late fees accrued on every overdue period at `now` that have not been paid yet
+    pub fn accrued_late_fees(&self, now: i64) -> Result<u64, ProgramError> {
+        self.late_fees_due(self.overdue_periods(now)?, now)
+    }
+
+    /// Late fees charged on the next `periods` periods at `now`. The oldest overdue periods are
+    /// paid first and are the most overdue, after free periods absorb the earliest of them.
+    fn late_fees_charged(&self, periods: u64, now: i64) -> Result<u64, ProgramError> {
+        let overdue_periods = self.overdue_periods(now)?;
+        let late_periods = overdue_periods.min(periods);
+        (self.free_periods_within(periods).min(late_periods)..late_periods).try_fold(
+            0,
+            |total, oldest_first| {
+                math::add(
+                    total,
+                    self.escalated_late_fee(overdue_periods - oldest_first)?,
+                )
+            },
+        )
+    }
+
+    /// Lamports the payee has netted from the agreement: rent, late fees and termination penalties
+    /// received, less deposit interest the payee paid out and platform fees charged on the agreement
+    pub fn landlord_net_received(&self) -> Result<u64, ProgramError> {
+        let received = math::add(
+            math::add(self.total_rent_paid, self.total_late_fees_paid)?,
+            self.termination_penalty_paid,
+        )?;
+        let deducted = math::add(self.deposit_interest_paid, self.platform_fees_paid)?;
+        // Interest on a large deposit can outgrow the rent of a short lease, so the net floors at 0
+        Ok(received.saturating_sub(deducted))
+    }
+
+    /// Penalty owed to the payee for terminating early: `termination_penalty_bps` of the rent still
+    /// owed, rounded down and capped at `termination_penalty_max` lamports when it is non-zero
+    pub fn termination_penalty(&self) -> Result<u64, ProgramError> {
+        if self.termination_penalty_bps > MAX_BASIS_POINTS {
+            msg!(
+                "[RentShare] Termination penalty exceeds 100%: {} bps",
+                self.termination_penalty_bps
+            );
+            return Err(RentShareError::InvalidAgreementTerms.into());
+        }
+
+        let remaining_rent = math::mul(
+            self.rent_amount,
+            self.charged_periods(self.remaining_payments),
+        )?;
+        let penalty = fees::bps_of(remaining_rent, self.termination_penalty_bps, false)?;
+
+        if self.termination_penalty_max > 0 {
+            Ok(penalty.min(self.termination_penalty_max))
+        } else {
+            Ok(penalty)
+        }
+    }
+
+    /// Total owed at `now` to pay the next `periods` periods: the rent, late fees for the periods
+    /// already overdue, and any shortfall restoring the deposit
+    pub fn amount_due(&self, periods: u64, now: i64) -> Result<u64, ProgramError> {
+        math::add(self.rent_due(periods, now)?, self.deposit_shortfall())
+    }
+
+    /// Amount a single period `PayRent` must carry at `now`: nothing for a free period, otherwise
+    /// the rent in effect after any `UpdateRentAmount` plus any late fee, and any shortfall
+    /// restoring the deposit. The schedule starts at acceptance, so the first period is always a
+    /// whole period and is charged in full; only refunds are prorated.
+    pub fn expected_payment_for_current_period(&self, now: i64) -> Result<u64, ProgramError> {
+        self.amount_due(1, now)
+    }
+
+    /// Rent and late fees for the next `periods` periods at `now`, excluding free periods
+    fn rent_due(&self, periods: u64, now: i64) -> Result<u64, ProgramError> {
+        let late_fees = self.late_fees_due(periods, now)?;
+        math::add(
+            math::mul(self.rent_amount, self.charged_periods(periods))?,
+            late_fees,
+        )
+    }
+
+    /// Number of the next `periods` periods that fall within the free periods at the lease start
+    fn free_periods_within(&self, periods: u64) -> u64 {
+        self.free_periods
+            .saturating_sub(self.payments_made())
+            .min(periods)
+    }
+
+    /// Number of the next `periods` periods that are charged rent
+    pub fn charged_periods(&self, periods: u64) -> u64 {
+        periods - self.free_periods_within(periods)
+    }
+
+    /// Number of the next `periods` periods that are charged a late fee at `now`. Overdue periods
+    /// come first, so free periods are the first to absorb them.
+    pub fn late_charged_periods(&self, periods: u64, now: i64) -> Result<u64, ProgramError> {
+        let late_periods = self.overdue_periods(now)?.min(periods);
+        Ok(late_periods.saturating_sub(self.free_periods_within(periods)))
+    }
+
+    /// Rent already paid for time after `now`, prorated by the second. This covers the unused
+    /// part of the current period, as a share of that calendar period's actual length, and any
+    /// periods paid in advance. The refund is rounded to the nearest multiple of
+    /// `proration_min_unit` lamports, halves rounding up, and never exceeds the rent paid for the
+    /// periods it covers.
+    pub fn prorated_refund(&self, now: i64) -> Result<u64, ProgramError> {
+        let schedule_start = self.schedule_start(now)?;
+        let payments_made = self.payments_made();
+        let paid_through = self.due_timestamp(schedule_start, payments_made)?;
+        if payments_made == 0 || paid_through <= now {
+            return Ok(0);
+        }
+
+        // The period under way at `now` is paid for, since `now` is before the paid through date
+        let current_period = self.elapsed_periods(now)?.min(payments_made - 1);
+        let current_start = self.due_timestamp(schedule_start, current_period)?;
+        let current_end = self.due_timestamp(schedule_start, current_period + 1)?;
+        let current_seconds = (current_end - current_start) as u128;
+        let current_unused = (current_end - now).min(current_end - current_start) as u128;
+        let advance_periods = (payments_made - current_period - 1) as u128;
+        let min_unit = self.proration_min_unit.max(1) as u128;
+
+        let numerator =
+            self.rent_amount as u128 * (advance_periods * current_seconds + current_unused);
+        let denominator = current_seconds * min_unit;
+        let refund = (numerator + denominator / 2) / denominator * min_unit;
+
+        // Rounding up to a whole unit must not refund more than was paid for the unused periods
+        let unused_periods = advance_periods + (current_unused > 0) as u128;
+        // Free periods at the lease start were never paid for
+        let paid_periods = self.payments_made().saturating_sub(self.free_periods) as u128;
+        let paid_for_unused = self.rent_amount as u128 * unused_periods.min(paid_periods);
+
+        u64::try_from(refund.min(paid_for_unused)).map_err(|_| ProgramError::InvalidAccountData)
+    }
+
+    /// Amounts that settle the lease if it is terminated at `now`: overdue rent with late fees and
+    /// the termination penalty owed by the payer, and rent already paid for time after `now`
+    /// credited back to them
+    pub fn payoff(&self, now: i64) -> Result<Payoff, ProgramError> {
+        let overdue_periods = self.overdue_periods(now)?;
+
+        Ok(Payoff {
+            overdue_rent: self.rent_due(overdue_periods, now)?,
+            prorated_credit: self.prorated_refund(now)?,
+            termination_penalty: self.termination_penalty()?,
+        })
+    }
+
+    /// Whether the payer gave notice and the notice period has elapsed by `now`.
+    /// Agreements without a notice period can always be terminated.
+    pub fn notice_period_elapsed(&self, now: i64) -> Result<bool, ProgramError> {
+        if self.notice_period_seconds == 0 {
+            return Ok(true);
+        }
+
+        if self.notice_given_at == 0 {
+            return Ok(false);
+        }
+
+        Self::validate_timestamp(self.notice_given_at, now)?;
+
+        let notice_period_end = i64::try_from(self.notice_period_seconds)
+            .ok()
+            .and_then(|seconds| self.notice_given_at.checked_add(seconds))
+            .ok_or(RentShareError::InvalidTimestamp)?;
+        Ok(now >= notice_period_end)
+    }
+
+    /// Reject timestamps from the future (beyond clock skew) or implausibly far in the past
+    pub fn validate_timestamp(timestamp: i64, now: i64) -> Result<(), ProgramError> {
+        if timestamp > now.saturating_add(MAX_CLOCK_SKEW_SECONDS) {
+            msg!(
+                "[RentShare] Timestamp {} is in the future. Current time: {}",
+                timestamp,
+                now
+            );
+            return Err(RentShareError::InvalidTimestamp.into());
+        }
+
+        if timestamp < now.saturating_sub(MAX_TIMESTAMP_AGE_SECONDS) {
+            msg!(
+                "[RentShare] Timestamp {} is too old. Current time: {}",
+                timestamp,
+                now
+            );
+            return Err(RentShareError::InvalidTimestamp.into());
+        }
+
+        Ok(())
+    }
 }
 
-#[derive(BorshSerialize, BorshDeserialize, Debug)]
+/// Standard lease terms stored in a program owned account, so clients initializing from it only send
+/// the parties, amounts and any overrides
+#[derive(BorshSerialize, BorshDeserialize, Debug, Default)]
+pub struct LeaseTemplate {
+    pub is_initialized: bool,
+    /// Signer allowed to write the template
+    pub authority: Pubkey,
+    pub duration: u64,
+    pub duration_unit: u8,
+    pub late_fee_bps: u16,
+    pub notice_period_seconds: u64,
+    pub termination_penalty_bps: u16,
+    pub termination_penalty_max: u64,
+}
+
+impl LeaseTemplate {
+    /// Serialized size of the template account data
+    pub const LEN: usize = 1 + 32 + 8 + 1 + 2 + 8 + 2 + 8;
+
+    /// Deserialize a template, requiring the exact template size so agreement accounts are never
+    /// mistaken for one
+    pub fn load(data: &[u8]) -> Result<Self, ProgramError> {
+        if data.len() != Self::LEN {
+            msg!(
+                "[RentShare] Lease template account data size incorrect: {}",
+                data.len()
+            );
+            return Err(ProgramError::InvalidAccountData);
+        }
+
+        Ok(Self::deserialize(&mut &data[..])?)
+    }
+
+    pub fn save(&self, data: &mut [u8]) -> ProgramResult {
+        let mut data = data;
+        self.serialize(&mut data)?;
+        Ok(())
+    }
+}
+
+/// Typed, read-only view of a rent agreement for off-chain consumers
+#[derive(Debug, PartialEq)]
+pub struct AgreementSnapshot {
+    pub status: AgreementStatus,
+    pub payee_pubkey: Pubkey,
+    pub payer_pubkey: Pubkey,
+    pub deposit: u64,
+    pub rent_amount: u64,
+    pub duration: u64,
+    pub duration_unit: Duration,
+    pub remaining_payments: u64,
+    pub payments_made: u64,
+    /// Timestamp the next payment is due, only set for active agreements
+    pub next_due: Option<i64>,
+    /// Timestamp the lease ends, only set for active agreements
+    pub lease_end: Option<i64>,
+    pub created_at: i64,
+    pub accepted_at: i64,
+}
+
+/// Settlement of a lease terminated early, as computed by `RentShareAccount::payoff`
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Payoff {
+    /// Rent and late fees for periods elapsed without payment
+    pub overdue_rent: u64,
+    /// Rent already paid for time after termination
+    pub prorated_credit: u64,
+    /// Penalty for ending the lease before its final payment
+    pub termination_penalty: u64,
+}
+
+/// Settlement state of the deposit
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum DepositStatus {
+    /// The agreement never required a deposit
+    None,
+    Held,
+    Refunded,
+    Forfeited,
+}
+
+/// Term change signed by both parties
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Amendment {
+    RentAmountUpdate,
+    Extension,
+    TenantTransfer,
+    EndDateExtension,
+}
+
+/// Next step required from either party, derived from the agreement status, schedule and clock
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum NextAction {
+    /// A rent payment is due or overdue
+    PayerMustPay,
+    /// The agreement is waiting for the payee to accept its terms
+    PayeeMustAccept,
+    /// The notice period has elapsed and the payer can terminate early
+    PayerMayTerminate,
+    /// The agreement has ended
+    Complete,
+    /// Nothing is required until the next payment comes due
+    None,
+}
+
+/// Reminder for the next rent payment
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Reminder {
+    /// The payment is due at `due_at`, within the reminder lead time
+    Upcoming { due_at: i64 },
+    /// The payment due at `due_at` has not been made
+    Overdue { due_at: i64 },
+}
+
+#[derive(BorshSerialize, BorshDeserialize, Debug, Copy, Clone, PartialEq)]
 pub enum Duration {
     Months = 0,
+    Years,
+}
+
+impl Duration {
+    /// Shortest lease accepted for this unit
+    pub fn min_duration(&self) -> u64 {
+        match self {
+            Duration::Months => MIN_DURATION_MONTHS,
+            Duration::Years => MIN_DURATION_YEARS,
+        }
+    }
+
+    /// Longest lease accepted for this unit
+    pub fn max_duration(&self) -> u64 {
+        match self {
+            Duration::Months => MAX_DURATION_MONTHS,
+            Duration::Years => MAX_DURATION_YEARS,
+        }
+    }
+
+    /// Seconds from `anchor` to the same time of day `count` units later on the calendar. Days
+    /// past the end of a shorter month land on its last day, so a lease anchored on January 31st
+    /// is due February 28th (or 29th in leap years) and then March 31st.
+    pub fn seconds_for(&self, count: u64, anchor: i64) -> Result<i64, ProgramError> {
+        let months_per_unit = match self {
+            Duration::Months => 1,
+            Duration::Years => 12,
+        };
+        let months = i64::try_from(count)
+            .ok()
+            .and_then(|count| count.checked_mul(months_per_unit))
+            .ok_or(RentShareError::InvalidTimestamp)?;
+
+        let (year, month, day) = civil_from_days(anchor.div_euclid(SECONDS_PER_DAY));
+        let month_index = months
+            .checked_add(month as i64 - 1)
+            .ok_or(RentShareError::InvalidTimestamp)?;
+        let target_year = year
+            .checked_add(month_index.div_euclid(12))
+            .ok_or(RentShareError::InvalidTimestamp)?;
+        let target_month = month_index.rem_euclid(12) as u32 + 1;
+        let target_day = day.min(days_in_month(target_year, target_month));
+
+        days_from_civil(target_year, target_month, target_day)
+            .checked_mul(SECONDS_PER_DAY)
+            .and_then(|start_of_day| start_of_day.checked_add(anchor.rem_euclid(SECONDS_PER_DAY)))
+            .and_then(|target| target.checked_sub(anchor))
+            .ok_or_else(|| RentShareError::InvalidTimestamp.into())
+    }
+}
+
+fn is_leap_year(year: i64) -> bool {
+    (year % 4 == 0 && year % 100 != 0) || year % 400 == 0
 }
 
-#[derive(Copy, Clone)]
+fn days_in_month(year: i64, month: u32) -> u32 {
+    match month {
+        2 if is_leap_year(year) => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    }
+}
+
+/// Days since the unix epoch of a proleptic Gregorian date
+fn days_from_civil(year: i64, month: u32, day: u32) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year.rem_euclid(400);
+    let month_from_march = (month as i64 + 9) % 12;
+    let day_of_year = (153 * month_from_march + 2) / 5 + day as i64 - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * 146_097 + day_of_era - 719_468
+}
+
+/// Proleptic Gregorian date of a day count since the unix epoch
+fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let days = days + 719_468;
+    let era = days.div_euclid(146_097);
+    let day_of_era = days.rem_euclid(146_097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1_460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_from_march = (5 * day_of_year + 2) / 153;
+    let day = (day_of_year - (153 * month_from_march + 2) / 5 + 1) as u32;
+    let month = if month_from_march < 10 {
+        month_from_march + 3
+    } else {
+        month_from_march - 9
+    } as u32;
+    let year = year_of_era + era * 400 + if month <= 2 { 1 } else { 0 };
+    (year, month, day)
+}
+
+impl TryFrom<u8> for Duration {
+    type Error = ProgramError;
+
+    fn try_from(value: u8) -> Result<Self, Self::Error> {
+        match value {
+            0 => Ok(Duration::Months),
+            1 => Ok(Duration::Years),
+            _ => {
+                msg!("[RentShare] Unknown duration unit: {}", value);
+                Err(ProgramError::InvalidAccountData)
+            }
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
 pub enum AgreementStatus {
     Uninitialized = 0,
     Active,
     Completed,
     Terminated,
+    PendingAcceptance,
+    AwaitingSettlement,
+}
+
+impl TryFrom<u8> for AgreementStatus {
+    type Error = ProgramError;
+
+    fn try_from(value: u8) -> Result<Self, Self::Error> {
+        match value {
+            0 => Ok(AgreementStatus::Uninitialized),
+            1 => Ok(AgreementStatus::Active),
+            2 => Ok(AgreementStatus::Completed),
+            3 => Ok(AgreementStatus::Terminated),
+            4 => Ok(AgreementStatus::PendingAcceptance),
+            5 => Ok(AgreementStatus::AwaitingSettlement),
+            _ => {
+                msg!("[RentShare] Unknown rent agreement status: {}", value);
+                Err(ProgramError::InvalidAccountData)
+            }
+        }
+    }
+}
+
+/// Agreements shared by the unit tests of every module, so their terms cannot drift apart
+#[cfg(test)]
+pub(crate) mod test_fixtures {
+    use super::*;
+    use crate::instruction::AgreementTerms;
+
+    pub(crate) const NOW: i64 = 1_650_000_000;
+    pub(crate) const RENT: u64 = 1_000_000;
+
+    /// Terms of a 12 month lease at `RENT` per month between new parties
+    pub(crate) fn monthly_terms() -> AgreementTerms {
+        AgreementTerms {
+            payee_pubkey: Pubkey::new_unique(),
+            payer_pubkey: Pubkey::new_unique(),
+            rent_amount: RENT,
+            duration: 12,
+            duration_unit: Duration::Months as u8,
+            ..AgreementTerms::default()
+        }
+    }
+
+    /// `monthly_terms` accepted at `NOW` with the first payment due at signing
+    pub(crate) fn active_agreement() -> RentShareAccount {
+        let terms = monthly_terms();
+        let mut rent_data = RentShareAccount::load(&[0; RentShareAccount::LEN]).unwrap();
+        rent_data.status = AgreementStatus::Active as u8;
+        rent_data.payee_pubkey = terms.payee_pubkey;
+        rent_data.payer_pubkey = terms.payer_pubkey;
+        rent_data.rent_amount = terms.rent_amount;
+        rent_data.duration = terms.duration;
+        rent_data.duration_unit = terms.duration_unit;
+        rent_data.remaining_payments = terms.duration;
+        rent_data.created_at = NOW;
+        rent_data.accepted_at = NOW;
+        rent_data.max_prepay_periods = terms.duration;
+        rent_data.deposit_refund_destination = terms.payer_pubkey;
+        rent_data.currency_decimals = SOL_DECIMALS;
+        rent_data.currency_symbol = SOL_SYMBOL;
+        rent_data
+    }
+
+    /// `active_agreement` moved to `status`, with the payments and acceptance time it implies
+    pub(crate) fn agreement_in(status: AgreementStatus) -> RentShareAccount {
+        let mut rent_data = active_agreement();
+        rent_data.status = status as u8;
+        match status {
+            AgreementStatus::Uninitialized | AgreementStatus::PendingAcceptance => {
+                rent_data.accepted_at = 0;
+            }
+            AgreementStatus::Completed
+            | AgreementStatus::Terminated
+            | AgreementStatus::AwaitingSettlement => rent_data.remaining_payments = 0,
+            AgreementStatus::Active => {}
+        }
+        rent_data
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::test_fixtures::*;
+    use super::*;
+
+    #[test]
+    fn len_matches_the_serialized_size() {
+        let mut rent_data = active_agreement();
+        rent_data.deposit = u64::MAX;
+        rent_data.document_hash = [0xff; 32];
+        rent_data.platform_pubkey = Pubkey::new_unique();
+        rent_data.observer_program = Pubkey::new_unique();
+        rent_data.admin_pubkey = Pubkey::new_unique();
+        rent_data.assigned_payee = Pubkey::new_unique();
+        rent_data.termination_platform_fee = u64::MAX;
+        rent_data.platform_fees_paid = u64::MAX;
+
+        assert_eq!(rent_data.try_to_vec().unwrap().len(), RentShareAccount::LEN);
+        assert_eq!(AGREEMENT_ACCOUNT_SPACE, RentShareAccount::LEN);
+    }
+
+    #[test]
+    fn pack_round_trips_an_initialized_agreement() {
+        let rent_data = active_agreement();
+        let serialized = rent_data.try_to_vec().unwrap();
+
+        let mut data = vec![0; RentShareAccount::LEN];
+        Pack::pack(rent_data, &mut data).unwrap();
+        let unpacked: RentShareAccount = Pack::unpack(&data).unwrap();
+        assert_eq!(unpacked.try_to_vec().unwrap(), serialized);
+
+        let mut short = vec![0; RentShareAccount::LEN - 1];
+        assert_eq!(
+            Pack::pack(active_agreement(), &mut short),
+            Err(ProgramError::InvalidAccountData)
+        );
+        assert_eq!(
+            <RentShareAccount as Pack>::unpack(&[0; RentShareAccount::LEN]).map(|_| ()),
+            Err(ProgramError::UninitializedAccount)
+        );
+    }
+
+    #[test]
+    fn first_payment_is_due_at_signing_unless_deferred() {
+        let mut rent_data = active_agreement();
+        assert_eq!(rent_data.next_due_timestamp(NOW), Ok(NOW));
+        assert_eq!(rent_data.is_before_first_payment(NOW), Ok(false));
+
+        rent_data.defer_first_payment = true;
+        let month_later = NOW + Duration::Months.seconds_for(1, NOW).unwrap();
+        assert_eq!(rent_data.next_due_timestamp(NOW), Ok(month_later));
+        assert_eq!(rent_data.is_before_first_payment(NOW), Ok(true));
+    }
+
+    const JAN_31_2023: i64 = 1_675_123_200;
+    const FEB_28_2023: i64 = 1_677_542_400;
+    const JAN_31_2024: i64 = 1_706_659_200;
+    const FEB_01_2024: i64 = 1_706_745_600;
+    const FEB_29_2024: i64 = 1_709_164_800;
+    const MAR_31_2024: i64 = 1_711_843_200;
+    const FEB_28_2025: i64 = 1_740_700_800;
+    const FEB_29_2028: i64 = 1_835_395_200;
+    const DAY: i64 = 24 * 60 * 60;
+
+    #[test]
+    fn seconds_for_clamps_to_the_end_of_shorter_months() {
+        let months = Duration::Months;
+        assert_eq!(
+            months.seconds_for(1, JAN_31_2023),
+            Ok(FEB_28_2023 - JAN_31_2023)
+        );
+        assert_eq!(
+            months.seconds_for(1, JAN_31_2024),
+            Ok(FEB_29_2024 - JAN_31_2024)
+        );
+        // Later periods stay anchored to the 31st rather than drifting to the 29th
+        assert_eq!(
+            months.seconds_for(2, JAN_31_2024),
+            Ok(MAR_31_2024 - JAN_31_2024)
+        );
+        // The time of day is kept
+        assert_eq!(
+            months.seconds_for(1, JAN_31_2024 + 3_600),
+            Ok(FEB_29_2024 - JAN_31_2024)
+        );
+    }
+
+    #[test]
+    fn seconds_for_years_crosses_leap_days() {
+        let years = Duration::Years;
+        assert_eq!(
+            years.seconds_for(1, FEB_29_2024),
+            Ok(FEB_28_2025 - FEB_29_2024)
+        );
+        assert_eq!(
+            years.seconds_for(4, FEB_29_2024),
+            Ok(FEB_29_2028 - FEB_29_2024)
+        );
+        assert_eq!(years.seconds_for(1, FEB_01_2024), Ok(366 * DAY));
+        assert_eq!(years.seconds_for(0, FEB_01_2024), Ok(0));
+    }
+
+    #[test]
+    fn prorated_refund_divides_by_the_current_calendar_period() {
+        let mut rent_data = active_agreement();
+        rent_data.created_at = FEB_01_2024;
+        rent_data.accepted_at = FEB_01_2024;
+        rent_data.rent_amount = 29_000;
+        rent_data.remaining_payments = 11;
+
+        // 15 of February 2024's 29 days are unused
+        assert_eq!(
+            rent_data.prorated_refund(FEB_01_2024 + 14 * DAY),
+            Ok(15_000)
+        );
+    }
+
+    /// Length of the first monthly period of `active_agreement`
+    fn first_period_seconds() -> i64 {
+        Duration::Months.seconds_for(1, NOW).unwrap()
+    }
+
+    #[test]
+    fn prorated_refund_at_the_start_middle_and_end_of_a_period() {
+        let mut rent_data = active_agreement();
+        rent_data.remaining_payments = 11;
+        let period = first_period_seconds();
+
+        assert_eq!(rent_data.prorated_refund(NOW), Ok(RENT));
+        assert_eq!(rent_data.prorated_refund(NOW + period / 2), Ok(RENT / 2));
+        assert_eq!(rent_data.prorated_refund(NOW + period), Ok(0));
+    }
+
+    #[test]
+    fn prorated_refund_includes_periods_paid_in_advance() {
+        let mut rent_data = active_agreement();
+        rent_data.remaining_payments = 10;
+
+        assert_eq!(
+            rent_data.prorated_refund(NOW + first_period_seconds() / 2),
+            Ok(RENT + RENT / 2)
+        );
+    }
+
+    #[test]
+    fn prorated_refund_rounds_to_the_unit_without_exceeding_rent_paid() {
+        let mut rent_data = active_agreement();
+        rent_data.remaining_payments = 11;
+        rent_data.proration_min_unit = 400_000;
+        let period = first_period_seconds();
+
+        assert_eq!(rent_data.prorated_refund(NOW + period / 2), Ok(400_000));
+        // A full period rounds up to 3 units, which is capped at the rent paid
+        assert_eq!(rent_data.prorated_refund(NOW), Ok(RENT));
+    }
+
+    #[test]
+    fn prorated_refund_is_zero_for_free_and_unpaid_periods() {
+        let mut rent_data = active_agreement();
+        assert_eq!(rent_data.prorated_refund(NOW), Ok(0));
+
+        rent_data.free_periods = 1;
+        rent_data.remaining_payments = 11;
+        assert_eq!(rent_data.prorated_refund(NOW), Ok(0));
+    }
+
+    #[test]
+    fn expected_payment_charges_a_first_period_accepted_mid_month_in_full() {
+        let mut rent_data = active_agreement();
+        rent_data.created_at = FEB_01_2024 + 14 * DAY;
+        rent_data.accepted_at = FEB_01_2024 + 14 * DAY;
+
+        assert_eq!(
+            rent_data.expected_payment_for_current_period(rent_data.accepted_at),
+            Ok(RENT)
+        );
+    }
+
+    #[test]
+    fn expected_payment_is_zero_for_a_free_period() {
+        let mut rent_data = active_agreement();
+        rent_data.free_periods = 1;
+        assert_eq!(rent_data.expected_payment_for_current_period(NOW), Ok(0));
+
+        rent_data.remaining_payments = 11;
+        assert_eq!(
+            rent_data.expected_payment_for_current_period(NOW + first_period_seconds()),
+            Ok(RENT)
+        );
+    }
+
+    #[test]
+    fn is_party_covers_each_role() {
+        let mut rent_data = active_agreement();
+        let admin = Pubkey::new_unique();
+        let lender = Pubkey::new_unique();
+        rent_data.admin_pubkey = admin;
+        rent_data.assigned_payee = lender;
+        rent_data.assigned_until_period = 1;
+
+        assert!(rent_data.is_party(&rent_data.payee_pubkey));
+        assert!(rent_data.is_party(&rent_data.payer_pubkey));
+        assert!(rent_data.is_party(&admin));
+        assert!(rent_data.is_party(&lender));
+        assert!(!rent_data.is_party(&Pubkey::new_unique()));
+
+        // The assigned payee stops being a party once the assignment ends
+        rent_data.remaining_payments = 11;
+        assert!(!rent_data.is_party(&lender));
+    }
+
+    #[test]
+    fn is_party_ignores_unset_roles() {
+        let rent_data = active_agreement();
+        assert!(!rent_data.is_party(&Pubkey::default()));
+    }
+
+    #[test]
+    fn require_active_returns_the_error_for_each_status() {
+        assert_eq!(
+            agreement_in(AgreementStatus::Active).require_active(),
+            Ok(())
+        );
+        for (status, error) in [
+            (
+                AgreementStatus::Uninitialized,
+                ProgramError::UninitializedAccount,
+            ),
+            (
+                AgreementStatus::PendingAcceptance,
+                RentShareError::AgreementNotAccepted.into(),
+            ),
+            (
+                AgreementStatus::Completed,
+                RentShareError::RentAlreadyPaidInFull.into(),
+            ),
+            (
+                AgreementStatus::AwaitingSettlement,
+                RentShareError::RentAlreadyPaidInFull.into(),
+            ),
+            (
+                AgreementStatus::Terminated,
+                RentShareError::RentAgreementTerminated.into(),
+            ),
+        ] {
+            let rent_data = agreement_in(status);
+            assert!(!rent_data.is_active(), "{:?}", status);
+            assert_eq!(rent_data.require_active(), Err(error), "{:?}", status);
+        }
+    }
+
+    #[test]
+    fn snapshot_matches_the_account_and_its_schedule() {
+        let mut rent_data = active_agreement();
+        rent_data.deposit = 2 * RENT;
+        rent_data.remaining_payments = 9;
+
+        assert_eq!(
+            rent_data.snapshot(NOW),
+            Ok(AgreementSnapshot {
+                status: AgreementStatus::Active,
+                payee_pubkey: rent_data.payee_pubkey,
+                payer_pubkey: rent_data.payer_pubkey,
+                deposit: 2 * RENT,
+                rent_amount: RENT,
+                duration: 12,
+                duration_unit: Duration::Months,
+                remaining_payments: 9,
+                payments_made: 3,
+                next_due: Some(months_after_acceptance(3)),
+                lease_end: Some(months_after_acceptance(12)),
+                created_at: NOW,
+                accepted_at: NOW,
+            })
+        );
+
+        let snapshot = agreement_in(AgreementStatus::Completed)
+            .snapshot(NOW)
+            .unwrap();
+        assert_eq!(snapshot.status, AgreementStatus::Completed);
+        assert_eq!(snapshot.payments_made, 12);
+        assert_eq!(snapshot.next_due, None);
+        assert_eq!(snapshot.lease_end, None);
+    }
+
+    #[test]
+    fn next_action_follows_the_status_and_schedule() {
+        let a_day_later = NOW + SECONDS_PER_DAY;
+        assert_eq!(
+            agreement_in(AgreementStatus::PendingAcceptance)
+                .next_action(a_day_later)
+                .unwrap(),
+            NextAction::PayeeMustAccept
+        );
+
+        let mut rent_data = active_agreement();
+        assert_eq!(
+            rent_data.next_action(a_day_later).unwrap(),
+            NextAction::PayerMustPay
+        );
+
+        rent_data.remaining_payments = 11;
+        assert_eq!(
+            rent_data.next_action(a_day_later).unwrap(),
+            NextAction::None
+        );
+
+        let overdue = NOW + Duration::Months.seconds_for(2, NOW).unwrap();
+        assert_eq!(
+            rent_data.next_action(overdue).unwrap(),
+            NextAction::PayerMustPay
+        );
+
+        rent_data.notice_period_seconds = 7 * SECONDS_PER_DAY as u64;
+        rent_data.notice_given_at = NOW;
+        assert_eq!(
+            rent_data.next_action(a_day_later).unwrap(),
+            NextAction::None
+        );
+        assert_eq!(
+            rent_data.next_action(NOW + 7 * SECONDS_PER_DAY).unwrap(),
+            NextAction::PayerMayTerminate
+        );
+
+        for status in [
+            AgreementStatus::Completed,
+            AgreementStatus::AwaitingSettlement,
+            AgreementStatus::Terminated,
+        ] {
+            assert_eq!(
+                agreement_in(status).next_action(overdue).unwrap(),
+                NextAction::Complete,
+                "{:?}",
+                status
+            );
+        }
+    }
+
+    #[test]
+    fn reminder_covers_the_lead_window_and_the_overdue_boundary() {
+        let mut rent_data = active_agreement();
+        rent_data.remaining_payments = 11;
+        rent_data.reminder_lead_seconds = 3 * SECONDS_PER_DAY as u64;
+        let due_at = NOW + Duration::Months.seconds_for(1, NOW).unwrap();
+        let window_start = due_at - 3 * SECONDS_PER_DAY;
+
+        assert_eq!(rent_data.reminder(window_start - 1).unwrap(), None);
+        for now in [window_start, due_at] {
+            assert_eq!(
+                rent_data.reminder(now).unwrap(),
+                Some(Reminder::Upcoming { due_at }),
+                "{}",
+                now
+            );
+        }
+        assert_eq!(
+            rent_data.reminder(due_at + 1).unwrap(),
+            Some(Reminder::Overdue { due_at })
+        );
+
+        rent_data.remaining_payments = 0;
+        assert_eq!(rent_data.reminder(due_at + 1).unwrap(), None);
+    }
+
+    #[test]
+    fn seconds_until_due_is_signed_around_the_due_date() {
+        let mut rent_data = active_agreement();
+        rent_data.remaining_payments = 11;
+        let due_at = NOW + Duration::Months.seconds_for(1, NOW).unwrap();
+
+        assert_eq!(
+            rent_data.seconds_until_due(due_at - 3 * SECONDS_PER_DAY),
+            Ok(3 * SECONDS_PER_DAY)
+        );
+        assert_eq!(rent_data.seconds_until_due(due_at), Ok(0));
+        assert_eq!(rent_data.seconds_until_due(due_at + 90), Ok(-90));
+    }
+
+    #[test]
+    fn payments_made_counts_paid_periods() {
+        let mut rent_data = active_agreement();
+        assert_eq!(rent_data.payments_made(), 0);
+
+        rent_data.remaining_payments = 7;
+        assert_eq!(rent_data.payments_made(), 5);
+
+        rent_data.remaining_payments = 0;
+        assert_eq!(rent_data.payments_made(), 12);
+    }
+
+    #[test]
+    fn schedule_rejects_a_created_at_in_the_future() {
+        let mut rent_data = active_agreement();
+        rent_data.created_at = NOW + MAX_CLOCK_SKEW_SECONDS + 1;
+        rent_data.accepted_at = rent_data.created_at;
+
+        assert_eq!(
+            rent_data.next_due_timestamp(NOW),
+            Err(RentShareError::InvalidTimestamp.into())
+        );
+        // Clock skew between validators is tolerated
+        assert!(rent_data.next_due_timestamp(NOW + 1).is_ok());
+    }
+
+    #[test]
+    fn schedule_rejects_an_implausibly_old_created_at() {
+        let mut rent_data = active_agreement();
+        let now = NOW + MAX_TIMESTAMP_AGE_SECONDS;
+        assert!(rent_data.next_due_timestamp(now).is_ok());
+
+        rent_data.created_at = NOW - 1;
+        assert_eq!(
+            rent_data.next_due_timestamp(now),
+            Err(RentShareError::InvalidTimestamp.into())
+        );
+    }
+
+    /// Timestamp `months` monthly periods after `active_agreement` was accepted
+    fn months_after_acceptance(months: u64) -> i64 {
+        NOW + Duration::Months.seconds_for(months, NOW).unwrap()
+    }
+
+    #[test]
+    fn expected_payment_uses_the_rent_after_a_step() {
+        let mut rent_data = active_agreement();
+        rent_data.remaining_payments = 11;
+        rent_data.rent_amount = 2 * RENT;
+        rent_data.last_rent_change_at = NOW + 1;
+
+        assert_eq!(
+            rent_data.expected_payment_for_current_period(months_after_acceptance(1)),
+            Ok(2 * RENT)
+        );
+    }
+
+    #[test]
+    fn health_score_is_high_for_a_lease_paid_on_time() {
+        let mut rent_data = active_agreement();
+        assert_eq!(rent_data.health_score(NOW), Ok(90));
+
+        rent_data.remaining_payments = 1;
+        rent_data.on_time_streak = 11;
+        assert_eq!(rent_data.health_score(months_after_acceptance(11)), Ok(100));
+
+        rent_data.remaining_payments = 0;
+        rent_data.status = AgreementStatus::Completed as u8;
+        assert_eq!(rent_data.health_score(months_after_acceptance(12)), Ok(100));
+    }
+
+    #[test]
+    fn health_score_is_low_for_a_heavily_overdue_lease() {
+        let rent_data = active_agreement();
+        assert_eq!(rent_data.health_score(months_after_acceptance(6)), Ok(10));
+    }
+
+    #[test]
+    fn health_score_penalizes_a_broken_streak() {
+        let mut rent_data = active_agreement();
+        rent_data.remaining_payments = 8;
+        rent_data.on_time_streak = 2;
+
+        // Half the payments were late, and two thirds of the lease is still owed
+        assert_eq!(rent_data.health_score(months_after_acceptance(3)), Ok(89));
+    }
+
+    #[test]
+    fn termination_penalty_is_a_share_of_the_remaining_rent_up_to_the_cap() {
+        let mut rent_data = active_agreement();
+        rent_data.remaining_payments = 10;
+        rent_data.termination_penalty_bps = 500;
+
+        // 5% of the 10 periods still owed
+        assert_eq!(rent_data.termination_penalty(), Ok(RENT / 2));
+
+        rent_data.termination_penalty_max = RENT;
+        assert_eq!(rent_data.termination_penalty(), Ok(RENT / 2));
+
+        rent_data.termination_penalty_max = RENT / 4;
+        assert_eq!(rent_data.termination_penalty(), Ok(RENT / 4));
+
+        rent_data.termination_penalty_bps = MAX_BASIS_POINTS + 1;
+        assert_eq!(
+            rent_data.termination_penalty(),
+            Err(RentShareError::InvalidAgreementTerms.into())
+        );
+    }
+
+    #[test]
+    fn payoff_charges_overdue_rent_and_the_capped_penalty() {
+        let mut rent_data = active_agreement();
+        rent_data.remaining_payments = 11;
+        rent_data.late_fee_bps = 500;
+        rent_data.termination_penalty_bps = 1_000;
+        rent_data.termination_penalty_max = RENT;
+
+        assert_eq!(
+            rent_data.payoff(months_after_acceptance(2)),
+            Ok(Payoff {
+                overdue_rent: RENT + RENT / 20,
+                prorated_credit: 0,
+                termination_penalty: RENT,
+            })
+        );
+    }
+
+    #[test]
+    fn payoff_credits_rent_paid_in_advance() {
+        let mut rent_data = active_agreement();
+        rent_data.remaining_payments = 10;
+        rent_data.termination_penalty_bps = 1_000;
+
+        assert_eq!(
+            rent_data.payoff(NOW + first_period_seconds() / 2),
+            Ok(Payoff {
+                overdue_rent: 0,
+                prorated_credit: RENT + RENT / 2,
+                termination_penalty: RENT,
+            })
+        );
+    }
+
+    /// Agreement three periods overdue with a 10% late fee escalating by half of it per period
+    fn overdue_agreement() -> (RentShareAccount, i64) {
+        let mut rent_data = active_agreement();
+        rent_data.late_fee_bps = 1_000;
+        rent_data.late_fee_escalation_bps = 5_000;
+        (rent_data, months_after_acceptance(3))
+    }
+
+    #[test]
+    fn late_fee_charges_a_tiny_rent_at_least_one_lamport() {
+        let mut rent_data = active_agreement();
+        rent_data.rent_amount = 1;
+        rent_data.late_fee_bps = 1;
+        assert_eq!(rent_data.late_fee(), Ok(1));
+
+        rent_data.late_fee_bps = 0;
+        assert_eq!(rent_data.late_fee(), Ok(0));
+    }
+
+    #[test]
+    fn late_fee_rejects_rates_above_100_percent() {
+        let mut rent_data = active_agreement();
+        rent_data.late_fee_bps = MAX_BASIS_POINTS;
+        assert_eq!(rent_data.late_fee(), Ok(RENT));
+
+        rent_data.late_fee_bps = MAX_BASIS_POINTS + 1;
+        assert_eq!(
+            rent_data.late_fee(),
+            Err(RentShareError::InvalidAgreementTerms.into())
+        );
+    }
+
+    #[test]
+    fn late_fees_escalate_for_each_additional_period_overdue() {
+        let (rent_data, now) = overdue_agreement();
+        assert_eq!(rent_data.escalated_late_fee(0), Ok(100_000));
+        assert_eq!(rent_data.escalated_late_fee(1), Ok(100_000));
+        assert_eq!(rent_data.escalated_late_fee(3), Ok(200_000));
+
+        // The oldest period is the most overdue
+        assert_eq!(rent_data.late_fees_due(1, now), Ok(200_000));
+        assert_eq!(rent_data.late_fees_due(3, now), Ok(450_000));
+        assert_eq!(rent_data.amount_due(3, now), Ok(3 * RENT + 450_000));
+    }
+
+    #[test]
+    fn late_fee_escalation_is_capped_per_period() {
+        let (mut rent_data, now) = overdue_agreement();
+        rent_data.late_fee_max = 180_000;
+
+        assert_eq!(rent_data.escalated_late_fee(3), Ok(180_000));
+        assert_eq!(rent_data.late_fees_due(3, now), Ok(430_000));
+    }
+
+    #[test]
+    fn late_fee_credit_reduces_the_late_fees_due() {
+        let (mut rent_data, now) = overdue_agreement();
+        rent_data.late_fee_credit = 120_000;
+
+        assert_eq!(rent_data.accrued_late_fees(now), Ok(330_000));
+        assert_eq!(rent_data.late_fee_credit_applied(1, now), Ok(120_000));
+        assert_eq!(rent_data.late_fees_due(1, now), Ok(80_000));
+
+        // Credit beyond the fees charged is kept for later periods
+        rent_data.late_fee_credit = 500_000;
+        assert_eq!(rent_data.late_fee_credit_applied(1, now), Ok(200_000));
+        assert_eq!(rent_data.late_fees_due(3, now), Ok(0));
+    }
+
+    #[cfg(feature = "client")]
+    #[test]
+    fn payment_schedule_lists_each_period_of_a_three_period_lease() {
+        let mut rent_data = active_agreement();
+        rent_data.duration = 3;
+        rent_data.remaining_payments = 3;
+
+        assert_eq!(
+            rent_data.payment_schedule(),
+            Ok(vec![
+                (NOW, RENT),
+                (months_after_acceptance(1), RENT),
+                (months_after_acceptance(2), RENT),
+            ])
+        );
+
+        // Deferring the first payment moves every due date to the end of its period
+        rent_data.defer_first_payment = true;
+        assert_eq!(
+            rent_data.payment_schedule(),
+            Ok(vec![
+                (months_after_acceptance(1), RENT),
+                (months_after_acceptance(2), RENT),
+                (months_after_acceptance(3), RENT),
+            ])
+        );
+
+        assert_eq!(
+            agreement_in(AgreementStatus::PendingAcceptance).payment_schedule(),
+            Err(RentShareError::AgreementNotAccepted.into())
+        );
+    }
+
+    #[cfg(feature = "client")]
+    #[test]
+    fn payment_schedule_charges_nothing_for_free_and_prepaid_periods() {
+        let mut rent_data = active_agreement();
+        rent_data.duration = 3;
+        rent_data.remaining_payments = 3;
+        let amounts = |rent_data: &RentShareAccount| {
+            rent_data
+                .payment_schedule()
+                .unwrap()
+                .into_iter()
+                .map(|(_, amount)| amount)
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(amounts(&rent_data), [RENT, RENT, RENT]);
+
+        rent_data.free_periods = 2;
+        assert_eq!(amounts(&rent_data), [0, 0, RENT]);
+
+        rent_data.free_periods = 0;
+        rent_data.prepay_first_period = true;
+        assert_eq!(amounts(&rent_data), [0, RENT, RENT]);
+        assert_eq!(
+            rent_data.payment_schedule().unwrap()[1].0,
+            months_after_acceptance(1)
+        );
+    }
+
+    #[test]
+    fn save_zeroes_bytes_past_len() {
+        let rent_data = active_agreement();
+        let mut data = vec![0xff; RentShareAccount::LEN + 16];
+
+        rent_data.save(&mut data).unwrap();
+        assert!(data[RentShareAccount::LEN..].iter().all(|byte| *byte == 0));
+        assert_eq!(
+            RentShareAccount::load(&data).unwrap().payee_pubkey,
+            rent_data.payee_pubkey
+        );
+    }
+
+    #[test]
+    fn assert_valid_state_rejects_inconsistent_agreements() {
+        for status in [
+            AgreementStatus::PendingAcceptance,
+            AgreementStatus::Active,
+            AgreementStatus::Completed,
+            AgreementStatus::AwaitingSettlement,
+            AgreementStatus::Terminated,
+        ] {
+            assert_eq!(
+                agreement_in(status).assert_valid_state(),
+                Ok(()),
+                "{:?}",
+                status
+            );
+        }
+
+        let mut remaining_past_duration = active_agreement();
+        remaining_past_duration.remaining_payments = 13;
+        let mut free_past_duration = active_agreement();
+        free_past_duration.free_periods = 13;
+        let mut refunded_but_held = agreement_in(AgreementStatus::Completed);
+        refunded_but_held.deposit = RENT;
+        refunded_but_held.deposit_refunded = true;
+        let mut paid_while_pending = agreement_in(AgreementStatus::PendingAcceptance);
+        paid_while_pending.total_rent_paid = RENT;
+        let mut pending_with_acceptance = agreement_in(AgreementStatus::PendingAcceptance);
+        pending_with_acceptance.accepted_at = NOW;
+        let mut active_with_nothing_left = active_agreement();
+        active_with_nothing_left.remaining_payments = 0;
+        let mut completed_with_payments_left = agreement_in(AgreementStatus::Completed);
+        completed_with_payments_left.remaining_payments = 1;
+        let mut terminated_with_payments_left = agreement_in(AgreementStatus::Terminated);
+        terminated_with_payments_left.remaining_payments = 1;
+
+        for rent_data in [
+            remaining_past_duration,
+            free_past_duration,
+            refunded_but_held,
+            paid_while_pending,
+            pending_with_acceptance,
+            active_with_nothing_left,
+            completed_with_payments_left,
+            terminated_with_payments_left,
+        ] {
+            assert_eq!(
+                rent_data.assert_valid_state(),
+                Err(ProgramError::InvalidAccountData)
+            );
+            assert_eq!(
+                rent_data.save(&mut [0; RentShareAccount::LEN]),
+                Err(ProgramError::InvalidAccountData)
+            );
+        }
+    }
+
+    #[test]
+    fn save_rejects_an_undersized_account_without_writing() {
+        let rent_data = active_agreement();
+        let mut data = vec![0xff; RentShareAccount::LEN - 1];
+
+        assert_eq!(
+            rent_data.save(&mut data),
+            Err(ProgramError::AccountDataTooSmall)
+        );
+        assert!(data.iter().all(|byte| *byte == 0xff));
+    }
+
+    #[cfg(feature = "client")]
+    #[test]
+    fn unpack_many_reads_back_pack_many() {
+        let first = active_agreement();
+        let mut second = active_agreement();
+        second.remaining_payments = 3;
+
+        let batch = RentShareAccount::pack_many(&[first, second]);
+        assert_eq!(batch.len(), 2 * (4 + RentShareAccount::LEN));
+
+        let unpacked = RentShareAccount::unpack_many(&batch).unwrap();
+        assert_eq!(unpacked.len(), 2);
+        assert_eq!(unpacked[1].remaining_payments, 3);
+        assert!(RentShareAccount::unpack_many(&[]).unwrap().is_empty());
+    }
+
+    #[cfg(feature = "client")]
+    #[test]
+    fn unpack_many_rejects_a_truncated_batch() {
+        let batch = RentShareAccount::pack_many(&[active_agreement()]);
+
+        for len in [2, 4, batch.len() - 1] {
+            assert_eq!(
+                RentShareAccount::unpack_many(&batch[..len]).map(|accounts| accounts.len()),
+                Err(ProgramError::InvalidAccountData)
+            );
+        }
+    }
+
+    #[test]
+    fn landlord_net_received_without_platform_fees() {
+        let mut rent_data = active_agreement();
+        rent_data.total_rent_paid = 6_000;
+        rent_data.total_late_fees_paid = 300;
+        rent_data.termination_penalty_paid = 700;
+        rent_data.deposit_interest_paid = 1_000;
+
+        assert_eq!(rent_data.landlord_net_received(), Ok(6_000));
+    }
+
+    #[test]
+    fn landlord_net_received_deducts_platform_fees() {
+        let mut rent_data = active_agreement();
+        rent_data.total_rent_paid = 6_000;
+        rent_data.total_late_fees_paid = 300;
+        rent_data.termination_penalty_paid = 700;
+        rent_data.deposit_interest_paid = 1_000;
+        rent_data.platform_fees_paid = 500;
+        assert_eq!(rent_data.landlord_net_received(), Ok(5_500));
+
+        rent_data.platform_fees_paid = 7_000;
+        assert_eq!(rent_data.landlord_net_received(), Ok(0));
+    }
 }